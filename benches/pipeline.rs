@@ -0,0 +1,69 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use sensor::{Measurement, Quantity};
+use weather_station::evapotranspiration::{self, Location};
+use weather_station::storage::{Reading, Storage};
+
+/// 2024-05-01, a day of one minute samples.
+const DAY: i64 = 19844;
+const SAMPLES_PER_DAY: i64 = 24 * 60;
+
+fn measurement() -> Measurement {
+    Measurement::new().with(Quantity::Temperature, 21.5).with(Quantity::RelativeHumidity, 40.0).with(Quantity::Pressure, 1013.2)
+}
+
+/// A day of readings following a sine, so the rollups see real extremes.
+fn day_of_readings() -> Vec<Reading> {
+    (0..SAMPLES_PER_DAY)
+        .map(|minute| {
+            let phase = (minute as f64 / SAMPLES_PER_DAY as f64 * std::f64::consts::TAU).sin();
+            let data = measurement()
+                .with(Quantity::Temperature, 15.0 + 8.0 * phase)
+                .with(Quantity::RelativeHumidity, 60.0 - 20.0 * phase)
+                .with(Quantity::Irradiance, (800.0 * phase).max(0.0))
+                .with(Quantity::LeafWetness, (60.0 - 50.0 * phase).clamp(0.0, 100.0));
+            Reading { timestamp: evapotranspiration::day_start(DAY) + minute * 60, ..Reading::new("outdoor", &Ok(data)) }
+        })
+        .collect()
+}
+
+/// A readout turned into a reading and the JSON the HTTP API and the event
+/// stream send.
+fn serialization_benchmark(c: &mut Criterion) {
+    let data = Ok(measurement());
+    c.bench_function("reading_to_json", |b| {
+        b.iter(|| serde_json::to_string(&Reading::new(black_box("outdoor"), black_box(&data))).unwrap())
+    });
+}
+
+/// A day of readings of one sensor into an empty in-memory database, one
+/// insert per reading as the sampling loop does.
+fn storage_benchmark(c: &mut Criterion) {
+    let readings = day_of_readings();
+    c.bench_function("storage_insert_day", |b| {
+        b.iter_batched(
+            || Storage::open(":memory:").unwrap(),
+            |storage| {
+                for reading in &readings {
+                    storage.insert_reading(reading).unwrap();
+                }
+                storage
+            },
+            BatchSize::PerIteration,
+        )
+    });
+}
+
+/// The daily values published after midnight, each from a day of readings.
+fn rollup_benchmark(c: &mut Criterion) {
+    let readings = day_of_readings();
+    let location = Location { latitude: 52.2, elevation_m: 100.0 };
+    c.bench_function("daily_radiation", |b| b.iter(|| evapotranspiration::daily_radiation(black_box(&readings), DAY).unwrap()));
+    c.bench_function("daily_wet_hours", |b| b.iter(|| evapotranspiration::daily_wet_hours(black_box(&readings), DAY, 50.0).unwrap()));
+    let radiation = evapotranspiration::daily_radiation(&readings, DAY);
+    c.bench_function("daily_et0", |b| {
+        b.iter(|| evapotranspiration::daily_et0(black_box(&readings), &location, DAY, radiation.as_ref()).unwrap())
+    });
+}
+
+criterion_group!(benches, serialization_benchmark, storage_benchmark, rollup_benchmark);
+criterion_main!(benches);
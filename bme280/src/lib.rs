@@ -0,0 +1,294 @@
+/// I2C address with SDO pulled low, 0x77 with SDO high.
+pub const BME280_DEFAULT_ADDRESS: u8 = 0x76;
+const BME280_CHIP_ID: u8 = 0x60;
+
+const REGISTER_CALIBRATION_TP: u8 = 0x88;
+const REGISTER_CHIP_ID: u8 = 0xD0;
+const REGISTER_CALIBRATION_H: u8 = 0xE1;
+const REGISTER_CTRL_HUM: u8 = 0xF2;
+const REGISTER_STATUS: u8 = 0xF3;
+const REGISTER_CTRL_MEAS: u8 = 0xF4;
+const REGISTER_DATA: u8 = 0xF7;
+
+/// Humidity oversampling x1.
+const CTRL_HUM_OVERSAMPLING_X1: u8 = 0b001;
+/// Temperature and pressure oversampling x1, forced mode: one measurement, then sleep.
+const CTRL_MEAS_FORCED_X1: u8 = (0b001 << 5) | (0b001 << 2) | 0b01;
+const STATUS_MEASURING: u8 = 0b1000;
+
+/// Maximum measurement time with x1 oversampling of all three values.
+const BME280_MEASUREMENT_TIME_US: u32 = 10 * 1000;
+const BME280_STATUS_POLL_US: u32 = 1000;
+const BME280_STATUS_POLLS: u32 = 10;
+
+pub trait Bme280Bus {
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), Bme280Error>;
+
+    /// Fills `buffer` with consecutive registers starting at `register`.
+    fn read_registers(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), Bme280Error>;
+}
+
+pub trait Bme280Timing {
+    fn wait(&self, microseconds: u32);
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Bme280Error {
+    /// The I2C transfer failed, usually nothing answers at the address.
+    Bus,
+    /// Something else answers at the address, e.g. a BMP280 (0x58).
+    UnexpectedChipId(u8),
+    Timeout,
+}
+
+/// Factory trimming parameters, different for every chip.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bme280Calibration {
+    t1: u16,
+    t2: i16,
+    t3: i16,
+    p1: u16,
+    p2: i16,
+    p3: i16,
+    p4: i16,
+    p5: i16,
+    p6: i16,
+    p7: i16,
+    p8: i16,
+    p9: i16,
+    h1: u8,
+    h2: i16,
+    h3: u8,
+    h4: i16,
+    h5: i16,
+    h6: i8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bme280Readout {
+    ///
+    /// # Unit
+    /// Celcius degrees.
+    pub temperature: f64,
+
+    ///
+    /// # Unit
+    /// Percents.
+    pub humidity: f64,
+
+    ///
+    /// # Unit
+    /// Hectopascals, at the station's altitude.
+    pub pressure: f64,
+}
+
+struct RawMeasurement {
+    temperature: i32,
+    pressure: i32,
+    humidity: i32,
+}
+
+impl Bme280Calibration {
+    /// # Parameters
+    /// tp = registers 0x88..=0xA1
+    /// h = registers 0xE1..=0xE7
+    fn from_registers(tp: &[u8; 26], h: &[u8; 7]) -> Self {
+        let unsigned = |index: usize| u16::from_le_bytes([tp[index], tp[index + 1]]);
+        let signed = |index: usize| i16::from_le_bytes([tp[index], tp[index + 1]]);
+        Bme280Calibration {
+            t1: unsigned(0),
+            t2: signed(2),
+            t3: signed(4),
+            p1: unsigned(6),
+            p2: signed(8),
+            p3: signed(10),
+            p4: signed(12),
+            p5: signed(14),
+            p6: signed(16),
+            p7: signed(18),
+            p8: signed(20),
+            p9: signed(22),
+            h1: tp[25],
+            h2: i16::from_le_bytes([h[0], h[1]]),
+            h3: h[2],
+            // 12 bit values sharing the nibbles of 0xE5.
+            h4: ((h[3] as i8 as i16) << 4) | (h[4] & 0x0F) as i16,
+            h5: ((h[5] as i8 as i16) << 4) | (h[4] >> 4) as i16,
+            h6: h[6] as i8,
+        }
+    }
+
+    /// Compensation formulas in double precision from section 8.1 of the datasheet.
+    fn compensate(&self, raw: &RawMeasurement) -> Bme280Readout {
+        let adc_t = raw.temperature as f64;
+        let var1 = (adc_t / 16384.0 - self.t1 as f64 / 1024.0) * self.t2 as f64;
+        let var2 = (adc_t / 131072.0 - self.t1 as f64 / 8192.0).powi(2) * self.t3 as f64;
+        let t_fine = var1 + var2;
+
+        Bme280Readout {
+            temperature: t_fine / 5120.0,
+            humidity: self.compensate_humidity(raw.humidity as f64, t_fine),
+            pressure: self.compensate_pressure(raw.pressure as f64, t_fine) / 100.0,
+        }
+    }
+
+    ///
+    /// # Returns
+    /// Pascals.
+    fn compensate_pressure(&self, adc_p: f64, t_fine: f64) -> f64 {
+        let mut var1 = t_fine / 2.0 - 64000.0;
+        let mut var2 = var1 * var1 * self.p6 as f64 / 32768.0;
+        var2 += var1 * self.p5 as f64 * 2.0;
+        var2 = var2 / 4.0 + self.p4 as f64 * 65536.0;
+        var1 = (self.p3 as f64 * var1 * var1 / 524288.0 + self.p2 as f64 * var1) / 524288.0;
+        var1 = (1.0 + var1 / 32768.0) * self.p1 as f64;
+        if var1 == 0.0 {
+            return 0.0;
+        }
+
+        let mut pressure = 1048576.0 - adc_p;
+        pressure = (pressure - var2 / 4096.0) * 6250.0 / var1;
+        let var1 = self.p9 as f64 * pressure * pressure / 2147483648.0;
+        let var2 = pressure * self.p8 as f64 / 32768.0;
+        pressure + (var1 + var2 + self.p7 as f64) / 16.0
+    }
+
+    fn compensate_humidity(&self, adc_h: f64, t_fine: f64) -> f64 {
+        let var = t_fine - 76800.0;
+        let var = (adc_h - (self.h4 as f64 * 64.0 + self.h5 as f64 / 16384.0 * var))
+            * (self.h2 as f64 / 65536.0 * (1.0 + self.h6 as f64 / 67108864.0 * var * (1.0 + self.h3 as f64 / 67108864.0 * var)));
+        let var = var * (1.0 - self.h1 as f64 * var / 524288.0);
+        var.clamp(0.0, 100.0)
+    }
+}
+
+/// Reads the chip id register, 0x60 on a BME280 and 0x58 on a BMP280.
+pub fn bme280_read_chip_id(bus: &mut dyn Bme280Bus) -> Result<u8, Bme280Error> {
+    let mut chip_id = [0u8];
+    bus.read_registers(REGISTER_CHIP_ID, &mut chip_id)?;
+    Ok(chip_id[0])
+}
+
+/// Verifies the chip id and reads the calibration, needed once per sensor
+/// before [`bme280_perform_readout`].
+pub fn bme280_read_calibration(bus: &mut dyn Bme280Bus) -> Result<Bme280Calibration, Bme280Error> {
+    let chip_id = bme280_read_chip_id(bus)?;
+    if chip_id != BME280_CHIP_ID {
+        return Err(Bme280Error::UnexpectedChipId(chip_id));
+    }
+
+    let mut tp = [0u8; 26];
+    let mut h = [0u8; 7];
+    bus.read_registers(REGISTER_CALIBRATION_TP, &mut tp)?;
+    bus.read_registers(REGISTER_CALIBRATION_H, &mut h)?;
+    Ok(Bme280Calibration::from_registers(&tp, &h))
+}
+
+/// Triggers a single forced-mode measurement and waits for its result. The
+/// sensor sleeps between readouts, so it does not heat itself up.
+pub fn bme280_perform_readout(
+    bus: &mut dyn Bme280Bus,
+    timing: &dyn Bme280Timing,
+    calibration: &Bme280Calibration,
+) -> Result<Bme280Readout, Bme280Error> {
+    // ctrl_hum only takes effect after a write to ctrl_meas.
+    bus.write_register(REGISTER_CTRL_HUM, CTRL_HUM_OVERSAMPLING_X1)?;
+    bus.write_register(REGISTER_CTRL_MEAS, CTRL_MEAS_FORCED_X1)?;
+    timing.wait(BME280_MEASUREMENT_TIME_US);
+
+    let mut status = [STATUS_MEASURING];
+    for _ in 0..BME280_STATUS_POLLS {
+        bus.read_registers(REGISTER_STATUS, &mut status)?;
+        if status[0] & STATUS_MEASURING == 0 {
+            break;
+        }
+        timing.wait(BME280_STATUS_POLL_US);
+    }
+    if status[0] & STATUS_MEASURING != 0 {
+        return Err(Bme280Error::Timeout);
+    }
+
+    let mut data = [0u8; 8];
+    bus.read_registers(REGISTER_DATA, &mut data)?;
+    let raw = RawMeasurement {
+        pressure: ((data[0] as i32) << 12) | ((data[1] as i32) << 4) | (data[2] as i32 >> 4),
+        temperature: ((data[3] as i32) << 12) | ((data[4] as i32) << 4) | (data[5] as i32 >> 4),
+        humidity: ((data[6] as i32) << 8) | data[7] as i32,
+    };
+    Ok(calibration.compensate(&raw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct RegisterBus {
+        registers: [u8; 256],
+    }
+
+    impl Bme280Bus for RegisterBus {
+        fn write_register(&mut self, register: u8, value: u8) -> Result<(), Bme280Error> {
+            self.registers[register as usize] = value;
+            Ok(())
+        }
+
+        fn read_registers(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), Bme280Error> {
+            let start = register as usize;
+            buffer.copy_from_slice(&self.registers[start..start + buffer.len()]);
+            Ok(())
+        }
+    }
+
+    struct NoWait;
+
+    impl Bme280Timing for NoWait {
+        fn wait(&self, _microseconds: u32) {}
+    }
+
+    /// Temperature and pressure trimming of the worked example in the BMP280
+    /// datasheet, which shares the BME280's compensation.
+    fn datasheet_bus() -> RegisterBus {
+        let mut registers = [0u8; 256];
+        registers[REGISTER_CHIP_ID as usize] = BME280_CHIP_ID;
+        let words: [i32; 12] = [27504, 26435, -1000, 36477, -10685, 3024, 2855, 140, -7, 15500, -14600, 6000];
+        for (index, word) in words.iter().enumerate() {
+            let bytes = (*word as u16).to_le_bytes();
+            registers[REGISTER_CALIBRATION_TP as usize + 2 * index] = bytes[0];
+            registers[REGISTER_CALIBRATION_TP as usize + 2 * index + 1] = bytes[1];
+        }
+        registers[0xA1] = 75;
+        registers[REGISTER_CALIBRATION_H as usize..REGISTER_CALIBRATION_H as usize + 7].copy_from_slice(&[0x6A, 0x01, 0x00, 0x14, 0x04, 0x03, 0x1E]);
+        // adc_P = 415148, adc_T = 519888, adc_H = 27000.
+        registers[REGISTER_DATA as usize..REGISTER_DATA as usize + 8].copy_from_slice(&[0x65, 0x5A, 0xC0, 0x7E, 0xED, 0x00, 0x69, 0x78]);
+        RegisterBus { registers }
+    }
+
+    #[test]
+    fn datasheet_example_is_compensated() {
+        let mut bus = datasheet_bus();
+        let calibration = bme280_read_calibration(&mut bus).unwrap();
+        assert_eq!((calibration.h4, calibration.h5), (324, 48));
+
+        let readout = bme280_perform_readout(&mut bus, &NoWait, &calibration).unwrap();
+        assert!((readout.temperature - 25.08).abs() < 0.01);
+        assert!((readout.pressure - 1006.53).abs() < 0.01);
+        assert!(readout.humidity > 0.0 && readout.humidity < 100.0);
+        assert_eq!(bus.registers[REGISTER_CTRL_MEAS as usize], CTRL_MEAS_FORCED_X1);
+    }
+
+    #[test]
+    fn other_chips_are_rejected() {
+        let mut bus = datasheet_bus();
+        bus.registers[REGISTER_CHIP_ID as usize] = 0x58;
+        assert_eq!(bme280_read_chip_id(&mut bus), Ok(0x58));
+        assert_eq!(bme280_read_calibration(&mut bus).err(), Some(Bme280Error::UnexpectedChipId(0x58)));
+    }
+
+    #[test]
+    fn stuck_measurement_times_out() {
+        let mut bus = datasheet_bus();
+        bus.registers[REGISTER_STATUS as usize] = STATUS_MEASURING;
+        let calibration = bme280_read_calibration(&mut bus).unwrap();
+        assert_eq!(bme280_perform_readout(&mut bus, &NoWait, &calibration), Err(Bme280Error::Timeout));
+    }
+}
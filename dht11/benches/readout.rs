@@ -0,0 +1,39 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use dht11::mock::{dht11_frame_waveform, MockCapture, MockPin, MockTiming};
+use dht11::{Dht11, Dht11Bus, Dht11SensorType};
+
+const FRAME: [u8; 5] = [48, 0, 23, 8, 79];
+
+/// Whole polled readout, against a simulated clock so it runs as fast as the
+/// polling and decoding allow instead of at sensor speed.
+fn readout_benchmark(c: &mut Criterion) {
+    let waveform = dht11_frame_waveform(FRAME);
+    let mut dht = Dht11::new(Dht11SensorType::Dht11);
+    c.bench_function("dht11_read", |b| {
+        b.iter_batched(
+            MockTiming::default,
+            |timing| {
+                let mut pin = MockPin::new(&timing, [&waveform]);
+                black_box(dht.read(Dht11Bus::Polling(&mut pin), &timing).unwrap())
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+/// Bit decoding alone, from captured edges.
+fn decode_benchmark(c: &mut Criterion) {
+    let waveform = dht11_frame_waveform(FRAME);
+    // Only retries wait, a single attempt never touches the clock.
+    let timing = MockTiming::default();
+    let mut dht = Dht11::new(Dht11SensorType::Dht11);
+    c.bench_function("dht11_read_edges", |b| {
+        b.iter(|| {
+            let mut capture = MockCapture::new([&waveform]);
+            black_box(dht.read(Dht11Bus::Edges(&mut capture), &timing).unwrap())
+        })
+    });
+}
+
+criterion_group!(benches, readout_benchmark, decode_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,146 @@
+use super::{wait_for_level, DHT11_STATE_CHANGE_TIMEOUT_US, Dht11Error, Dht11Pin, Dht11Timing};
+
+const DHT11_RESPONSE_DELAY_US: u32 = 30;
+const DHT11_RESPONSE_PULSE_US: u32 = 80;
+const DHT11_BIT_START_US: u32 = 50;
+const DHT11_BIT_ZERO_US: u32 = 27;
+const DHT11_BIT_ONE_US: u32 = 70;
+
+pub enum Dht11EmulatorFault {
+    None,
+    /// Sends a frame whose checksum does not match the data.
+    BadChecksum,
+    /// Detects the start signal but never answers, so the reader times out.
+    NoResponse,
+}
+
+pub struct Dht11Emulation {
+    ///
+    /// # Unit
+    /// Percents.
+    pub humidity: f64,
+
+    ///
+    /// # Unit
+    /// Celcius degrees.
+    pub temperature: f64,
+
+    pub fault: Dht11EmulatorFault,
+}
+
+/// Splits a value into the integral and decimal bytes of a DHT11 frame.
+fn encode_value(value: f64) -> (u8, u8) {
+    let tenths = (value.max(0.0) * 10.0).round() as u32;
+    ((tenths / 10).min(255) as u8, (tenths % 10) as u8)
+}
+
+/// Negative temperatures set the MSB of the decimal byte, like DHT11 v1.3 and DHT12 do.
+fn encode_temperature(temperature: f64) -> (u8, u8) {
+    let (integral, decimal) = encode_value(temperature.abs());
+    if temperature < 0.0 && (integral, decimal) != (0, 0) {
+        (integral, decimal | 0x80)
+    } else {
+        (integral, decimal)
+    }
+}
+
+fn encode_frame(emulation: &Dht11Emulation) -> [u8; 5] {
+    let (integral_rh, decimal_rh) = encode_value(emulation.humidity);
+    let (integral_t, decimal_t) = encode_temperature(emulation.temperature);
+    let mut checksum = integral_rh.wrapping_add(decimal_rh).wrapping_add(integral_t).wrapping_add(decimal_t);
+
+    if let Dht11EmulatorFault::BadChecksum = emulation.fault {
+        checksum = checksum.wrapping_add(1);
+    }
+
+    [integral_rh, decimal_rh, integral_t, decimal_t, checksum]
+}
+
+/// Sleeping is too coarse for the response pulses, so spin on the clock instead.
+fn busy_wait(timing: &dyn Dht11Timing, microseconds: u32) {
+    let end = timing.get_time_us() + microseconds as u128;
+    while timing.get_time_us() < end {}
+}
+
+fn send_pulse(pin: &mut dyn Dht11Pin, timing: &dyn Dht11Timing, low_us: u32, high_us: u32) {
+    pin.set_low();
+    busy_wait(timing, low_us);
+    pin.set_high();
+    busy_wait(timing, high_us);
+}
+
+/// Acts as a DHT11 sensor: waits for the start signal of a reader and answers it
+/// with a frame built from `emulation`.
+///
+/// # Returns
+/// `Dht11Error::Timeout` when no reader started a readout, callers usually retry.
+pub fn dht11_emulate_readout(
+    pin: &mut dyn Dht11Pin,
+    timing: &dyn Dht11Timing,
+    emulation: &Dht11Emulation,
+) -> Result<(), Dht11Error> {
+    pin.set_mode_input();
+    wait_for_level(false, pin, timing, DHT11_STATE_CHANGE_TIMEOUT_US)?;
+    wait_for_level(true, pin, timing, DHT11_STATE_CHANGE_TIMEOUT_US)?;
+
+    if let Dht11EmulatorFault::NoResponse = emulation.fault {
+        return Ok(());
+    }
+
+    pin.set_mode_output();
+    pin.set_high();
+    busy_wait(timing, DHT11_RESPONSE_DELAY_US);
+    send_pulse(pin, timing, DHT11_RESPONSE_PULSE_US, DHT11_RESPONSE_PULSE_US);
+
+    for byte in encode_frame(emulation) {
+        for bit in (0..8).rev() {
+            let high_us = if byte & (1 << bit) != 0 { DHT11_BIT_ONE_US } else { DHT11_BIT_ZERO_US };
+            send_pulse(pin, timing, DHT11_BIT_START_US, high_us);
+        }
+    }
+
+    pin.set_low();
+    busy_wait(timing, DHT11_BIT_START_US);
+    pin.set_high();
+    pin.set_mode_input();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dht11FixedReadout, Dht11RawData, Dht11Readout};
+
+    fn frame_to_bits(frame: &[u8; 5]) -> [bool; 40] {
+        let mut bits = [false; 40];
+        for (index, bit) in bits.iter_mut().enumerate() {
+            *bit = frame[index / 8] & (0x80 >> (index % 8)) != 0;
+        }
+        bits
+    }
+
+    #[test]
+    fn emulated_frame_decodes_to_requested_values() {
+        let frame = encode_frame(&Dht11Emulation { humidity: 40.0, temperature: 21.5, fault: Dht11EmulatorFault::None });
+        let raw_data = Dht11RawData::new(&frame_to_bits(&frame));
+        assert!(raw_data.is_checksum_correct());
+
+        let readout = Dht11Readout::from(Dht11FixedReadout::new(&raw_data));
+        assert_eq!(readout.humidity, 40.0);
+        assert_eq!(readout.temperature, 21.5);
+    }
+
+    #[test]
+    fn emulated_sub_zero_temperature() {
+        let frame = encode_frame(&Dht11Emulation { humidity: 80.0, temperature: -12.3, fault: Dht11EmulatorFault::None });
+        let raw_data = Dht11RawData::new(&frame_to_bits(&frame));
+        assert!(raw_data.is_checksum_correct());
+        assert_eq!(Dht11Readout::from(Dht11FixedReadout::new(&raw_data)).temperature, -12.3);
+    }
+
+    #[test]
+    fn bad_checksum_fault_corrupts_checksum() {
+        let frame = encode_frame(&Dht11Emulation { humidity: 40.0, temperature: 21.5, fault: Dht11EmulatorFault::BadChecksum });
+        assert!(!Dht11RawData::new(&frame_to_bits(&frame)).is_checksum_correct());
+    }
+}
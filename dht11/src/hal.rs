@@ -0,0 +1,218 @@
+//! Adapters plugging the driver into the embedded-hal 1.0 ecosystem.
+
+use core::cell::RefCell;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin};
+use super::{Dht11Pin, Dht11Timing};
+
+/// Wraps a pin configured as open-drain output with a pull-up, the usual way of
+/// wiring the data line on microcontrollers. Driving it high releases the line so
+/// it can be read back without switching modes.
+///
+/// Pin errors are not recoverable in the middle of a readout; a failed read is
+/// reported as low, which ends in a timeout or checksum error.
+pub struct EmbeddedHalPin<P> {
+    pin: P,
+}
+
+impl<P: InputPin + OutputPin> EmbeddedHalPin<P> {
+    pub fn new(pin: P) -> Self {
+        EmbeddedHalPin { pin }
+    }
+
+    pub fn release(self) -> P {
+        self.pin
+    }
+}
+
+impl<P: InputPin + OutputPin> Dht11Pin for EmbeddedHalPin<P> {
+    fn is_low(&mut self) -> bool {
+        self.pin.is_low().unwrap_or(true)
+    }
+
+    fn is_high(&mut self) -> bool {
+        self.pin.is_high().unwrap_or(false)
+    }
+
+    fn set_low(&mut self) {
+        let _ = self.pin.set_low();
+    }
+
+    fn set_high(&mut self) {
+        let _ = self.pin.set_high();
+    }
+
+    fn set_mode_input(&mut self) {
+        let _ = self.pin.set_high();
+    }
+
+    fn set_mode_output(&mut self) {}
+}
+
+/// Builds the time base out of a delay provider, used for waits, and
+/// `now_us`, a monotonic microsecond counter the bit lengths are measured
+/// with, e.g. a free running hardware timer or the DWT cycle counter divided
+/// down. Counting delays instead would miss the time spent polling the pin.
+///
+/// `now_us` must not wrap during a readout; a 32-bit counter at 1 MHz wraps
+/// every 71 minutes, so extend it to 64 bits.
+pub struct EmbeddedHalTiming<D, C> {
+    delay: RefCell<D>,
+    now_us: C,
+}
+
+impl<D: DelayNs, C: Fn() -> u64> EmbeddedHalTiming<D, C> {
+    pub fn new(delay: D, now_us: C) -> Self {
+        EmbeddedHalTiming { delay: RefCell::new(delay), now_us }
+    }
+
+    pub fn release(self) -> D {
+        self.delay.into_inner()
+    }
+}
+
+impl<D: DelayNs, C: Fn() -> u64> Dht11Timing for EmbeddedHalTiming<D, C> {
+    fn wait(&self, microseconds: u32) {
+        self.delay.borrow_mut().delay_us(microseconds);
+    }
+
+    fn get_time_us(&self) -> u128 {
+        (self.now_us)() as u128
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+    use core::convert::Infallible;
+    use embedded_hal::digital::{ErrorType, InputPin, OutputPin};
+    use crate::mock::{dht11_frame_waveform, MockPin, MockTiming};
+    use crate::{Dht11, Dht11Bus, Dht11SensorType};
+
+    struct FakePin {
+        level: bool,
+    }
+
+    impl ErrorType for FakePin {
+        type Error = Infallible;
+    }
+
+    impl InputPin for FakePin {
+        fn is_high(&mut self) -> Result<bool, Infallible> {
+            Ok(self.level)
+        }
+
+        fn is_low(&mut self) -> Result<bool, Infallible> {
+            Ok(!self.level)
+        }
+    }
+
+    impl OutputPin for FakePin {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.level = false;
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.level = true;
+            Ok(())
+        }
+    }
+
+    struct CountingDelay {
+        total_ns: u64,
+    }
+
+    impl DelayNs for CountingDelay {
+        fn delay_ns(&mut self, ns: u32) {
+            self.total_ns += ns as u64;
+        }
+    }
+
+    /// Open-drain data line of a scripted sensor: pulling it low starts a
+    /// readout, releasing it hands the line to the sensor.
+    struct OpenDrainLine<'a> {
+        pin: MockPin<'a, 1>,
+        pulled_low: bool,
+    }
+
+    impl ErrorType for OpenDrainLine<'_> {
+        type Error = Infallible;
+    }
+
+    impl InputPin for OpenDrainLine<'_> {
+        fn is_high(&mut self) -> Result<bool, Infallible> {
+            Ok(Dht11Pin::is_high(&mut self.pin))
+        }
+
+        fn is_low(&mut self) -> Result<bool, Infallible> {
+            Ok(Dht11Pin::is_low(&mut self.pin))
+        }
+    }
+
+    impl OutputPin for OpenDrainLine<'_> {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            if !self.pulled_low {
+                self.pin.set_mode_output();
+                Dht11Pin::set_low(&mut self.pin);
+                self.pulled_low = true;
+            }
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            if self.pulled_low {
+                Dht11Pin::set_high(&mut self.pin);
+                self.pin.set_mode_input();
+                self.pulled_low = false;
+            }
+            Ok(())
+        }
+    }
+
+    /// Waits on the simulated clock.
+    struct ClockDelay<'a> {
+        clock: &'a MockTiming,
+    }
+
+    impl DelayNs for ClockDelay<'_> {
+        fn delay_ns(&mut self, ns: u32) {
+            self.clock.wait(ns.div_ceil(1000));
+        }
+    }
+
+    #[test]
+    fn pin_is_released_high_when_switched_to_input() {
+        let mut pin = EmbeddedHalPin::new(FakePin { level: true });
+        pin.set_low();
+        assert!(pin.is_low());
+        pin.set_mode_input();
+        assert!(pin.is_high());
+    }
+
+    #[test]
+    fn time_comes_from_the_counter_and_waits_from_the_delay() {
+        let counter = Cell::new(1000);
+        let timing = EmbeddedHalTiming::new(CountingDelay { total_ns: 0 }, || counter.get());
+        timing.wait(20);
+        assert_eq!(timing.get_time_us(), 1000);
+        counter.set(1070);
+        assert_eq!(timing.get_time_us(), 1070);
+        assert_eq!(timing.release().total_ns, 20_000);
+    }
+
+    #[test]
+    fn polling_overhead_does_not_skew_bit_lengths() {
+        const FRAME: [u8; 5] = [48, 0, 23, 8, 79];
+        let waveform = dht11_frame_waveform(FRAME);
+        // Every query of the counter costs 15 µs of loop overhead, more than
+        // half of a 27 µs 0 bit.
+        let clock = MockTiming::new(15);
+        let timing = EmbeddedHalTiming::new(ClockDelay { clock: &clock }, || clock.get_time_us() as u64);
+        let mut pin = EmbeddedHalPin::new(OpenDrainLine { pin: MockPin::new(&clock, [&waveform]), pulled_low: false });
+
+        let readout = Dht11::new(Dht11SensorType::Dht11).read_fixed(Dht11Bus::Polling(&mut pin), &timing).unwrap();
+        assert_eq!((readout.humidity, readout.temperature), (480, 238));
+    }
+}
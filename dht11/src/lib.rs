@@ -1,6 +1,20 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+pub mod emulator;
+#[cfg(feature = "embedded-hal")]
+pub mod hal;
+pub mod mock;
+
 const DHT11_STARTING_TIME_US: u32 = 20 * 1000;
+const DHT22_STARTING_TIME_US: u32 = 1100;
 const DHT11_WAIT_FOR_START_US: u32 = 10;
 const DHT11_STATE_CHANGE_TIMEOUT_US: u32 = 1000 * 1000;
+const DHT11_BIT_THRESHOLD_US: u32 = 50;
+/// A DHT11 read more often than this answers with stale or corrupted data.
+pub const DHT11_MIN_SAMPLING_PERIOD_US: u32 = 1000 * 1000;
+/// A DHT22 read more often than this answers with stale or corrupted data.
+pub const DHT22_MIN_SAMPLING_PERIOD_US: u32 = 2000 * 1000;
 
 pub trait Dht11Pin {
     fn is_low(&mut self) -> bool;
@@ -19,16 +33,45 @@ pub trait Dht11Timing {
     fn get_time_us(&self) -> u128;
 }
 
+/// Enough for the sensor response and the 40 data bits, two edges each.
+pub const DHT11_MAX_EDGES: usize = 96;
+
+/// A line transition recorded by a `Dht11EdgeCapture`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Dht11Edge {
+    /// Whether the line went high.
+    pub rising: bool,
+
+    ///
+    /// # Unit
+    /// Microseconds, from any fixed origin.
+    pub time_us: u128,
+}
+
+/// Alternative to polling a `Dht11Pin`: the platform timestamps the line
+/// transitions itself, e.g. with GPIO interrupts, so a preempted reader no
+/// longer stretches or shortens the measured pulses.
+pub trait Dht11EdgeCapture {
+    /// Pulls the line low for `starting_time_us`, releases it and records the
+    /// transitions that follow until `edges` is full, the line went quiet after
+    /// the frame or `timeout_us` passed.
+    /// Edges before the data bits may be missed, only the data bits are decoded.
+    ///
+    /// # Returns
+    /// Number of edges recorded.
+    fn capture(&mut self, starting_time_us: u32, timeout_us: u32, edges: &mut [Dht11Edge]) -> usize;
+}
+
 
-fn wait_for_level(level: bool, pin: &mut dyn Dht11Pin, timing: &dyn Dht11Timing) -> Result<(), Dht11Error>{
-    let timeout = timing.get_time_us() + DHT11_STATE_CHANGE_TIMEOUT_US as u128;
+fn wait_for_level(level: bool, pin: &mut dyn Dht11Pin, timing: &dyn Dht11Timing, timeout_us: u32) -> Result<(), Dht11Error>{
+    let timeout = timing.get_time_us() + timeout_us as u128;
     loop {
         if level {
-            if pin.is_high() == true {
+            if pin.is_high() {
                 return Ok(());
             }
         } else {
-            if pin.is_low() == true {
+            if pin.is_low() {
                 return Ok(());
             }
         }
@@ -109,12 +152,169 @@ impl Dht11RawData {
     }
 }
 
+/// Sensors speaking the DHT single-wire protocol. They share the framing but
+/// differ in start signal length and in how the data bytes are encoded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dht11SensorType {
+    Dht11,
+    /// Also sold as AM2302.
+    Dht22,
+}
+
+impl Dht11SensorType {
+    const fn starting_time_us(&self) -> u32 {
+        match self {
+            Dht11SensorType::Dht11 => DHT11_STARTING_TIME_US,
+            Dht11SensorType::Dht22 => DHT22_STARTING_TIME_US,
+        }
+    }
+
+    /// Time the sensor needs between two readouts to answer with fresh data.
+    pub const fn min_sampling_period_us(&self) -> u32 {
+        match self {
+            Dht11SensorType::Dht11 => DHT11_MIN_SAMPLING_PERIOD_US,
+            Dht11SensorType::Dht22 => DHT22_MIN_SAMPLING_PERIOD_US,
+        }
+    }
+
+    fn decode(&self, data: &Dht11RawData) -> Dht11FixedReadout {
+        match self {
+            Dht11SensorType::Dht11 => Dht11FixedReadout::new(data),
+            Dht11SensorType::Dht22 => Dht11FixedReadout::from_dht22(data),
+        }
+    }
+}
+
+/// Protocol timings of a readout. The defaults follow the datasheets, platforms
+/// whose reads of the pin lag or whose timer is coarse may need to shift them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dht11Config {
+    pub sensor_type: Dht11SensorType,
+
+    /// How long the start signal pulls the line low.
+    ///
+    /// # Unit
+    /// Microseconds.
+    pub start_time_us: u32,
+
+    /// How long the line may stay at one level before the readout times out.
+    ///
+    /// # Unit
+    /// Microseconds.
+    pub timeout_us: u32,
+
+    /// High pulses at least this long are decoded as a 1. Nominally 26-28 µs
+    /// for a 0 and 70 µs for a 1.
+    ///
+    /// # Unit
+    /// Microseconds.
+    pub bit_threshold_us: u32,
+}
+
+impl Dht11Config {
+    pub const fn new(sensor_type: Dht11SensorType) -> Self {
+        Dht11Config {
+            sensor_type,
+            start_time_us: sensor_type.starting_time_us(),
+            timeout_us: DHT11_STATE_CHANGE_TIMEOUT_US,
+            bit_threshold_us: DHT11_BIT_THRESHOLD_US,
+        }
+    }
+
+    const fn convert_time_to_bit(&self, time_us: u32) -> bool {
+        time_us >= self.bit_threshold_us
+    }
+}
+
+impl Default for Dht11Config {
+    fn default() -> Self {
+        Dht11Config::new(Dht11SensorType::Dht11)
+    }
+}
+
+impl From<Dht11SensorType> for Dht11Config {
+    fn from(sensor_type: Dht11SensorType) -> Self {
+        Dht11Config::new(sensor_type)
+    }
+}
+
 #[derive(Debug)]
 pub enum Dht11Error {
     Timeout,
     ChecksumError,
 }
 
+/// How failed readouts are retried. The delay is never shorter than the
+/// sensor's minimum sampling period, whatever is configured.
+#[derive(Debug, Clone, Copy)]
+pub struct Dht11RetryPolicy {
+    pub max_attempts: u32,
+    pub retry_delay_us: u32,
+}
+
+impl Default for Dht11RetryPolicy {
+    fn default() -> Self {
+        Dht11RetryPolicy{ max_attempts: 3, retry_delay_us: DHT22_MIN_SAMPLING_PERIOD_US }
+    }
+}
+
+/// Counts of individual readout attempts, for monitoring wiring and timing quality.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Dht11Statistics {
+    pub attempts: u64,
+    pub timeouts: u64,
+    pub checksum_errors: u64,
+}
+
+impl Dht11Statistics {
+    pub fn record<T>(&mut self, result: &Result<T, Dht11Error>) {
+        self.attempts += 1;
+        match result {
+            Ok(_) => {}
+            Err(Dht11Error::Timeout) => self.timeouts += 1,
+            Err(Dht11Error::ChecksumError) => self.checksum_errors += 1,
+        }
+    }
+}
+
+/// Readout in tenths, for targets where floating point is unavailable or expensive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dht11FixedReadout {
+    ///
+    /// # Unit
+    /// Tenths of percents.
+    pub humidity: u16,
+
+    ///
+    /// # Unit
+    /// Tenths of Celcius degrees.
+    pub temperature: i16,
+}
+
+impl Dht11FixedReadout {
+    /// DHT11 (datasheet v1.3 and later) and DHT12 frames flag sub-zero
+    /// temperatures with the MSB of the temperature decimal byte.
+    const fn new(data: &Dht11RawData) -> Self {
+        let temperature = data.integral_t_data as i16 * 10 + (data.decimal_t_data & 0x7F) as i16;
+
+        Dht11FixedReadout{
+            humidity: data.integral_rh_data as u16 * 10 + data.decimal_rh_data as u16,
+            temperature: if data.decimal_t_data & 0x80 != 0 { -temperature } else { temperature }
+        }
+    }
+
+    /// DHT22 sends both values as 16-bit tenths, temperature with a sign bit in the MSB.
+    const fn from_dht22(data: &Dht11RawData) -> Self {
+        let humidity = u16::from_be_bytes([data.integral_rh_data, data.decimal_rh_data]);
+        let temperature = u16::from_be_bytes([data.integral_t_data & 0x7F, data.decimal_t_data]) as i16;
+
+        Dht11FixedReadout{
+            humidity,
+            temperature: if data.integral_t_data & 0x80 != 0 { -temperature } else { temperature }
+        }
+    }
+}
+
 pub struct Dht11Readout {
     ///
     /// # Unit
@@ -127,66 +327,211 @@ pub struct Dht11Readout {
     pub temperature: f64,
 }
 
-impl Dht11Readout {
-    fn new(data: &Dht11RawData) -> Self {
+impl From<Dht11FixedReadout> for Dht11Readout {
+    fn from(readout: Dht11FixedReadout) -> Self {
         Dht11Readout{
-            humidity: data.integral_rh_data as f64 + data.decimal_rh_data as f64 / 10.0,
-            temperature: data.integral_t_data as f64 + data.decimal_t_data as f64 / 10.0
+            humidity: readout.humidity as f64 / 10.0,
+            temperature: readout.temperature as f64 / 10.0
         }
     }
 }
 
-///
-/// # Parameters
-/// time = microseconds
-const fn convert_time_to_bit(time: u128) -> bool {
-    assert!(time < 1000000);
-    if time < 50 {
-        return false;
-    } else {
-        return true;
-    }
-}
-
-fn dht11_init_readout(pin: &mut dyn Dht11Pin, timing: &dyn Dht11Timing) -> Result<(), Dht11Error>{
+fn dht11_init_readout(config: &Dht11Config, pin: &mut dyn Dht11Pin, timing: &dyn Dht11Timing) -> Result<(), Dht11Error>{
     pin.set_mode_output();
     pin.set_high();
     pin.set_low();
-    timing.wait(DHT11_STARTING_TIME_US);
+    timing.wait(config.start_time_us);
     pin.set_high();
     timing.wait(DHT11_WAIT_FOR_START_US);
 
     pin.set_mode_input();
-    wait_for_level(false, pin, timing)?;
-    wait_for_level(true, pin, timing)?;
-    wait_for_level(false, pin, timing)?;
+    wait_for_level(false, pin, timing, config.timeout_us)?;
+    wait_for_level(true, pin, timing, config.timeout_us)?;
+    wait_for_level(false, pin, timing, config.timeout_us)?;
     Ok(())
 }
 
-fn dht11_read_bit(pin: &mut dyn Dht11Pin, timing: &dyn Dht11Timing) -> Result<bool, Dht11Error> {
-    wait_for_level(true, pin, timing)?;
+///
+/// # Returns
+/// Duration of the high pulse in microseconds, a 1 when at least `Dht11Config::bit_threshold_us`.
+fn dht11_read_pulse(config: &Dht11Config, pin: &mut dyn Dht11Pin, timing: &dyn Dht11Timing) -> Result<u32, Dht11Error> {
+    wait_for_level(true, pin, timing, config.timeout_us)?;
     let start_time: u128 = timing.get_time_us();
-    wait_for_level(false, pin, timing)?;
+    wait_for_level(false, pin, timing, config.timeout_us)?;
     let elapsed_time = timing.get_time_us() - start_time;
-    Ok(convert_time_to_bit(elapsed_time))
+    Ok(elapsed_time.min(u32::MAX as u128) as u32)
 }
 
-pub fn dht11_perform_readout(pin: &mut dyn Dht11Pin, timing: &dyn Dht11Timing) -> Result<Dht11Readout, Dht11Error> {
-    dht11_init_readout(pin, timing)?;
+/// Pulse timings of one readout and what they decode to, for diagnosing
+/// flaky sensors and tuning the bit threshold.
+#[derive(Debug)]
+pub struct Dht11RawReadout {
+    ///
+    /// # Unit
+    /// Microseconds, the high pulse of every data bit in transmission order.
+    pub pulse_durations_us: [u32; 40],
+
+    pub bits: [bool; 40],
 
-    let mut bits: [bool; 40] = [false; 40];
+    /// Decoded bits, or the checksum error.
+    pub readout: Result<Dht11FixedReadout, Dht11Error>,
+}
 
-    for bit in bits.iter_mut() {
-        *bit = dht11_read_bit(pin, timing)?;
+impl Dht11RawReadout {
+    fn decode(config: &Dht11Config, pulse_durations_us: [u32; 40]) -> Self {
+        let mut bits: [bool; 40] = [false; 40];
+        for (bit, duration) in bits.iter_mut().zip(pulse_durations_us) {
+            *bit = config.convert_time_to_bit(duration);
+        }
+        Dht11RawReadout { pulse_durations_us, bits, readout: dht_decode_bits(config.sensor_type, &bits) }
     }
+}
 
-    let raw_data = Dht11RawData::new(&bits);
+fn dht_decode_bits(sensor_type: Dht11SensorType, bits: &[bool; 40]) -> Result<Dht11FixedReadout, Dht11Error> {
+    let raw_data = Dht11RawData::new(bits);
 
-    if raw_data.is_checksum_correct() == false {
+    if !raw_data.is_checksum_correct() {
         return Err(Dht11Error::ChecksumError);
     }
 
-    return Ok(Dht11Readout::new(&raw_data));
+    Ok(sensor_type.decode(&raw_data))
+}
+
+///
+/// # Returns
+/// The pulse timings whenever all 40 bits arrived, checksum errors are only
+/// reported in `Dht11RawReadout::readout`.
+fn dht_polled_raw_readout(config: &Dht11Config, pin: &mut dyn Dht11Pin, timing: &dyn Dht11Timing) -> Result<Dht11RawReadout, Dht11Error> {
+    dht11_init_readout(config, pin, timing)?;
+
+    let mut pulse_durations_us: [u32; 40] = [0; 40];
+
+    for duration in pulse_durations_us.iter_mut() {
+        *duration = dht11_read_pulse(config, pin, timing)?;
+    }
+
+    Ok(Dht11RawReadout::decode(config, pulse_durations_us))
+}
+
+/// Durations of the last 40 high pulses, the data bits. Earlier ones belong to
+/// the sensor response, and the line is released high after the last bit.
+fn dht_edges_to_pulses(edges: &[Dht11Edge]) -> Result<[u32; 40], Dht11Error> {
+    let mut pulse_durations_us: [u32; 40] = [0; 40];
+    let mut pulses = edges.windows(2).rev().filter(|pair| pair[0].rising && !pair[1].rising);
+
+    for duration in pulse_durations_us.iter_mut().rev() {
+        let pair = pulses.next().ok_or(Dht11Error::Timeout)?;
+        *duration = pair[1].time_us.saturating_sub(pair[0].time_us).min(u32::MAX as u128) as u32;
+    }
+    Ok(pulse_durations_us)
+}
+
+/// Same as `dht_polled_raw_readout`, with the pulses timed by `capture`.
+fn dht_edge_raw_readout(config: &Dht11Config, capture: &mut dyn Dht11EdgeCapture) -> Result<Dht11RawReadout, Dht11Error> {
+    let mut edges = [Dht11Edge::default(); DHT11_MAX_EDGES];
+    let count = capture.capture(config.start_time_us, config.timeout_us, &mut edges);
+    Ok(Dht11RawReadout::decode(config, dht_edges_to_pulses(&edges[..count.min(DHT11_MAX_EDGES)])?))
+}
+
+/// How the pulses of a readout are timed.
+pub enum Dht11Bus<'a> {
+    /// By busy polling the pin.
+    Polling(&'a mut dyn Dht11Pin),
+    /// From the edges the capture timestamps, immune to scheduler preemption.
+    Edges(&'a mut dyn Dht11EdgeCapture),
+}
+
+impl Dht11Bus<'_> {
+    fn raw_readout(&mut self, config: &Dht11Config, timing: &dyn Dht11Timing) -> Result<Dht11RawReadout, Dht11Error> {
+        match self {
+            Dht11Bus::Polling(pin) => dht_polled_raw_readout(config, *pin, timing),
+            Dht11Bus::Edges(capture) => dht_edge_raw_readout(config, *capture),
+        }
+    }
+}
+
+/// Reads a sensor with the configured protocol timings, retrying failed
+/// readouts per the retry policy and counting every attempt. The readout is
+/// in floating point (`read`), tenths (`read_fixed`) or pulse timings (`read_raw`),
+/// from either `Dht11Bus`.
+#[derive(Debug, Clone, Copy)]
+pub struct Dht11 {
+    config: Dht11Config,
+    retry_policy: Dht11RetryPolicy,
+    statistics: Dht11Statistics,
+}
+
+impl Dht11 {
+    /// Takes a `Dht11Config`, or just a `Dht11SensorType` for the datasheet
+    /// defaults. Readouts are attempted once until `with_retries`.
+    pub fn new(config: impl Into<Dht11Config>) -> Self {
+        Dht11 {
+            config: config.into(),
+            retry_policy: Dht11RetryPolicy { max_attempts: 1, ..Dht11RetryPolicy::default() },
+            statistics: Dht11Statistics::default(),
+        }
+    }
+
+    pub fn with_retries(self, retry_policy: Dht11RetryPolicy) -> Self {
+        Dht11 { retry_policy, ..self }
+    }
+
+    pub fn config(&self) -> &Dht11Config {
+        &self.config
+    }
+
+    /// Every attempt since the reader was created.
+    pub fn statistics(&self) -> Dht11Statistics {
+        self.statistics
+    }
+
+    pub fn read(&mut self, bus: Dht11Bus<'_>, timing: &dyn Dht11Timing) -> Result<Dht11Readout, Dht11Error> {
+        self.read_fixed(bus, timing).map(Dht11Readout::from)
+    }
+
+    ///
+    /// # Returns
+    /// First successful readout, or the error of the last attempt once all attempts failed.
+    pub fn read_fixed(&mut self, mut bus: Dht11Bus<'_>, timing: &dyn Dht11Timing) -> Result<Dht11FixedReadout, Dht11Error> {
+        let config = self.config;
+        dht_retry_readout(config, timing, &self.retry_policy, &mut self.statistics, || bus.raw_readout(&config, timing)?.readout)
+    }
+
+    /// A single attempt, never retried, for diagnosing flaky sensors.
+    ///
+    /// # Returns
+    /// The pulse timings whenever all 40 bits arrived, checksum errors are only
+    /// reported in `Dht11RawReadout::readout`.
+    pub fn read_raw(&mut self, mut bus: Dht11Bus<'_>, timing: &dyn Dht11Timing) -> Result<Dht11RawReadout, Dht11Error> {
+        let result = bus.raw_readout(&self.config, timing);
+        match &result {
+            Ok(raw) => self.statistics.record(&raw.readout),
+            Err(_) => self.statistics.record(&result),
+        }
+        result
+    }
+}
+
+fn dht_retry_readout(config: Dht11Config, timing: &dyn Dht11Timing, policy: &Dht11RetryPolicy, statistics: &mut Dht11Statistics, mut readout: impl FnMut() -> Result<Dht11FixedReadout, Dht11Error>) -> Result<Dht11FixedReadout, Dht11Error> {
+    let retry_delay_us = policy.retry_delay_us.max(config.sensor_type.min_sampling_period_us());
+    let mut attempt = 1;
+
+    loop {
+        let result = readout();
+        statistics.record(&result);
+        match result {
+            Ok(readout) => return Ok(readout),
+            Err(error) if attempt >= policy.max_attempts => return Err(error),
+            Err(_) => {}
+        }
+        attempt += 1;
+        timing.wait(retry_delay_us);
+    }
+}
+
+#[deprecated(note = "use `Dht11::read`")]
+pub fn dht11_perform_readout(pin: &mut dyn Dht11Pin, timing: &dyn Dht11Timing) -> Result<Dht11Readout, Dht11Error> {
+    Dht11::new(Dht11SensorType::Dht11).read(Dht11Bus::Polling(pin), timing)
 }
 
 #[cfg(test)]
@@ -202,14 +547,189 @@ mod tests {
 
     #[test]
     fn conversion_to_readout() {
-        let readout = Dht11Readout::new(&Dht11RawData { 
+        let readout = Dht11Readout::from(Dht11FixedReadout::new(&Dht11RawData { 
             integral_rh_data: 48, 
             decimal_rh_data: 0, 
             integral_t_data: 23, 
             decimal_t_data: 8, 
-            checksum: 0 });
+            checksum: 0 }));
         
         assert_eq!(readout.humidity, 48.0);
         assert_eq!(readout.temperature, 23.8);
     }
+
+    #[test]
+    fn dht11_negative_temperature() {
+        let readout = Dht11SensorType::Dht11.decode(&Dht11RawData {
+            integral_rh_data: 85,
+            decimal_rh_data: 0,
+            integral_t_data: 3,
+            decimal_t_data: 0x80 | 4,
+            checksum: 0 });
+
+        assert_eq!(readout, Dht11FixedReadout { humidity: 850, temperature: -34 });
+        assert_eq!(Dht11Readout::from(readout).temperature, -3.4);
+    }
+
+    #[test]
+    fn dht11_negative_temperature_below_one_degree() {
+        let readout = Dht11SensorType::Dht11.decode(&Dht11RawData {
+            integral_rh_data: 90,
+            decimal_rh_data: 0,
+            integral_t_data: 0,
+            decimal_t_data: 0x80 | 5,
+            checksum: 0 });
+
+        assert_eq!(readout.temperature, -5);
+    }
+
+    #[test]
+    fn dht22_conversion_to_readout() {
+        let readout: Dht11Readout = Dht11SensorType::Dht22.decode(&Dht11RawData {
+            integral_rh_data: 0x02,
+            decimal_rh_data: 0x8C,
+            integral_t_data: 0x01,
+            decimal_t_data: 0x5F,
+            checksum: 0 }).into();
+
+        assert_eq!(readout.humidity, 65.2);
+        assert_eq!(readout.temperature, 35.1);
+    }
+
+    #[test]
+    fn dht22_negative_temperature() {
+        let readout = Dht11SensorType::Dht22.decode(&Dht11RawData {
+            integral_rh_data: 0x02,
+            decimal_rh_data: 0x8C,
+            integral_t_data: 0x80,
+            decimal_t_data: 0x65,
+            checksum: 0 });
+
+        assert_eq!(readout, Dht11FixedReadout { humidity: 652, temperature: -101 });
+    }
+
+    /// Line stuck high: every readout times out while waiting for the sensor response.
+    struct StuckPin {
+        readouts_started: u32,
+    }
+
+    impl Dht11Pin for StuckPin {
+        fn is_low(&mut self) -> bool { false }
+        fn is_high(&mut self) -> bool { true }
+        fn set_low(&mut self) {}
+        fn set_high(&mut self) {}
+        fn set_mode_input(&mut self) {}
+        fn set_mode_output(&mut self) { self.readouts_started += 1; }
+    }
+
+    struct FastTiming {
+        now: core::cell::Cell<u128>,
+        longest_wait: core::cell::Cell<u32>,
+    }
+
+    impl Dht11Timing for FastTiming {
+        fn wait(&self, microseconds: u32) {
+            self.now.set(self.now.get() + microseconds as u128);
+            self.longest_wait.set(self.longest_wait.get().max(microseconds));
+        }
+
+        fn get_time_us(&self) -> u128 {
+            self.now.set(self.now.get() + 1000);
+            self.now.get()
+        }
+    }
+
+    #[test]
+    fn retries_respect_attempts_and_cool_down() {
+        let mut pin = StuckPin { readouts_started: 0 };
+        let timing = FastTiming { now: Default::default(), longest_wait: Default::default() };
+        let policy = Dht11RetryPolicy { max_attempts: 4, retry_delay_us: 10 };
+
+        let mut dht = Dht11::new(Dht11SensorType::Dht22).with_retries(policy);
+
+        let result = dht.read(Dht11Bus::Polling(&mut pin), &timing);
+
+        assert!(matches!(result, Err(Dht11Error::Timeout)));
+        assert_eq!(pin.readouts_started, 4);
+        assert_eq!(dht.statistics(), Dht11Statistics { attempts: 4, timeouts: 4, checksum_errors: 0 });
+        assert_eq!(timing.longest_wait.get(), DHT22_MIN_SAMPLING_PERIOD_US);
+
+        // Raw readouts are diagnostics, never retried.
+        assert!(matches!(dht.read_raw(Dht11Bus::Polling(&mut pin), &timing), Err(Dht11Error::Timeout)));
+        assert_eq!(pin.readouts_started, 5);
+        assert_eq!(dht.statistics().attempts, 5);
+    }
+
+    /// Replays the edges of a frame, preceded by the sensor response.
+    struct FrameCapture {
+        bytes: [u8; 5],
+        skip_response: bool,
+    }
+
+    impl Dht11EdgeCapture for FrameCapture {
+        fn capture(&mut self, _starting_time_us: u32, _timeout_us: u32, edges: &mut [Dht11Edge]) -> usize {
+            let mut count = 0;
+            let mut time_us = 1000;
+            let mut push = |rising, duration_us| {
+                edges[count] = Dht11Edge { rising, time_us };
+                count += 1;
+                time_us += duration_us;
+            };
+            if !self.skip_response {
+                push(false, 80);
+                push(true, 80);
+            }
+            for byte in self.bytes {
+                for bit in (0..8).rev() {
+                    push(false, 50);
+                    push(true, if byte >> bit & 1 == 1 { 70 } else { 27 });
+                }
+            }
+            push(false, 50);
+            push(true, 0);
+            count
+        }
+    }
+
+    #[test]
+    fn edge_readout_decodes_data_bits() {
+        let mut capture = FrameCapture { bytes: [48, 0, 23, 8, 79], skip_response: false };
+        let timing = FastTiming { now: Default::default(), longest_wait: Default::default() };
+        let mut dht = Dht11::new(Dht11SensorType::Dht11);
+        let readout = dht.read(Dht11Bus::Edges(&mut capture), &timing).unwrap();
+        assert_eq!((readout.humidity, readout.temperature), (48.0, 23.8));
+
+        capture.skip_response = true;
+        assert!(dht.read(Dht11Bus::Edges(&mut capture), &timing).is_ok());
+        capture.bytes[4] = 80;
+        assert!(matches!(dht.read(Dht11Bus::Edges(&mut capture), &timing), Err(Dht11Error::ChecksumError)));
+
+        // Raw readouts keep the timings of frames that fail the checksum.
+        let raw = dht.read_raw(Dht11Bus::Edges(&mut capture), &timing).unwrap();
+        assert_eq!(raw.pulse_durations_us[..4], [27, 27, 70, 70]);
+        assert_eq!(raw.bits[..4], [false, false, true, true]);
+        assert!(matches!(raw.readout, Err(Dht11Error::ChecksumError)));
+    }
+
+    #[test]
+    fn bit_threshold_is_configurable() {
+        // Below the 27 us of a 0 every bit decodes as a 1.
+        let mut capture = FrameCapture { bytes: [48, 0, 23, 8, 79], skip_response: false };
+        let timing = FastTiming { now: Default::default(), longest_wait: Default::default() };
+        let config = Dht11Config { bit_threshold_us: 20, ..Dht11Config::new(Dht11SensorType::Dht11) };
+        let raw = Dht11::new(config).read_raw(Dht11Bus::Edges(&mut capture), &timing).unwrap();
+        assert!(raw.bits.iter().all(|bit| *bit));
+        assert!(matches!(raw.readout, Err(Dht11Error::ChecksumError)));
+
+        let config = Dht11Config { bit_threshold_us: 28, ..config };
+        assert!(Dht11::new(config).read_raw(Dht11Bus::Edges(&mut capture), &timing).unwrap().readout.is_ok());
+        assert_eq!(Dht11Config::from(Dht11SensorType::Dht22).start_time_us, DHT22_STARTING_TIME_US);
+    }
+
+    #[test]
+    fn edge_readout_with_missing_bits_times_out() {
+        let edges = [Dht11Edge { rising: true, time_us: 0 }, Dht11Edge { rising: false, time_us: 70 }];
+        assert!(matches!(dht_edges_to_pulses(&edges), Err(Dht11Error::Timeout)));
+        assert!(matches!(dht_edges_to_pulses(&[]), Err(Dht11Error::Timeout)));
+    }
 }
\ No newline at end of file
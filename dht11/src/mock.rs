@@ -0,0 +1,261 @@
+//! Scripted pin and clock for testing readouts on the host: `MockPin` replays
+//! recorded waveforms against the simulated time of a `MockTiming`,
+//! `MockCapture` hands them to edge capture readouts.
+
+use core::cell::Cell;
+use super::{Dht11Edge, Dht11EdgeCapture, Dht11Pin, Dht11Timing};
+
+/// Edges of `dht11_frame_waveform`: the response, two per data bit and the release.
+pub const DHT11_FRAME_EDGES: usize = 84;
+
+const RESPONSE_DELAY_US: u128 = 30;
+const RESPONSE_PULSE_US: u128 = 80;
+const BIT_START_US: u128 = 50;
+const BIT_ZERO_US: u128 = 27;
+const BIT_ONE_US: u128 = 70;
+
+/// Simulated clock. Waiting moves it forward by the waited time, every reading
+/// of the time by `poll_cost_us`, the cost of one poll of the pin.
+pub struct MockTiming {
+    now_us: Cell<u128>,
+    poll_cost_us: u128,
+}
+
+impl MockTiming {
+    pub const fn new(poll_cost_us: u128) -> Self {
+        MockTiming { now_us: Cell::new(0), poll_cost_us }
+    }
+
+    pub fn now_us(&self) -> u128 {
+        self.now_us.get()
+    }
+}
+
+impl Default for MockTiming {
+    fn default() -> Self {
+        MockTiming::new(1)
+    }
+}
+
+impl Dht11Timing for MockTiming {
+    fn wait(&self, microseconds: u32) {
+        self.now_us.set(self.now_us.get() + microseconds as u128);
+    }
+
+    fn get_time_us(&self) -> u128 {
+        self.now_us.set(self.now_us.get() + self.poll_cost_us);
+        self.now_us.get()
+    }
+}
+
+/// Data line of a scripted sensor. Every readout replays the next of
+/// `waveforms` from the moment the reader releases the line, edge times are
+/// relative to that moment. The line idles high, before the first edge, after
+/// the last one and once all waveforms were replayed.
+pub struct MockPin<'a, const N: usize> {
+    timing: &'a MockTiming,
+    waveforms: [&'a [Dht11Edge]; N],
+    is_input: bool,
+    driven_high: bool,
+    released_at_us: u128,
+    low_since_us: u128,
+    /// Index of the first edge of the current waveform not replayed yet, time
+    /// only moves forward so polls never rescan the waveform.
+    next_edge: usize,
+    line_high: bool,
+
+    /// Readouts started by switching the pin to output.
+    pub readouts_started: usize,
+
+    /// How long the reader pulled the line low for the last start signal.
+    ///
+    /// # Unit
+    /// Microseconds.
+    pub start_signal_us: u128,
+}
+
+impl<'a, const N: usize> MockPin<'a, N> {
+    pub fn new(timing: &'a MockTiming, waveforms: [&'a [Dht11Edge]; N]) -> Self {
+        MockPin {
+            timing,
+            waveforms,
+            is_input: false,
+            driven_high: true,
+            released_at_us: 0,
+            low_since_us: 0,
+            next_edge: 0,
+            line_high: true,
+            readouts_started: 0,
+            start_signal_us: 0,
+        }
+    }
+
+    fn level(&mut self) -> bool {
+        if !self.is_input {
+            return self.driven_high;
+        }
+        let Some(waveform) = self.readouts_started.checked_sub(1).and_then(|index| self.waveforms.get(index).copied()) else {
+            return true;
+        };
+        let elapsed_us = self.timing.now_us() - self.released_at_us;
+        while let Some(edge) = waveform.get(self.next_edge).filter(|edge| edge.time_us <= elapsed_us) {
+            self.line_high = edge.rising;
+            self.next_edge += 1;
+        }
+        self.line_high
+    }
+}
+
+impl<const N: usize> Dht11Pin for MockPin<'_, N> {
+    fn is_low(&mut self) -> bool {
+        !self.level()
+    }
+
+    fn is_high(&mut self) -> bool {
+        self.level()
+    }
+
+    fn set_low(&mut self) {
+        self.driven_high = false;
+        self.low_since_us = self.timing.now_us();
+    }
+
+    fn set_high(&mut self) {
+        if !self.driven_high {
+            self.start_signal_us = self.timing.now_us() - self.low_since_us;
+        }
+        self.driven_high = true;
+    }
+
+    fn set_mode_input(&mut self) {
+        self.is_input = true;
+        self.released_at_us = self.timing.now_us();
+        self.next_edge = 0;
+        self.line_high = true;
+    }
+
+    fn set_mode_output(&mut self) {
+        self.is_input = false;
+        self.readouts_started += 1;
+    }
+}
+
+/// Edge capture replaying the next of `waveforms` on every readout, nothing
+/// once all were replayed.
+pub struct MockCapture<'a, const N: usize> {
+    waveforms: [&'a [Dht11Edge]; N],
+    /// Readouts captured so far.
+    pub readouts_started: usize,
+}
+
+impl<'a, const N: usize> MockCapture<'a, N> {
+    pub fn new(waveforms: [&'a [Dht11Edge]; N]) -> Self {
+        MockCapture { waveforms, readouts_started: 0 }
+    }
+}
+
+impl<const N: usize> Dht11EdgeCapture for MockCapture<'_, N> {
+    fn capture(&mut self, _starting_time_us: u32, _timeout_us: u32, edges: &mut [Dht11Edge]) -> usize {
+        let waveform = self.waveforms.get(self.readouts_started).copied().unwrap_or_default();
+        self.readouts_started += 1;
+        let count = waveform.len().min(edges.len());
+        edges[..count].copy_from_slice(&waveform[..count]);
+        count
+    }
+}
+
+/// Waveform of a sensor answering with `bytes`, checksum included, with the
+/// datasheet's nominal timings.
+pub fn dht11_frame_waveform(bytes: [u8; 5]) -> [Dht11Edge; DHT11_FRAME_EDGES] {
+    let mut edges = [Dht11Edge::default(); DHT11_FRAME_EDGES];
+    let mut count = 0;
+    let mut time_us = RESPONSE_DELAY_US;
+    let mut push = |rising, duration_us| {
+        edges[count] = Dht11Edge { rising, time_us };
+        count += 1;
+        time_us += duration_us;
+    };
+    push(false, RESPONSE_PULSE_US);
+    push(true, RESPONSE_PULSE_US);
+    for byte in bytes {
+        for bit in (0..8).rev() {
+            push(false, BIT_START_US);
+            push(true, if byte >> bit & 1 == 1 { BIT_ONE_US } else { BIT_ZERO_US });
+        }
+    }
+    push(false, BIT_START_US);
+    push(true, 0);
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dht11, Dht11Bus, Dht11Error, Dht11RetryPolicy, Dht11SensorType, Dht11Statistics};
+
+    const FRAME: [u8; 5] = [48, 0, 23, 8, 79];
+
+    #[test]
+    fn replayed_frame_is_read() {
+        let timing = MockTiming::default();
+        let waveform = dht11_frame_waveform(FRAME);
+        let mut pin = MockPin::new(&timing, [&waveform]);
+
+        let readout = Dht11::new(Dht11SensorType::Dht11).read(Dht11Bus::Polling(&mut pin), &timing).unwrap();
+        assert_eq!((readout.humidity, readout.temperature), (48.0, 23.8));
+        assert_eq!(pin.start_signal_us, 20_000);
+    }
+
+    #[test]
+    fn slow_polling_still_decodes() {
+        let timing = MockTiming::new(5);
+        let waveform = dht11_frame_waveform(FRAME);
+        let mut pin = MockPin::new(&timing, [&waveform]);
+
+        let raw = Dht11::new(Dht11SensorType::Dht11).read_raw(Dht11Bus::Polling(&mut pin), &timing).unwrap();
+        assert!(raw.pulse_durations_us.iter().all(|duration| (25..=80).contains(duration)));
+        assert!(raw.readout.is_ok());
+    }
+
+    #[test]
+    fn faults_are_injected_through_the_waveform() {
+        let timing = MockTiming::default();
+        let mut dht = Dht11::new(Dht11SensorType::Dht11);
+        let bad_checksum = dht11_frame_waveform([48, 0, 23, 8, 80]);
+        let mut pin = MockPin::new(&timing, [&bad_checksum]);
+        assert!(matches!(dht.read(Dht11Bus::Polling(&mut pin), &timing), Err(Dht11Error::ChecksumError)));
+
+        // The sensor stops after 10 bits and the line stays low.
+        let truncated = dht11_frame_waveform(FRAME);
+        let mut pin = MockPin::new(&timing, [&truncated[..23]]);
+        assert!(matches!(dht.read(Dht11Bus::Polling(&mut pin), &timing), Err(Dht11Error::Timeout)));
+
+        let mut pin = MockPin::new(&timing, []);
+        assert!(matches!(dht.read(Dht11Bus::Polling(&mut pin), &timing), Err(Dht11Error::Timeout)));
+    }
+
+    #[test]
+    fn captured_frame_is_decoded() {
+        let waveform = dht11_frame_waveform(FRAME);
+        let mut capture = MockCapture::new([&waveform]);
+        let timing = MockTiming::default();
+        let mut dht = Dht11::new(Dht11SensorType::Dht11);
+        let readout = dht.read(Dht11Bus::Edges(&mut capture), &timing).unwrap();
+        assert_eq!((readout.humidity, readout.temperature), (48.0, 23.8));
+        assert!(matches!(dht.read(Dht11Bus::Edges(&mut capture), &timing), Err(Dht11Error::Timeout)));
+    }
+
+    #[test]
+    fn retries_replay_the_next_waveform() {
+        let timing = MockTiming::default();
+        let bad_checksum = dht11_frame_waveform([48, 0, 23, 8, 80]);
+        let good = dht11_frame_waveform(FRAME);
+        let mut pin = MockPin::new(&timing, [&[], &bad_checksum, &good]);
+        let mut dht = Dht11::new(Dht11SensorType::Dht11).with_retries(Dht11RetryPolicy::default());
+
+        let result = dht.read(Dht11Bus::Polling(&mut pin), &timing);
+        assert!(result.is_ok());
+        assert_eq!(pin.readouts_started, 3);
+        assert_eq!(dht.statistics(), Dht11Statistics { attempts: 3, timeouts: 1, checksum_errors: 1 });
+    }
+}
@@ -0,0 +1,137 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where the kernel's w1-gpio and w1-therm drivers expose 1-Wire devices,
+/// enabled with `dtoverlay=w1-gpio` (GPIO 4 by default).
+pub const W1_DEVICES_PATH: &str = "/sys/bus/w1/devices";
+/// ROM ids of DS18B20 probes start with their family code.
+const DS18B20_FAMILY_PREFIX: &str = "28-";
+/// Register value after power-up, read back when no conversion took place,
+/// e.g. because of a parasite power supply that can't keep up.
+const POWER_ON_RESET_MILLIDEGREES: i32 = 85_000;
+
+pub const DS18B20_MIN_RESOLUTION: u8 = 9;
+pub const DS18B20_MAX_RESOLUTION: u8 = 12;
+
+#[derive(Debug)]
+pub enum Ds18b20Error {
+    /// Usually the probe is disconnected and the kernel dropped it.
+    Io(io::Error),
+    ChecksumError,
+    PowerOnReset,
+    Malformed,
+}
+
+impl From<io::Error> for Ds18b20Error {
+    fn from(error: io::Error) -> Self {
+        Ds18b20Error::Io(error)
+    }
+}
+
+pub struct Ds18b20Readout {
+    ///
+    /// # Unit
+    /// Celcius degrees.
+    pub temperature: f64,
+}
+
+/// A single probe on the bus, addressed by its ROM id (e.g. `28-0316a2795aff`).
+pub struct Ds18b20Probe {
+    directory: PathBuf,
+}
+
+/// Whether `rom_id` has the `28-` family prefix and 12 hex digits of a DS18B20.
+pub fn ds18b20_is_rom_id(rom_id: &str) -> bool {
+    match rom_id.strip_prefix(DS18B20_FAMILY_PREFIX) {
+        Some(serial) => serial.len() == 12 && serial.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
+
+/// ROM ids of the probes the kernel currently sees, sorted.
+pub fn ds18b20_list_probes(devices_path: &Path) -> io::Result<Vec<String>> {
+    let mut rom_ids: Vec<String> = fs::read_dir(devices_path)?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| ds18b20_is_rom_id(name))
+        .collect();
+    rom_ids.sort();
+    Ok(rom_ids)
+}
+
+/// Parses `w1_slave`, two lines of scratchpad bytes ending with
+/// `crc=xx YES` and `t=<millidegrees>`.
+fn parse_w1_slave(text: &str) -> Result<Ds18b20Readout, Ds18b20Error> {
+    let mut lines = text.lines();
+    let crc_line = lines.next().ok_or(Ds18b20Error::Malformed)?;
+    if !crc_line.trim_end().ends_with("YES") {
+        return Err(Ds18b20Error::ChecksumError);
+    }
+
+    let temperature_line = lines.next().ok_or(Ds18b20Error::Malformed)?;
+    let (_, millidegrees) = temperature_line.rsplit_once("t=").ok_or(Ds18b20Error::Malformed)?;
+    let millidegrees: i32 = millidegrees.trim().parse().map_err(|_| Ds18b20Error::Malformed)?;
+    if millidegrees == POWER_ON_RESET_MILLIDEGREES {
+        return Err(Ds18b20Error::PowerOnReset);
+    }
+    Ok(Ds18b20Readout { temperature: millidegrees as f64 / 1000.0 })
+}
+
+impl Ds18b20Probe {
+    pub fn new(rom_id: &str) -> Self {
+        Ds18b20Probe::with_devices_path(Path::new(W1_DEVICES_PATH), rom_id)
+    }
+
+    pub fn with_devices_path(devices_path: &Path, rom_id: &str) -> Self {
+        Ds18b20Probe { directory: devices_path.join(rom_id) }
+    }
+
+    /// Sets the conversion resolution, 9 bits (0.5 °C, ~94 ms) to 12 bits
+    /// (0.0625 °C, ~750 ms). Writing the attribute requires root.
+    pub fn set_resolution(&self, bits: u8) -> Result<(), Ds18b20Error> {
+        if !(DS18B20_MIN_RESOLUTION..=DS18B20_MAX_RESOLUTION).contains(&bits) {
+            return Err(Ds18b20Error::Malformed);
+        }
+        fs::write(self.directory.join("resolution"), bits.to_string())?;
+        Ok(())
+    }
+
+    /// Blocks for the conversion time of the configured resolution.
+    pub fn perform_readout(&self) -> Result<Ds18b20Readout, Ds18b20Error> {
+        parse_w1_slave(&fs::read_to_string(self.directory.join("w1_slave"))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn w1_slave_is_parsed() {
+        let readout = parse_w1_slave("72 01 4b 46 7f ff 0e 10 57 : crc=57 YES\n72 01 4b 46 7f ff 0e 10 57 t=23125\n").unwrap();
+        assert_eq!(readout.temperature, 23.125);
+        let readout = parse_w1_slave("5e ff 4b 46 7f ff 02 10 c5 : crc=c5 YES\n5e ff 4b 46 7f ff 02 10 c5 t=-10125\n").unwrap();
+        assert_eq!(readout.temperature, -10.125);
+    }
+
+    #[test]
+    fn bad_frames_are_rejected() {
+        assert!(matches!(parse_w1_slave("72 01 4b 46 7f ff 0e 10 57 : crc=00 NO\n72 01 t=23125\n"), Err(Ds18b20Error::ChecksumError)));
+        assert!(matches!(parse_w1_slave("50 05 4b 46 7f ff 0c 10 1c : crc=1c YES\n50 05 t=85000\n"), Err(Ds18b20Error::PowerOnReset)));
+        assert!(matches!(parse_w1_slave(""), Err(Ds18b20Error::Malformed)));
+    }
+
+    #[test]
+    fn probes_are_listed_from_sysfs() {
+        let devices = std::env::temp_dir().join(format!("ds18b20_devices_{}", std::process::id()));
+        for name in ["28-0316a2795aff", "28-01144c2cbcaa", "w1_bus_master1", "00-400000000000"] {
+            fs::create_dir_all(devices.join(name)).unwrap();
+        }
+        fs::write(devices.join("28-0316a2795aff/w1_slave"), "72 01 : crc=57 YES\n72 01 t=23125\n").unwrap();
+
+        assert_eq!(ds18b20_list_probes(&devices).unwrap(), ["28-01144c2cbcaa", "28-0316a2795aff"]);
+        assert_eq!(Ds18b20Probe::with_devices_path(&devices, "28-0316a2795aff").perform_readout().unwrap().temperature, 23.125);
+        assert!(matches!(Ds18b20Probe::with_devices_path(&devices, "28-000000000000").perform_readout(), Err(Ds18b20Error::Io(_))));
+        fs::remove_dir_all(devices).unwrap();
+    }
+}
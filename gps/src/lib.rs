@@ -0,0 +1,236 @@
+const GPS_MAX_SENTENCES: usize = 50;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+pub trait GpsSerial {
+    /// # Returns
+    /// Next NMEA sentence without the line terminator, `None` when the port has no more data.
+    fn read_sentence(&mut self) -> Option<String>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum GpsError {
+    NoData,
+    NoFix,
+    ChecksumError,
+    MalformedSentence,
+}
+
+#[derive(Debug, Default)]
+pub struct GpsFix {
+    ///
+    /// # Unit
+    /// Degrees, positive north.
+    pub latitude: f64,
+
+    ///
+    /// # Unit
+    /// Degrees, positive east.
+    pub longitude: f64,
+
+    ///
+    /// # Unit
+    /// Meters above mean sea level.
+    pub elevation: f64,
+
+    ///
+    /// # Unit
+    /// Seconds since the unix epoch, UTC. Only present once an RMC sentence was received.
+    pub time: Option<i64>,
+}
+
+impl GpsFix {
+    ///
+    /// # Returns
+    /// Difference between the system clock and GPS time in seconds, positive when the
+    /// system clock is ahead.
+    pub fn clock_skew(&self, system_time: i64) -> Option<i64> {
+        self.time.map(|time| system_time - time)
+    }
+}
+
+enum NmeaSentence {
+    Gga { latitude: f64, longitude: f64, elevation: f64 },
+    Rmc { time: i64 },
+    Other,
+}
+
+fn verify_checksum(sentence: &str) -> Result<&str, GpsError> {
+    let body = sentence.strip_prefix('$').ok_or(GpsError::MalformedSentence)?;
+    let (payload, checksum) = body.split_once('*').ok_or(GpsError::MalformedSentence)?;
+    let expected = u8::from_str_radix(checksum.trim(), 16).map_err(|_| GpsError::MalformedSentence)?;
+    let computed = payload.bytes().fold(0u8, |acc, byte| acc ^ byte);
+
+    if computed != expected {
+        return Err(GpsError::ChecksumError);
+    }
+    Ok(payload)
+}
+
+///
+/// # Parameters
+/// value = NMEA `(d)ddmm.mmmm` field, hemisphere = N/S/E/W
+fn parse_coordinate(value: &str, hemisphere: &str) -> Result<f64, GpsError> {
+    let raw: f64 = value.parse().map_err(|_| GpsError::MalformedSentence)?;
+    let degrees = (raw / 100.0).trunc();
+    let decimal = degrees + (raw - degrees * 100.0) / 60.0;
+
+    match hemisphere {
+        "N" | "E" => Ok(decimal),
+        "S" | "W" => Ok(-decimal),
+        _ => Err(GpsError::MalformedSentence),
+    }
+}
+
+/// Days since the unix epoch for a proleptic Gregorian date.
+const fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn parse_field<T: std::str::FromStr>(field: &str) -> Result<T, GpsError> {
+    field.parse().map_err(|_| GpsError::MalformedSentence)
+}
+
+///
+/// # Parameters
+/// time = `hhmmss(.ss)`, date = `ddmmyy`
+fn parse_utc_time(time: &str, date: &str) -> Result<i64, GpsError> {
+    // The fields are sliced by byte, which would split a multibyte character.
+    if !time.is_ascii() || !date.is_ascii() || time.len() < 6 || date.len() != 6 {
+        return Err(GpsError::MalformedSentence);
+    }
+
+    let hours: i64 = parse_field(&time[0..2])?;
+    let minutes: i64 = parse_field(&time[2..4])?;
+    let seconds: i64 = parse_field(&time[4..6])?;
+    let day: i64 = parse_field(&date[0..2])?;
+    let month: i64 = parse_field(&date[2..4])?;
+    // Two digit years are pivoted at the GPS epoch of 1980.
+    let short_year: i64 = parse_field(&date[4..6])?;
+    let year = if short_year < 80 { 2000 + short_year } else { 1900 + short_year };
+
+    Ok(days_from_civil(year, month, day) * SECONDS_PER_DAY + hours * 3600 + minutes * 60 + seconds)
+}
+
+fn parse_sentence(sentence: &str) -> Result<NmeaSentence, GpsError> {
+    let payload = verify_checksum(sentence)?;
+    let fields: Vec<&str> = payload.split(',').collect();
+
+    // Talker id (GP, GN, GL...) is irrelevant, only the sentence type matters.
+    let sentence_type = fields[0].get(2..).unwrap_or("");
+    match sentence_type {
+        "GGA" if fields.len() >= 10 => {
+            if fields[6] == "0" || fields[6].is_empty() {
+                return Err(GpsError::NoFix);
+            }
+            Ok(NmeaSentence::Gga {
+                latitude: parse_coordinate(fields[2], fields[3])?,
+                longitude: parse_coordinate(fields[4], fields[5])?,
+                elevation: parse_field(fields[9])?,
+            })
+        }
+        "RMC" if fields.len() >= 10 => {
+            if fields[2] != "A" {
+                return Err(GpsError::NoFix);
+            }
+            Ok(NmeaSentence::Rmc {
+                time: parse_utc_time(fields[1], fields[9])?,
+            })
+        }
+        "GGA" | "RMC" => Err(GpsError::MalformedSentence),
+        _ => Ok(NmeaSentence::Other),
+    }
+}
+
+/// Reads sentences until both a position (GGA) and a date/time (RMC) fix are known.
+/// Sentences with bad checksums are skipped.
+pub fn gps_perform_readout(serial: &mut dyn GpsSerial) -> Result<GpsFix, GpsError> {
+    let mut fix = GpsFix::default();
+    let mut has_position = false;
+    let mut last_error = GpsError::NoData;
+
+    for _ in 0..GPS_MAX_SENTENCES {
+        let Some(sentence) = serial.read_sentence() else {
+            break;
+        };
+
+        match parse_sentence(sentence.trim()) {
+            Ok(NmeaSentence::Gga { latitude, longitude, elevation }) => {
+                fix.latitude = latitude;
+                fix.longitude = longitude;
+                fix.elevation = elevation;
+                has_position = true;
+            }
+            Ok(NmeaSentence::Rmc { time }) => fix.time = Some(time),
+            Ok(NmeaSentence::Other) => {}
+            Err(error) => last_error = error,
+        }
+
+        if has_position && fix.time.is_some() {
+            return Ok(fix);
+        }
+    }
+
+    if has_position {
+        return Ok(fix);
+    }
+    Err(last_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ScriptedSerial(Vec<&'static str>);
+
+    impl GpsSerial for ScriptedSerial {
+        fn read_sentence(&mut self) -> Option<String> {
+            if self.0.is_empty() {
+                return None;
+            }
+            Some(self.0.remove(0).to_string())
+        }
+    }
+
+    const GGA: &str = "$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47";
+    const RMC: &str = "$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A";
+
+    #[test]
+    fn readout_combines_gga_and_rmc() {
+        let mut serial = ScriptedSerial(vec!["$GPGSV,1,1,00*79", GGA, RMC]);
+        let fix = gps_perform_readout(&mut serial).unwrap();
+
+        assert!((fix.latitude - 48.1173).abs() < 1e-4);
+        assert!((fix.longitude - 11.516_667).abs() < 1e-4);
+        assert_eq!(fix.elevation, 545.4);
+        assert_eq!(fix.time, Some(764_426_119));
+        assert_eq!(fix.clock_skew(764_426_121), Some(2));
+    }
+
+    #[test]
+    fn southern_and_western_hemispheres_are_negative() {
+        assert_eq!(parse_coordinate("3330.000", "S").unwrap(), -33.5);
+        assert_eq!(parse_coordinate("07015.000", "W").unwrap(), -70.25);
+    }
+
+    #[test]
+    fn corrupted_sentence_is_rejected() {
+        let corrupted = GGA.replace("4807", "4808");
+        assert_eq!(verify_checksum(&corrupted).unwrap_err(), GpsError::ChecksumError);
+
+        let mut serial = ScriptedSerial(vec!["$GPGGA,123519,,,,,0,00,,,M,,M,,*6B"]);
+        assert_eq!(gps_perform_readout(&mut serial).unwrap_err(), GpsError::NoFix);
+    }
+
+    #[test]
+    fn non_ascii_time_is_rejected() {
+        assert_eq!(parse_utc_time("1°2519", "230394").unwrap_err(), GpsError::MalformedSentence);
+        assert_eq!(parse_utc_time("123519", "2°0394").unwrap_err(), GpsError::MalformedSentence);
+        assert!(parse_utc_time("123519", "230394").is_ok());
+    }
+}
@@ -0,0 +1,135 @@
+const SECONDS_PER_HOUR: f64 = 3600.0;
+
+#[derive(Debug, PartialEq)]
+pub enum LeafWetnessError {
+    /// The conversion failed, e.g. the ADC is not connected.
+    Adc,
+}
+
+pub trait LeafWetnessAdc {
+    /// # Returns
+    /// Raw conversion result of the channel the sensor is wired to.
+    fn read_raw(&mut self) -> Result<u16, LeafWetnessError>;
+}
+
+/// Raw ADC values of the sensor measured once completely dry and once fully wet.
+/// Depending on the divider wiring, wet may read lower or higher than dry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LeafWetnessCalibration {
+    pub dry_raw: u16,
+    pub wet_raw: u16,
+
+    ///
+    /// # Unit
+    /// Percents. Wetness at or above which the surface counts as wet.
+    pub wet_threshold: f64,
+}
+
+pub struct LeafWetnessReadout {
+    ///
+    /// # Unit
+    /// Percents.
+    pub wetness: f64,
+
+    pub is_wet: bool,
+}
+
+impl LeafWetnessCalibration {
+    fn convert_raw_to_wetness(&self, raw: u16) -> f64 {
+        if self.dry_raw == self.wet_raw {
+            return 0.0;
+        }
+
+        let span = self.wet_raw as f64 - self.dry_raw as f64;
+        ((raw as f64 - self.dry_raw as f64) / span * 100.0).clamp(0.0, 100.0)
+    }
+}
+
+pub fn leaf_wetness_perform_readout(
+    adc: &mut dyn LeafWetnessAdc,
+    calibration: &LeafWetnessCalibration,
+) -> Result<LeafWetnessReadout, LeafWetnessError> {
+    let wetness = calibration.convert_raw_to_wetness(adc.read_raw()?);
+    Ok(LeafWetnessReadout {
+        wetness,
+        is_wet: wetness >= calibration.wet_threshold,
+    })
+}
+
+/// Accumulates the time the surface spent wet during the current day.
+pub struct WetHoursAccumulator {
+    day: u64,
+    wet_seconds: f64,
+}
+
+impl WetHoursAccumulator {
+    pub fn new(day: u64) -> Self {
+        WetHoursAccumulator { day, wet_seconds: 0.0 }
+    }
+
+    /// Adds a sample covering `elapsed_seconds` of the day `day`.
+    ///
+    /// # Returns
+    /// Wet hours of the previous day when `day` starts a new day.
+    pub fn add_sample(&mut self, day: u64, readout: &LeafWetnessReadout, elapsed_seconds: f64) -> Option<f64> {
+        let mut finished_day = None;
+        if day != self.day {
+            finished_day = Some(self.wet_hours());
+            self.day = day;
+            self.wet_seconds = 0.0;
+        }
+
+        if readout.is_wet {
+            self.wet_seconds += elapsed_seconds;
+        }
+
+        finished_day
+    }
+
+    ///
+    /// # Unit
+    /// Hours, for the day in progress.
+    pub fn wet_hours(&self) -> f64 {
+        self.wet_seconds / SECONDS_PER_HOUR
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedAdc(u16);
+
+    impl LeafWetnessAdc for FixedAdc {
+        fn read_raw(&mut self) -> Result<u16, LeafWetnessError> {
+            Ok(self.0)
+        }
+    }
+
+    #[test]
+    fn calibration_handles_inverted_wiring() {
+        let calibration = LeafWetnessCalibration { dry_raw: 1000, wet_raw: 200, wet_threshold: 50.0 };
+
+        let readout = leaf_wetness_perform_readout(&mut FixedAdc(400), &calibration).unwrap();
+        assert_eq!(readout.wetness, 75.0);
+        assert!(readout.is_wet);
+
+        let readout = leaf_wetness_perform_readout(&mut FixedAdc(1023), &calibration).unwrap();
+        assert_eq!(readout.wetness, 0.0);
+        assert!(!readout.is_wet);
+    }
+
+    #[test]
+    fn wet_hours_roll_over_at_day_change() {
+        let wet = LeafWetnessReadout { wetness: 80.0, is_wet: true };
+        let dry = LeafWetnessReadout { wetness: 5.0, is_wet: false };
+        let mut accumulator = WetHoursAccumulator::new(10);
+
+        assert_eq!(accumulator.add_sample(10, &wet, 5400.0), None);
+        assert_eq!(accumulator.add_sample(10, &dry, 600.0), None);
+        assert_eq!(accumulator.wet_hours(), 1.5);
+
+        assert_eq!(accumulator.add_sample(11, &wet, 1800.0), Some(1.5));
+        assert_eq!(accumulator.wet_hours(), 0.5);
+    }
+}
@@ -0,0 +1,155 @@
+const SECONDS_PER_HOUR: f64 = 3600.0;
+const JOULES_PER_MEGAJOULE: f64 = 1_000_000.0;
+/// WMO threshold of direct irradiance for a sunny interval, applied to global irradiance.
+pub const SUNSHINE_THRESHOLD_W_M2: f64 = 120.0;
+
+#[derive(Debug, PartialEq)]
+pub enum PyranometerError {
+    /// The conversion failed, e.g. the ADC is not connected.
+    Adc,
+}
+
+pub trait PyranometerAdc {
+    /// # Returns
+    /// Raw conversion result of the channel the sensor is wired to.
+    fn read_raw(&mut self) -> Result<u16, PyranometerError>;
+}
+
+/// Converts raw ADC values into irradiance for analog pyranometers, both
+/// amplified thermopiles and silicon cells with a voltage output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PyranometerCalibration {
+    /// Raw value at `full_scale_mv`.
+    pub full_scale_raw: u16,
+    pub full_scale_mv: f64,
+
+    ///
+    /// # Unit
+    /// Millivolts per W/m², from the sensor's calibration certificate.
+    pub sensitivity: f64,
+
+    ///
+    /// # Unit
+    /// Millivolts measured in darkness.
+    pub offset_mv: f64,
+}
+
+pub struct PyranometerReadout {
+    ///
+    /// # Unit
+    /// W/m².
+    pub irradiance: f64,
+}
+
+impl PyranometerCalibration {
+    fn convert_raw_to_irradiance(&self, raw: u16) -> f64 {
+        if self.full_scale_raw == 0 || self.sensitivity <= 0.0 {
+            return 0.0;
+        }
+
+        let voltage = raw as f64 / self.full_scale_raw as f64 * self.full_scale_mv;
+        ((voltage - self.offset_mv) / self.sensitivity).max(0.0)
+    }
+}
+
+pub fn pyranometer_perform_readout(
+    adc: &mut dyn PyranometerAdc,
+    calibration: &PyranometerCalibration,
+) -> Result<PyranometerReadout, PyranometerError> {
+    Ok(PyranometerReadout {
+        irradiance: calibration.convert_raw_to_irradiance(adc.read_raw()?),
+    })
+}
+
+/// Daily totals of the current day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DailyRadiation {
+    ///
+    /// # Unit
+    /// MJ/m², as used by the FAO-56 evapotranspiration.
+    pub radiation: f64,
+
+    ///
+    /// # Unit
+    /// Hours.
+    pub sunshine_hours: f64,
+}
+
+/// Integrates irradiance into the daily radiation total and sunshine duration.
+pub struct RadiationAccumulator {
+    day: u64,
+    joules: f64,
+    sunshine_seconds: f64,
+}
+
+impl RadiationAccumulator {
+    pub fn new(day: u64) -> Self {
+        RadiationAccumulator { day, joules: 0.0, sunshine_seconds: 0.0 }
+    }
+
+    /// Adds a sample covering `elapsed_seconds` of the day `day`.
+    ///
+    /// # Returns
+    /// Totals of the previous day when `day` starts a new day.
+    pub fn add_sample(&mut self, day: u64, readout: &PyranometerReadout, elapsed_seconds: f64) -> Option<DailyRadiation> {
+        let mut finished_day = None;
+        if day != self.day {
+            finished_day = Some(self.totals());
+            self.day = day;
+            self.joules = 0.0;
+            self.sunshine_seconds = 0.0;
+        }
+
+        self.joules += readout.irradiance * elapsed_seconds;
+        if readout.irradiance >= SUNSHINE_THRESHOLD_W_M2 {
+            self.sunshine_seconds += elapsed_seconds;
+        }
+
+        finished_day
+    }
+
+    /// Totals of the day in progress.
+    pub fn totals(&self) -> DailyRadiation {
+        DailyRadiation {
+            radiation: self.joules / JOULES_PER_MEGAJOULE,
+            sunshine_hours: self.sunshine_seconds / SECONDS_PER_HOUR,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedAdc(u16);
+
+    impl PyranometerAdc for FixedAdc {
+        fn read_raw(&mut self) -> Result<u16, PyranometerError> {
+            Ok(self.0)
+        }
+    }
+
+    #[test]
+    fn calibration_converts_voltage() {
+        // 0-2 V output for 0-2000 W/m² on a 12 bit, 3.3 V ADC.
+        let calibration = PyranometerCalibration { full_scale_raw: 4095, full_scale_mv: 3300.0, sensitivity: 1.0, offset_mv: 0.0 };
+        let readout = pyranometer_perform_readout(&mut FixedAdc(1241), &calibration).unwrap();
+        assert!((readout.irradiance - 1000.0).abs() < 1.0);
+
+        let calibration = PyranometerCalibration { offset_mv: 5.0, ..calibration };
+        assert_eq!(pyranometer_perform_readout(&mut FixedAdc(0), &calibration).unwrap().irradiance, 0.0);
+    }
+
+    #[test]
+    fn totals_roll_over_at_day_change() {
+        let mut accumulator = RadiationAccumulator::new(10);
+
+        assert_eq!(accumulator.add_sample(10, &PyranometerReadout { irradiance: 500.0 }, 3600.0), None);
+        assert_eq!(accumulator.add_sample(10, &PyranometerReadout { irradiance: 100.0 }, 3600.0), None);
+        assert_eq!(accumulator.totals(), DailyRadiation { radiation: 2.16, sunshine_hours: 1.0 });
+
+        let finished = accumulator.add_sample(11, &PyranometerReadout { irradiance: 0.0 }, 60.0);
+        assert_eq!(finished, Some(DailyRadiation { radiation: 2.16, sunshine_hours: 1.0 }));
+        assert_eq!(accumulator.totals().radiation, 0.0);
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt;
+
+/// Physical quantities station sensors measure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Quantity {
+    Temperature,
+    RelativeHumidity,
+    Pressure,
+    /// Global solar irradiance on a horizontal surface.
+    Irradiance,
+    /// Snow depth above the ground.
+    SnowDepth,
+    /// Share of a leaf or surface that is wet.
+    LeafWetness,
+}
+
+impl Quantity {
+    pub const ALL: [Quantity; 6] =
+        [Quantity::Temperature, Quantity::RelativeHumidity, Quantity::Pressure, Quantity::Irradiance, Quantity::SnowDepth, Quantity::LeafWetness];
+
+    /// Inverse of [`Quantity::name`].
+    pub fn parse(name: &str) -> Option<Quantity> {
+        Quantity::ALL.into_iter().find(|quantity| quantity.name() == name)
+    }
+
+    /// Name used in MQTT topics, JSON and metrics.
+    pub fn name(self) -> &'static str {
+        match self {
+            Quantity::Temperature => "temperature",
+            Quantity::RelativeHumidity => "humidity",
+            Quantity::Pressure => "pressure",
+            Quantity::Irradiance => "irradiance",
+            Quantity::SnowDepth => "snow_depth",
+            Quantity::LeafWetness => "leaf_wetness",
+        }
+    }
+
+    /// Unit values of this quantity are always kept in.
+    pub fn unit(self) -> &'static str {
+        match self {
+            Quantity::Temperature => "°C",
+            Quantity::RelativeHumidity => "%",
+            Quantity::Pressure => "hPa",
+            Quantity::Irradiance => "W/m²",
+            Quantity::SnowDepth => "cm",
+            Quantity::LeafWetness => "%",
+        }
+    }
+}
+
+/// Values taken by one readout of a sensor, each in the unit of its [`Quantity`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Measurement {
+    values: Vec<(Quantity, f64)>,
+}
+
+impl Measurement {
+    pub fn new() -> Self {
+        Measurement::default()
+    }
+
+    /// Adds or replaces the value of `quantity`.
+    pub fn with(mut self, quantity: Quantity, value: f64) -> Self {
+        self.values.retain(|(existing, _)| *existing != quantity);
+        self.values.push((quantity, value));
+        self
+    }
+
+    pub fn get(&self, quantity: Quantity) -> Option<f64> {
+        self.values.iter().find(|(existing, _)| *existing == quantity).map(|(_, value)| *value)
+    }
+
+    /// In the order the driver added them.
+    pub fn iter(&self) -> impl Iterator<Item = (Quantity, f64)> + '_ {
+        self.values.iter().copied()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SensorErrorKind {
+    /// The sensor did not answer in time.
+    Timeout,
+    /// The answer was corrupted on the way.
+    Checksum,
+    /// The bus or device node is unusable, usually the sensor is disconnected.
+    Bus,
+    /// Something else answers where the sensor should be.
+    UnexpectedDevice,
+    /// The sensor answered with values it can't have measured.
+    InvalidData,
+}
+
+impl SensorErrorKind {
+    /// Stored with failed readings.
+    pub fn status(self) -> &'static str {
+        match self {
+            SensorErrorKind::Timeout => "timeout",
+            SensorErrorKind::Checksum => "checksum_error",
+            SensorErrorKind::Bus => "bus_error",
+            SensorErrorKind::UnexpectedDevice => "unexpected_device",
+            SensorErrorKind::InvalidData => "invalid_data",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SensorError {
+    pub kind: SensorErrorKind,
+    /// The driver's own error, for logs.
+    pub detail: String,
+}
+
+impl SensorError {
+    pub fn new(kind: SensorErrorKind, detail: impl fmt::Debug) -> Self {
+        SensorError { kind, detail: format!("{:?}", detail) }
+    }
+}
+
+impl fmt::Display for SensorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.kind.status(), self.detail)
+    }
+}
+
+/// Monotonic driver counter, e.g. retried readouts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SensorCounter {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub value: u64,
+}
+
+/// A sensor the station samples every interval. Drivers that need a pin, a bus
+/// or timing own them, so the station treats every sensor alike.
+pub trait Sensor {
+    /// Short driver name, e.g. `dht11`.
+    fn driver(&self) -> &'static str;
+
+    /// Takes a readout, blocking until the sensor answered or gave up.
+    fn read(&mut self) -> Result<Measurement, SensorError>;
+
+    /// Counters the driver keeps about its readouts, none by default.
+    fn counters(&self) -> Vec<SensorCounter> {
+        Vec::new()
+    }
+
+    /// Air temperature in °C measured before the next readout, for drivers that
+    /// compensate for it. Ignored by default.
+    fn set_air_temperature(&mut self, _temperature: f64) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Thermometer {
+        readouts: u64,
+    }
+
+    impl Sensor for Thermometer {
+        fn driver(&self) -> &'static str {
+            "thermometer"
+        }
+
+        fn read(&mut self) -> Result<Measurement, SensorError> {
+            self.readouts += 1;
+            match self.readouts {
+                1 => Ok(Measurement::new().with(Quantity::Temperature, 21.5)),
+                _ => Err(SensorError::new(SensorErrorKind::Timeout, "no answer")),
+            }
+        }
+    }
+
+    #[test]
+    fn measurement_keeps_one_value_per_quantity() {
+        let measurement = Measurement::new().with(Quantity::Temperature, 20.0).with(Quantity::RelativeHumidity, 40.0).with(Quantity::Temperature, 21.0);
+        assert_eq!(measurement.get(Quantity::Temperature), Some(21.0));
+        assert_eq!(measurement.get(Quantity::Pressure), None);
+        assert_eq!(measurement.iter().map(|(quantity, _)| quantity.name()).collect::<Vec<_>>(), ["humidity", "temperature"]);
+        assert_eq!(Quantity::parse("pressure"), Some(Quantity::Pressure));
+        assert_eq!(Quantity::parse("wind"), None);
+    }
+
+    #[test]
+    fn sensors_are_used_through_the_trait() {
+        let mut sensor: Box<dyn Sensor> = Box::new(Thermometer { readouts: 0 });
+        assert_eq!(sensor.read().unwrap().get(Quantity::Temperature), Some(21.5));
+        let error = sensor.read().unwrap_err();
+        assert_eq!(error.to_string(), "timeout (\"no answer\")");
+        assert!(sensor.counters().is_empty());
+    }
+}
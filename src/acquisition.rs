@@ -0,0 +1,424 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use sensor::{Measurement, Quantity, SensorError, SensorErrorKind};
+use tracing::{debug, error, info, info_span, warn};
+use weather_math::ComfortIndex;
+use crate::cli::OutputFormat;
+use crate::commands::Command;
+use crate::config::{SensorConfig, SensorKind, StationConfig};
+use crate::connectivity::{Connectivity, ProbeMonitor};
+use crate::csv_log::CsvLogger;
+use crate::diagnostics::{self, ChecksumErrorRate, ReliabilityMonitor, ReliabilityStatus};
+use crate::error::StationError;
+use crate::evapotranspiration::{self, GpsConfig, Location};
+use crate::events::{EventBus, StationEvent};
+use crate::http_api;
+use crate::metrics::StationMetrics;
+use crate::mqtt::MqttPublisher;
+use crate::precision::Precision;
+use crate::sensors::{self, AirTemperatures, SensorChannel};
+use crate::shutdown::Shutdown;
+use crate::signing::ArchiveSigner;
+use crate::systemd::{self, Notifier};
+use crate::storage::{self, Reading, Storage};
+use crate::{hardware_report, precision, system_metrics, UartGps};
+
+const PRUNE_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Time left until the next multiple of `interval` since the unix epoch, so
+/// stations and restarts sample at the same wall clock instants.
+fn delay_to_next_boundary(since_epoch: Duration, interval: Duration) -> Duration {
+    let interval_ms = interval.as_millis().max(1);
+    let remainder_ms = since_epoch.as_millis() % interval_ms;
+    Duration::from_millis((interval_ms - remainder_ms) as u64)
+}
+
+fn wait_for_next_sample(options: &StationConfig, cycle_start: Instant, shutdown: &Shutdown) {
+    let delay = if options.align_to_clock {
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO);
+        delay_to_next_boundary(since_epoch, options.interval)
+    } else {
+        options.interval.saturating_sub(cycle_start.elapsed())
+    };
+    shutdown.sleep(delay);
+}
+
+fn print_readout(sensor: &SensorConfig, data: &Measurement, comfort_index: Option<ComfortIndex>) {
+    println!("Weather station readout ({}):", sensor.name);
+    for (quantity, value) in data.iter() {
+        let (label, unit) = match quantity {
+            Quantity::Temperature => ("Temperature", "*C"),
+            Quantity::RelativeHumidity => ("Humidity", "%"),
+            Quantity::Pressure => ("Pressure", " hPa"),
+            Quantity::Irradiance => ("Irradiance", " W/m2"),
+            Quantity::SnowDepth => ("Snow depth", " cm"),
+            Quantity::LeafWetness => ("Leaf wetness", "%"),
+        };
+        println!("{}: {}{}", label, Precision::of(quantity).format(value), unit);
+    }
+    if let Some(derived) = sensors::meteo_derived(data) {
+        println!("Dew point: {}*C", precision::TEMPERATURE_PRECISION.format(derived.dew_point));
+        println!("Heat index: {}*C", precision::TEMPERATURE_PRECISION.format(derived.heat_index));
+        println!("Absolute humidity: {} g/m3", precision::ABSOLUTE_HUMIDITY_PRECISION.format(derived.absolute_humidity));
+    }
+    // No wind sensor yet, so THW degrades to the heat index.
+    if let (Some(index), Some(temperature), Some(humidity)) = (comfort_index, data.get(Quantity::Temperature), data.get(Quantity::RelativeHumidity)) {
+        let value = index.compute(temperature, humidity, 0.0);
+        println!("{:?}: {}", index, precision::TEMPERATURE_PRECISION.format(value));
+    }
+}
+
+fn print_station_status(options: &StationConfig, reliability: &ReliabilityStatus) {
+    system_metrics::SystemMetrics::read().print();
+    Connectivity::check(&options.connectivity).print();
+    reliability.print();
+}
+
+/// Values are rounded like everywhere they leave the station.
+fn log_readout(data: &Measurement) {
+    let round = |quantity| data.get(quantity).map(|value| Precision::of(quantity).round(value));
+    let derived = sensors::meteo_derived(data);
+    info!(
+        temperature = round(Quantity::Temperature),
+        humidity = round(Quantity::RelativeHumidity),
+        pressure = round(Quantity::Pressure),
+        irradiance = round(Quantity::Irradiance),
+        snow_depth = round(Quantity::SnowDepth),
+        leaf_wetness = round(Quantity::LeafWetness),
+        dew_point = derived.as_ref().map(|derived| precision::TEMPERATURE_PRECISION.round(derived.dew_point)),
+        heat_index = derived.as_ref().map(|derived| precision::TEMPERATURE_PRECISION.round(derived.heat_index)),
+        "readout"
+    );
+}
+
+/// Reports the latest probe, never waits for one.
+fn log_station_status(probes: &ProbeMonitor) {
+    let system = system_metrics::SystemMetrics::read();
+    let connectivity = Connectivity::with_probe(probes.latest());
+    info!(
+        cpu_temperature = system.cpu_temperature.map(|temperature| precision::CPU_TEMPERATURE_PRECISION.round(temperature)),
+        throttled = system.throttled,
+        probe_latency_ms = connectivity.probe.latency(),
+        wifi_rssi_dbm = connectivity.wifi_rssi,
+        "station status"
+    );
+}
+
+fn readout_json(sensor: &SensorConfig, data: &Measurement, comfort_index: Option<ComfortIndex>, timestamp: i64) -> serde_json::Value {
+    let mut json = serde_json::json!({
+        "timestamp": timestamp,
+        "sensor": sensor.name,
+        "location": sensor.location,
+    });
+    for (quantity, value) in data.iter() {
+        json[quantity.name()] = Precision::of(quantity).round(value).into();
+    }
+    if let Some(derived) = sensors::meteo_derived(data) {
+        json["dew_point"] = precision::TEMPERATURE_PRECISION.round(derived.dew_point).into();
+        json["heat_index"] = precision::TEMPERATURE_PRECISION.round(derived.heat_index).into();
+        json["absolute_humidity"] = precision::ABSOLUTE_HUMIDITY_PRECISION.round(derived.absolute_humidity).into();
+    }
+    if let (Some(index), Some(temperature), Some(humidity)) = (comfort_index, data.get(Quantity::Temperature), data.get(Quantity::RelativeHumidity)) {
+        let value = index.compute(temperature, humidity, 0.0);
+        json[format!("{:?}", index).to_lowercase()] = precision::TEMPERATURE_PRECISION.round(value).into();
+    }
+    json
+}
+
+fn readout_error_json(sensor: &SensorConfig, error: &SensorError, timestamp: i64) -> serde_json::Value {
+    serde_json::json!({
+        "timestamp": timestamp,
+        "sensor": sensor.name,
+        "location": sensor.location,
+        "error": error.kind.status(),
+        "detail": error.detail,
+    })
+}
+
+/// Publishes the solar radiation and sunshine duration of the UTC day `days` for
+/// every pyranometer, the wet hours for every leaf wetness sensor, and with a
+/// `location` its reference evapotranspiration for every sensor, whose readings
+/// cover it. The first pyranometer's radiation
+/// makes the evapotranspiration a Penman-Monteith one.
+fn publish_daily_values(storage: &Storage, publisher: &MqttPublisher, options: &StationConfig, location: Option<&Location>, days: i64) {
+    let start = evapotranspiration::day_start(days);
+    let readings = match storage.readings_between(start, start + SECONDS_PER_DAY - 1) {
+        Ok(readings) => readings,
+        Err(error) => {
+            warn!(%error, "reading the day for daily values failed");
+            return;
+        }
+    };
+    let day_of = |sensor: &SensorConfig| -> Vec<Reading> { readings.iter().filter(|reading| reading.sensor_id == sensor.name).cloned().collect() };
+
+    let mut station_radiation = None;
+    for sensor in options.sensors.iter().filter(|sensor| matches!(sensor.kind, SensorKind::Pyranometer { .. })) {
+        if let Some(radiation) = evapotranspiration::daily_radiation(&day_of(sensor), days) {
+            info!(
+                sensor = %sensor.name,
+                radiation_mj_m2 = precision::RADIATION_PRECISION.round(radiation.radiation),
+                sunshine_hours = precision::SUNSHINE_PRECISION.round(radiation.sunshine_hours),
+                "daily solar radiation"
+            );
+            publisher.publish_radiation(&sensor.name, &radiation, start);
+            station_radiation.get_or_insert(radiation);
+        }
+    }
+    for sensor in &options.sensors {
+        let SensorKind::LeafWetness { calibration, .. } = &sensor.kind else {
+            continue;
+        };
+        if let Some(wet_hours) = evapotranspiration::daily_wet_hours(&day_of(sensor), days, calibration.wet_threshold) {
+            info!(sensor = %sensor.name, wet_hours = precision::WET_HOURS_PRECISION.round(wet_hours), "daily leaf wetness");
+            publisher.publish_wet_hours(&sensor.name, wet_hours, start);
+        }
+    }
+
+    let Some(location) = location else {
+        return;
+    };
+    for sensor in &options.sensors {
+        if let Some(et0) = evapotranspiration::daily_et0(&day_of(sensor), location, days, station_radiation.as_ref()) {
+            info!(sensor = %sensor.name, et0_mm = precision::EVAPOTRANSPIRATION_PRECISION.round(et0), "daily evapotranspiration");
+            publisher.publish_evapotranspiration(&sensor.name, et0, start);
+        }
+    }
+}
+
+/// Location of the GPS module's fix, `None` while it has none, e.g. during a
+/// cold start.
+fn gps_location(gps: &GpsConfig) -> Option<Location> {
+    let location = UartGps::new(&gps.device, gps.baud_rate)
+        .map_err(|error| error.to_string())
+        .and_then(|mut serial| Location::from_gps(&mut serial, storage::unix_time_now()).map_err(|error| format!("{:?}", error)));
+    match location {
+        Ok(location) => {
+            info!(device = %gps.device, latitude = location.latitude, elevation_m = location.elevation_m, "location from GPS");
+            Some(location)
+        }
+        Err(error) => {
+            warn!(device = %gps.device, %error, "no GPS location, trying again tomorrow");
+            None
+        }
+    }
+}
+
+/// Reads every configured sensor once. JSON output has one line per sensor.
+/// A sensor that can't be opened or read is reported in its place, the others
+/// are still read; the error of the first one is returned at the end.
+pub fn read_once(options: &StationConfig, format: OutputFormat) -> Result<(), StationError> {
+    let start = Instant::now();
+    let mut temperatures = AirTemperatures::default();
+    let mut probes = Vec::new();
+    let mut counters = Vec::new();
+    for sensor in &options.sensors {
+        match SensorChannel::open(sensor, options) {
+            Ok(mut channel) => {
+                probes.push(temperatures.read(&mut channel));
+                counters.extend(channel.counters());
+            }
+            Err(error) => probes.push(Err(SensorError { kind: SensorErrorKind::Bus, detail: error.to_string() })),
+        }
+    }
+    let mut checksum_errors = ChecksumErrorRate::new(start);
+    checksum_errors.record(Instant::now(), &counters);
+    let reliability = ReliabilityStatus::new(diagnostics::reliability_warnings(), checksum_errors.rate());
+    if format == OutputFormat::Text {
+        let report: Vec<_> = options.sensors.iter().zip(&probes).collect();
+        hardware_report::log_hardware_report(options, &report);
+        diagnostics::log_reliability_warnings(&reliability.warnings);
+    }
+    for (sensor, probe) in options.sensors.iter().zip(&probes) {
+        match (format, probe) {
+            (OutputFormat::Text, Ok(data)) => print_readout(sensor, data, options.comfort_index),
+            (OutputFormat::Text, Err(error)) => println!("Weather station readout ({}): failed, {}", sensor.name, error),
+            (OutputFormat::Json, Ok(data)) => println!("{}", readout_json(sensor, data, options.comfort_index, storage::unix_time_now())),
+            (OutputFormat::Json, Err(error)) => println!("{}", readout_error_json(sensor, error, storage::unix_time_now())),
+        }
+    }
+    if format == OutputFormat::Text {
+        print_station_status(options, &reliability);
+    }
+    match probes.into_iter().find_map(Result::err) {
+        Some(error) => Err(error.into()),
+        None => Ok(()),
+    }
+}
+
+/// Samples every sensor each configured interval until SIGINT or SIGTERM.
+/// Sensors are read one after another on this thread, so the timing critical
+/// bit-banging of one never overlaps another's. Failed samples are reported and
+/// skipped, they never end the loop. On a signal the readout in flight is
+/// completed and stored, the remaining sensors of the cycle are skipped.
+pub fn run(options: StationConfig) -> Result<(), StationError> {
+    let shutdown = Shutdown::register()?;
+    let notifier = Notifier::from_env();
+    let storage = Storage::open(&options.database_path)?;
+    let signer = options.signing_key_file.as_deref().map(ArchiveSigner::load).transpose()?;
+    let mut csv_logger = options.csv_directory.as_deref().map(|directory| CsvLogger::new(directory, signer));
+    let metrics = Arc::new(StationMetrics::default());
+    let reliability = Arc::new(ReliabilityMonitor::default());
+    let events = EventBus::default();
+    if let Some(address) = options.http_address {
+        http_api::spawn(address, &options, Arc::clone(&metrics), Arc::clone(&reliability), &events)?;
+    }
+    let probes = ProbeMonitor::spawn(&options.connectivity);
+    let mut publisher = MqttPublisher::connect(&options.mqtt, &options.sensors);
+    let mut channels = Vec::new();
+    for sensor in &options.sensors {
+        channels.push(SensorChannel::open(sensor, &options)?);
+    }
+    info!(sensors = channels.len(), interval_s = options.interval.as_secs(), "weather station started");
+    notifier.ready(options.interval);
+    if options.align_to_clock {
+        wait_for_next_sample(&options, Instant::now(), &shutdown);
+    }
+
+    let mut is_first_sample = true;
+    let mut last_prune: Option<Instant> = None;
+    // UTC day the daily values were last published for.
+    let mut daily_values_day: Option<i64> = None;
+    // Configured, or taken from the GPS on the first day with a fix.
+    let mut location = options.location.clone();
+    let mut temperatures = AirTemperatures::default();
+    let mut checksum_errors = ChecksumErrorRate::new(Instant::now());
+    let mut cycle: u64 = 0;
+    while !shutdown.is_requested() {
+        cycle += 1;
+        let _cycle = info_span!("cycle", cycle).entered();
+        let cycle_start = Instant::now();
+        if publisher.as_ref().is_some_and(|publisher| !publisher.is_running()) {
+            error!("MQTT event loop stopped, reconnecting");
+            publisher = MqttPublisher::connect(&options.mqtt, &options.sensors);
+        }
+        let commands = publisher.as_ref().map(MqttPublisher::received_commands).unwrap_or_default();
+        if commands.contains(&Command::Prune) || last_prune.is_none_or(|time| time.elapsed() >= PRUNE_PERIOD) {
+            match storage.prune(options.retention_days, storage::unix_time_now()) {
+                Ok(deleted) => info!(deleted, retention_days = options.retention_days, "pruned old readings"),
+                Err(error) => warn!(%error, "pruning failed"),
+            }
+            last_prune = Some(cycle_start);
+        }
+        if let Some(publisher) = &publisher {
+            let yesterday = storage::unix_time_now().div_euclid(SECONDS_PER_DAY) - 1;
+            if daily_values_day != Some(yesterday) {
+                if location.is_none() {
+                    location = options.gps.as_ref().and_then(gps_location);
+                }
+                publish_daily_values(&storage, publisher, &options, location.as_ref(), yesterday);
+                daily_values_day = Some(yesterday);
+            }
+        }
+
+        let mut results = Vec::with_capacity(channels.len());
+        for channel in &mut channels {
+            if shutdown.is_requested() {
+                break;
+            }
+            let _readout = info_span!("readout", sensor = %channel.config.name, driver = channel.driver()).entered();
+            let read_start = Instant::now();
+            let result = temperatures.read(channel);
+            debug!(duration_ms = read_start.elapsed().as_millis() as u64, ok = result.is_ok(), "sensor read");
+            metrics.update(&channel.config, channel.driver(), result.as_ref().ok(), &channel.counters());
+            results.push(result);
+        }
+        if is_first_sample {
+            let report: Vec<_> = channels.iter().map(|channel| &channel.config).zip(&results).collect();
+            hardware_report::log_hardware_report(&options, &report);
+            is_first_sample = false;
+        }
+        let counters: Vec<_> = channels.iter().flat_map(SensorChannel::counters).collect();
+        checksum_errors.record(Instant::now(), &counters);
+        reliability.update(ReliabilityStatus::new(diagnostics::reliability_warnings(), checksum_errors.rate()));
+
+        let mut readings = Vec::with_capacity(results.len());
+        for (channel, result) in channels.iter().zip(&results) {
+            let _readout = info_span!("readout", sensor = %channel.config.name, driver = channel.driver()).entered();
+            let reading = Reading::new(&channel.config.name, result);
+            if let Err(error) = storage.insert_reading(&reading) {
+                warn!(%error, "storing readout failed");
+            }
+            if let Some(Err(error)) = csv_logger.as_mut().map(|logger| logger.append(&reading)) {
+                warn!(%error, "logging readout to CSV failed");
+            }
+            events.publish(StationEvent::Reading(reading.clone()));
+
+            match result {
+                Ok(data) => {
+                    if let Some(publisher) = &mut publisher {
+                        publisher.publish(&channel.config.name, data, reading.timestamp);
+                    }
+                    log_readout(data);
+                }
+                Err(error) => warn!(error_kind = error.kind.status(), detail = %error.detail, "readout failed"),
+            }
+            readings.push(reading);
+        }
+        let any_ok = results.iter().any(Result::is_ok);
+        if let Some(publisher) = &publisher {
+            publisher.set_available(any_ok);
+        }
+        // Only readouts feed the watchdog, a station whose sensors all fail gets restarted too.
+        let status = systemd::cycle_status(&readings);
+        if any_ok {
+            notifier.cycle_completed(&status);
+        } else {
+            notifier.status(&status);
+        }
+        log_station_status(&probes);
+
+        wait_for_next_sample(&options, cycle_start, &shutdown);
+    }
+
+    info!("shutting down");
+    notifier.stopping();
+    if let Some(publisher) = publisher {
+        publisher.shutdown();
+    }
+    // Releases the DHT11 pins.
+    drop(channels);
+    storage.close()?;
+    info!("weather station stopped");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boundaries_follow_the_wall_clock() {
+        let five_minutes = Duration::from_secs(300);
+        assert_eq!(delay_to_next_boundary(Duration::from_secs(1_700_000_200), five_minutes), Duration::from_secs(200));
+        assert_eq!(delay_to_next_boundary(Duration::from_millis(1_700_000_399_500), five_minutes), Duration::from_millis(500));
+        assert_eq!(delay_to_next_boundary(Duration::from_secs(1_700_000_400), five_minutes), five_minutes);
+    }
+
+    #[test]
+    fn json_readout_is_rounded() {
+        let data = Measurement::new().with(Quantity::Temperature, 21.53).with(Quantity::RelativeHumidity, 40.04).with(Quantity::Pressure, 1013.26);
+        let json = readout_json(&SensorConfig::single(23), &data, Some(ComfortIndex::Humidex), 1700000000);
+        assert_eq!(json["sensor"], "dht11");
+        assert_eq!(json["temperature"], 21.5);
+        assert_eq!(json["humidity"], 40.0);
+        assert!(json["humidex"].is_f64());
+        assert_eq!(json["absolute_humidity"], 7.6);
+        assert_eq!(json["pressure"], 1013.3);
+
+        let probe = Measurement::new().with(Quantity::Temperature, 12.0625);
+        let json = readout_json(&SensorConfig::single(23), &probe, Some(ComfortIndex::Humidex), 1700000000);
+        assert_eq!(json["temperature"], 12.1);
+        assert!(json.get("humidity").is_none() && json.get("humidex").is_none());
+    }
+
+    #[test]
+    fn json_error_names_the_failed_sensor() {
+        let error = SensorError { kind: SensorErrorKind::Bus, detail: "I2C error: no such device".to_string() };
+        let json = readout_error_json(&SensorConfig::single(23), &error, 1700000000);
+        assert_eq!(json["sensor"], "dht11");
+        assert_eq!(json["error"], "bus_error");
+        assert_eq!(json["detail"], "I2C error: no such device");
+        assert!(json.get("temperature").is_none());
+    }
+}
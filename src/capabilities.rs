@@ -0,0 +1,98 @@
+use serde::Serialize;
+use crate::cli::OutputFormat;
+use crate::config::{SensorKind, StationConfig};
+
+/// An optional subsystem, whether this build contains it and whether the
+/// configuration turns it on.
+#[derive(Serialize)]
+pub struct Capability {
+    name: &'static str,
+    compiled: bool,
+    active: bool,
+}
+
+#[derive(Serialize)]
+pub struct CapabilityReport {
+    version: &'static str,
+    capabilities: Vec<Capability>,
+}
+
+fn has_sensor(config: &StationConfig, matches: fn(&SensorKind) -> bool) -> bool {
+    config.sensors.iter().any(|sensor| matches(&sensor.kind))
+}
+
+impl CapabilityReport {
+    pub fn new(config: &StationConfig) -> Self {
+        let mqtt = &config.mqtt;
+        let capability = |name, compiled, active| Capability { name, compiled, active: compiled && active };
+        CapabilityReport {
+            version: env!("CARGO_PKG_VERSION"),
+            capabilities: vec![
+                capability("dht11", true, has_sensor(config, |kind| matches!(kind, SensorKind::Dht11 { .. }))),
+                capability("dht11_edge_capture", true, config.edge_capture && has_sensor(config, |kind| matches!(kind, SensorKind::Dht11 { .. }))),
+                capability("bme280", true, has_sensor(config, |kind| matches!(kind, SensorKind::Bme280 { .. }))),
+                capability("ds18b20", true, has_sensor(config, |kind| matches!(kind, SensorKind::Ds18b20 { .. }))),
+                capability("pyranometer", true, has_sensor(config, |kind| matches!(kind, SensorKind::Pyranometer { .. }))),
+                capability("leaf_wetness", true, has_sensor(config, |kind| matches!(kind, SensorKind::LeafWetness { .. }))),
+                capability("ultrasonic", true, has_sensor(config, |kind| matches!(kind, SensorKind::Ultrasonic { .. }))),
+                capability("gps", true, config.location.is_none() && config.gps.is_some()),
+                capability("http_api", true, config.http_address.is_some()),
+                capability("csv_log", true, config.csv_directory.is_some()),
+                capability("mqtt", true, mqtt.broker.is_some()),
+                capability("mqtt_tls", cfg!(feature = "tls"), mqtt.broker.is_some() && mqtt.tls),
+                capability("mqtt_ha_discovery", true, mqtt.broker.is_some() && mqtt.discovery_prefix.is_some()),
+                capability("mqtt_report_by_exception", true, mqtt.broker.is_some() && mqtt.report_by_exception.is_some()),
+                capability("mqtt_commands", true, mqtt.broker.is_some() && mqtt.commands.is_some()),
+                capability("bundled_sqlite", cfg!(feature = "bundled-sqlite"), true),
+            ],
+        }
+    }
+
+    /// Names of subsystems the configuration asks for but this build lacks.
+    fn missing(&self, config: &StationConfig) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if config.mqtt.broker.is_some() && config.mqtt.tls && !cfg!(feature = "tls") {
+            missing.push("mqtt_tls");
+        }
+        missing
+    }
+
+    pub fn print(&self, config: &StationConfig, format: OutputFormat) {
+        match format {
+            OutputFormat::Json => println!("{}", serde_json::to_string(self).unwrap_or_default()),
+            OutputFormat::Text => {
+                println!("weather_station {}", self.version);
+                for capability in &self.capabilities {
+                    let state = match (capability.compiled, capability.active) {
+                        (false, _) => "not compiled",
+                        (true, false) => "inactive",
+                        (true, true) => "active",
+                    };
+                    println!("  {:<26} {}", capability.name, state);
+                }
+                for name in self.missing(config) {
+                    println!("Note: the configuration enables {} but this build does not include it", name);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configuration_activates_compiled_subsystems() {
+        let mut config = StationConfig::load(Some("/dev/null")).unwrap();
+        config.mqtt.broker = Some("broker.local".to_string());
+        config.mqtt.tls = true;
+        let report = CapabilityReport::new(&config);
+        let find = |name| report.capabilities.iter().find(|capability| capability.name == name).unwrap();
+
+        assert!(find("dht11").active && !find("bme280").active);
+        assert!(find("mqtt").active && !find("http_api").active);
+        assert_eq!(find("mqtt_tls").active, cfg!(feature = "tls"));
+        assert_eq!(report.missing(&config).is_empty(), cfg!(feature = "tls"));
+    }
+}
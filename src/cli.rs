@@ -0,0 +1,389 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use dht11::emulator::Dht11EmulatorFault;
+use dht11::Dht11Config;
+use rumqttc::QoS;
+use weather_math::ComfortIndex;
+use crate::config::{self, StationConfig};
+use crate::error::StationError;
+use crate::mqtt::{self, ReportByException, TopicTree};
+use crate::units::TemperatureUnit;
+
+/// In-house weather station. Without a subcommand the station samples the sensor
+/// like `run` does.
+#[derive(Parser)]
+#[command(name = "weather_station", args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    /// Configuration file, `config.toml` in the working directory by default.
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+
+    /// Format of log events on stderr, verbosity is set with `RUST_LOG`.
+    #[arg(long, value_enum, global = true, default_value_t)]
+    pub log_format: OutputFormat,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Performs a single readout and exits.
+    Read(ReadArgs),
+    /// Samples the sensor every interval until stopped.
+    Run(Box<RunArgs>),
+    /// Dumps stored readings to stdout or a file.
+    Export(ExportArgs),
+    /// Checks exports and CSV logs against their `.sig` signatures.
+    Verify(VerifyArgs),
+    /// Verifies GPIO access and sensor connectivity.
+    Selftest(SelftestArgs),
+    /// Lists the optional subsystems built in and which the configuration enables.
+    Features(FeaturesArgs),
+    /// Answers readouts of a DHT11 reader on another pin or Pi with fixed values.
+    EmulateDht11(EmulateArgs),
+    /// Checks that an input pin jumpered to an output pin follows it.
+    LoopbackTest(LoopbackArgs),
+    /// Logs the measured pulse durations of DHT11 readouts, for diagnosing flaky sensors.
+    RawReadout(RawReadoutArgs),
+    /// Writes and enables a systemd service running the station.
+    InstallService(InstallServiceArgs),
+}
+
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Args)]
+pub struct ReadArgs {
+    #[arg(long)]
+    pub pin: Option<u8>,
+
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+fn parse_comfort_index(value: &str) -> Result<ComfortIndex, String> {
+    config::parse_comfort_index(value).ok_or_else(|| "expected humidex or thw".to_string())
+}
+
+fn parse_qos(value: &str) -> Result<QoS, String> {
+    mqtt::parse_qos(value).ok_or_else(|| "expected 0, 1 or 2".to_string())
+}
+
+fn parse_temperature_unit(value: &str) -> Result<TemperatureUnit, String> {
+    TemperatureUnit::parse(value).ok_or_else(|| "expected celsius or fahrenheit".to_string())
+}
+
+fn parse_topic_tree(value: &str) -> Result<TopicTree, String> {
+    TopicTree::parse(value).ok_or_else(|| "expected <prefix>=celsius|fahrenheit".to_string())
+}
+
+/// Overrides of the configuration file for the sampling loop.
+#[derive(Args, Default)]
+pub struct RunArgs {
+    /// Sample a single sensor on this pin instead of the configured ones.
+    #[arg(long)]
+    pub pin: Option<u8>,
+
+    /// Sampling interval in seconds, at least 1 for a DHT11 and 2 for a DHT22.
+    #[arg(long)]
+    pub interval: Option<u64>,
+
+    /// Sample on wall clock multiples of the interval (:00, :05, :10...).
+    #[arg(long)]
+    pub align_to_clock: bool,
+
+    /// Time DHT11 pulses with GPIO interrupts instead of busy polling.
+    #[arg(long)]
+    pub edge_capture: bool,
+
+    #[arg(long, value_parser = parse_comfort_index)]
+    pub comfort_index: Option<ComfortIndex>,
+
+    #[arg(long)]
+    pub database: Option<String>,
+
+    #[arg(long)]
+    pub retention_days: Option<u32>,
+
+    /// Also append readouts to daily CSV files in this directory.
+    #[arg(long)]
+    pub csv_dir: Option<String>,
+
+    /// Serve the HTTP API on this address, e.g. 0.0.0.0:8080.
+    #[arg(long)]
+    pub http: Option<SocketAddr>,
+
+    /// Publish readouts to this MQTT broker, `host` or `host:port`.
+    #[arg(long)]
+    pub mqtt_broker: Option<String>,
+
+    #[arg(long)]
+    pub mqtt_username: Option<String>,
+
+    #[arg(long)]
+    pub mqtt_password: Option<String>,
+
+    #[arg(long, value_parser = parse_qos)]
+    pub mqtt_qos: Option<QoS>,
+
+    #[arg(long)]
+    pub mqtt_retain: bool,
+
+    /// Connect over TLS, port 8883 by default. Needs the `tls` feature.
+    #[arg(long)]
+    pub mqtt_tls: bool,
+
+    /// CA certificate (PEM) the broker is verified against instead of the system ones.
+    #[arg(long)]
+    pub mqtt_ca_file: Option<String>,
+
+    #[arg(long)]
+    pub mqtt_topic_prefix: Option<String>,
+
+    #[arg(long, value_parser = parse_temperature_unit)]
+    pub mqtt_temperature_unit: Option<TemperatureUnit>,
+
+    /// Also publish to `<prefix>=celsius|fahrenheit`, repeatable.
+    #[arg(long, value_parser = parse_topic_tree)]
+    pub mqtt_mirror: Vec<TopicTree>,
+
+    /// Announce the sensors to Home Assistant.
+    #[arg(long)]
+    pub mqtt_ha_discovery: bool,
+
+    #[arg(long)]
+    pub mqtt_discovery_prefix: Option<String>,
+
+    /// Only republish changed values, and unchanged ones after this many seconds.
+    #[arg(long)]
+    pub mqtt_max_silence: Option<u64>,
+}
+
+impl RunArgs {
+    /// Applies the flags given on the command line on top of `config`,
+    /// validated like the keys they override.
+    pub fn apply(self, config: &mut StationConfig) -> Result<(), StationError> {
+        if let Some(pin) = self.pin {
+            config.use_pin(pin);
+        }
+        if let Some(interval) = self.interval {
+            config.interval = config::check_interval("--interval", interval, config.dht11.sensor_type).map_err(StationError::Config)?;
+        }
+        config.align_to_clock |= self.align_to_clock;
+        config.edge_capture |= self.edge_capture;
+        config.comfort_index = self.comfort_index.or(config.comfort_index);
+        if let Some(database) = self.database {
+            config.database_path = database;
+        }
+        if let Some(retention_days) = self.retention_days {
+            config.retention_days = retention_days;
+        }
+        config.csv_directory = self.csv_dir.or(config.csv_directory.take());
+        config.http_address = self.http.or(config.http_address);
+
+        let mqtt = &mut config.mqtt;
+        mqtt.broker = self.mqtt_broker.or(mqtt.broker.take());
+        mqtt.username = self.mqtt_username.or(mqtt.username.take());
+        mqtt.password = self.mqtt_password.or(mqtt.password.take());
+        mqtt.qos = self.mqtt_qos.unwrap_or(mqtt.qos);
+        mqtt.retain |= self.mqtt_retain;
+        mqtt.tls |= self.mqtt_tls;
+        mqtt.ca_file = self.mqtt_ca_file.or(mqtt.ca_file.take());
+        if let Some(topic_prefix) = self.mqtt_topic_prefix {
+            mqtt.topic_prefix = topic_prefix;
+        }
+        mqtt.temperature_unit = self.mqtt_temperature_unit.unwrap_or(mqtt.temperature_unit);
+        mqtt.mirrors.extend(self.mqtt_mirror);
+        if self.mqtt_ha_discovery {
+            mqtt.enable_discovery();
+        }
+        if self.mqtt_discovery_prefix.is_some() {
+            mqtt.discovery_prefix = self.mqtt_discovery_prefix;
+        }
+        if let Some(max_silence) = self.mqtt_max_silence.map(Duration::from_secs) {
+            match &mut mqtt.report_by_exception {
+                Some(exception) => exception.max_silence = max_silence,
+                None => mqtt.report_by_exception = Some(ReportByException { deltas: Default::default(), max_silence }),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Unix seconds, 24 hours before `--to` by default.
+    #[arg(long)]
+    pub from: Option<i64>,
+
+    /// Unix seconds, now by default.
+    #[arg(long)]
+    pub to: Option<i64>,
+
+    #[arg(long)]
+    pub database: Option<String>,
+
+    /// `text` is CSV with the same columns as the CSV log.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+
+    /// Writes the readings to this file instead, signed when `signing.key_file`
+    /// is configured.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+
+    /// Hex encoded ed25519 public key the files must be signed with, the
+    /// configured signing key's by default.
+    #[arg(long)]
+    pub public_key: Option<String>,
+}
+
+#[derive(Args)]
+pub struct SelftestArgs {
+    #[arg(long)]
+    pub pin: Option<u8>,
+
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct FeaturesArgs {
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum FaultArg {
+    Checksum,
+    Timeout,
+}
+
+impl From<FaultArg> for Dht11EmulatorFault {
+    fn from(fault: FaultArg) -> Self {
+        match fault {
+            FaultArg::Checksum => Dht11EmulatorFault::BadChecksum,
+            FaultArg::Timeout => Dht11EmulatorFault::NoResponse,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct EmulateArgs {
+    #[arg(long)]
+    pub pin: u8,
+
+    /// Celcius degrees.
+    #[arg(long, default_value_t = 21.5)]
+    pub temp: f64,
+
+    /// Percents.
+    #[arg(long, default_value_t = 40.0)]
+    pub rh: f64,
+
+    #[arg(long, value_enum)]
+    pub fault: Option<FaultArg>,
+}
+
+#[derive(Args)]
+pub struct LoopbackArgs {
+    #[arg(long)]
+    pub output: u8,
+
+    #[arg(long)]
+    pub input: u8,
+}
+
+#[derive(Args)]
+pub struct RawReadoutArgs {
+    #[arg(long)]
+    pub pin: u8,
+
+    /// Number of readouts, 2 s apart.
+    #[arg(long, default_value_t = 1)]
+    pub count: u32,
+
+    /// Time the pulses with GPIO interrupts instead of busy polling.
+    #[arg(long)]
+    pub edge_capture: bool,
+
+    /// Shortest high pulse decoded as a 1, to try thresholds against the logged timings.
+    #[arg(long, default_value_t = Dht11Config::default().bit_threshold_us)]
+    pub bit_threshold_us: u32,
+
+    /// `json` prints one line per readout.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct InstallServiceArgs {
+    /// Install a user service instead of a system one.
+    #[arg(long)]
+    pub user: bool,
+
+    /// User the system service runs as, `$SUDO_USER` by default.
+    #[arg(long, conflicts_with = "user")]
+    pub run_as: Option<String>,
+
+    /// Where the database and config.toml live.
+    #[arg(long)]
+    pub state_dir: Option<String>,
+
+    /// Print the unit instead of installing it.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Install without enabling and starting the service.
+    #[arg(long)]
+    pub no_enable: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dht11::Dht11SensorType;
+
+    #[test]
+    fn flags_without_subcommand_run_the_station() {
+        let cli = Cli::try_parse_from(["weather_station", "--interval", "1", "--mqtt-mirror", "legacy=fahrenheit"]).unwrap();
+        assert!(cli.command.is_none());
+
+        let mut config = StationConfig::load(Some("/dev/null")).unwrap();
+        cli.run.apply(&mut config).unwrap();
+        assert_eq!(config.interval, Duration::from_secs(1));
+        assert_eq!(config.mqtt.mirrors[0].prefix, "legacy");
+    }
+
+    #[test]
+    fn interval_flag_is_validated_like_the_config() {
+        let mut config = StationConfig::load(Some("/dev/null")).unwrap();
+        config.dht11 = Dht11Config::new(Dht11SensorType::Dht22);
+        let cli = Cli::try_parse_from(["weather_station", "--interval", "1"]).unwrap();
+        assert_eq!(cli.run.apply(&mut config).unwrap_err().to_string(), "configuration error: invalid --interval = 1, expected at least 2");
+    }
+
+    #[test]
+    fn subcommands_take_their_own_flags() {
+        let cli = Cli::try_parse_from(["weather_station", "export", "--from", "10", "--format", "json"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Export(ExportArgs { from: Some(10), format: OutputFormat::Json, .. }))));
+        assert!(Cli::try_parse_from(["weather_station", "read", "--interval", "5"]).is_err());
+        assert!(Cli::try_parse_from(["weather_station", "verify"]).is_err());
+    }
+}
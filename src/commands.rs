@@ -0,0 +1,169 @@
+//! Commands the station takes over MQTT. Everyone on the broker can publish
+//! to the command topic, so every command carries an HMAC-SHA256 signature with
+//! a secret shared with the sender, a timestamp and a nonce:
+//!
+//! `{"command":"prune","timestamp":1700000000,"nonce":"4f1c...","signature":"<hex>"}`
+//!
+//! The signature covers `<topic>\n<timestamp>\n<nonce>\n<command>`, so a
+//! command can't be replayed to another station or turned into another command.
+
+use std::collections::HashMap;
+use std::fmt;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+/// Longer nonces are rejected, they are kept until they expire.
+const MAX_NONCE_LENGTH: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    /// Deletes readings older than the retention, like the admin area's prune.
+    Prune,
+}
+
+impl Command {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "prune" => Some(Command::Prune),
+            _ => None,
+        }
+    }
+}
+
+/// Why a command was ignored.
+#[derive(Debug, PartialEq)]
+pub enum Rejection {
+    Malformed,
+    BadSignature,
+    /// Signed further in the past or future than the maximum age.
+    Stale,
+    /// The nonce was already used.
+    Replayed,
+    /// Correctly signed but not a command this station knows.
+    Unknown(String),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::Malformed => write!(f, "malformed command"),
+            Rejection::BadSignature => write!(f, "bad signature"),
+            Rejection::Stale => write!(f, "stale timestamp"),
+            Rejection::Replayed => write!(f, "replayed nonce"),
+            Rejection::Unknown(command) => write!(f, "unknown command {}", command),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CommandAuth {
+    pub secret: String,
+    ///
+    /// # Unit
+    /// Seconds a command stays valid after it was signed, also the clock skew tolerated.
+    pub max_age_s: u64,
+}
+
+#[derive(Deserialize)]
+struct SignedCommand {
+    command: String,
+    timestamp: i64,
+    nonce: String,
+    signature: String,
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Hmac<Sha256> {
+    // HMAC takes keys of any length.
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC key of any length");
+    mac.update(message);
+    mac
+}
+
+/// Checks commands and remembers the nonces of accepted ones until they expire.
+pub struct CommandVerifier {
+    auth: CommandAuth,
+    seen_nonces: HashMap<String, i64>,
+}
+
+impl CommandVerifier {
+    pub fn new(auth: CommandAuth) -> Self {
+        CommandVerifier { auth, seen_nonces: HashMap::new() }
+    }
+
+    /// Signature of `command` published to `topic`, hex encoded in the payload.
+    fn mac(&self, topic: &str, command: &str, timestamp: i64, nonce: &str) -> Hmac<Sha256> {
+        let message = format!("{}\n{}\n{}\n{}", topic, timestamp, nonce, command);
+        hmac_sha256(self.auth.secret.as_bytes(), message.as_bytes())
+    }
+
+    /// The signature is checked first, so unauthenticated senders can't fill the
+    /// nonce memory.
+    pub fn verify(&mut self, topic: &str, payload: &[u8], now: i64) -> Result<Command, Rejection> {
+        let signed: SignedCommand = serde_json::from_slice(payload).map_err(|_| Rejection::Malformed)?;
+        if signed.nonce.is_empty() || signed.nonce.len() > MAX_NONCE_LENGTH {
+            return Err(Rejection::Malformed);
+        }
+        let signature = hex::decode(&signed.signature).map_err(|_| Rejection::BadSignature)?;
+        // Compares in constant time, so response times don't leak the signature.
+        if self.mac(topic, &signed.command, signed.timestamp, &signed.nonce).verify_slice(&signature).is_err() {
+            return Err(Rejection::BadSignature);
+        }
+        let max_age_s = self.auth.max_age_s as i64;
+        if (now - signed.timestamp).abs() > max_age_s {
+            return Err(Rejection::Stale);
+        }
+        // Expired nonces can't be replayed anyway, their timestamps are stale by now.
+        self.seen_nonces.retain(|_, timestamp| (now - *timestamp).abs() <= max_age_s);
+        if self.seen_nonces.insert(signed.nonce, signed.timestamp).is_some() {
+            return Err(Rejection::Replayed);
+        }
+        Command::parse(&signed.command).ok_or(Rejection::Unknown(signed.command))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOPIC: &str = "weather/command";
+
+    fn payload(verifier: &CommandVerifier, command: &str, timestamp: i64, nonce: &str) -> Vec<u8> {
+        let signature = hex::encode(verifier.mac(TOPIC, command, timestamp, nonce).finalize().into_bytes());
+        serde_json::json!({ "command": command, "timestamp": timestamp, "nonce": nonce, "signature": signature }).to_string().into_bytes()
+    }
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        let vectors: [(&[u8], &[u8], &str); 2] = [
+            (b"Jefe", b"what do ya want for nothing?", "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"),
+            (&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First", "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"),
+        ];
+        for (key, message, expected) in vectors {
+            assert_eq!(hex::encode(hmac_sha256(key, message).finalize().into_bytes()), expected);
+            assert!(hmac_sha256(key, message).verify_slice(&hex::decode(expected).unwrap()).is_ok());
+        }
+    }
+
+    #[test]
+    fn only_fresh_signed_commands_are_accepted() {
+        let now = 1_700_000_000;
+        let mut verifier = CommandVerifier::new(CommandAuth { secret: "correct horse battery".to_string(), max_age_s: 60 });
+
+        let command = payload(&verifier, "prune", now - 5, "a1");
+        assert_eq!(verifier.verify(TOPIC, &command, now), Ok(Command::Prune));
+        assert_eq!(verifier.verify(TOPIC, &command, now + 10), Err(Rejection::Replayed));
+        assert_eq!(verifier.verify("other/command", &command, now), Err(Rejection::BadSignature));
+
+        assert_eq!(verifier.verify(TOPIC, &payload(&verifier, "prune", now - 61, "a2"), now), Err(Rejection::Stale));
+        assert_eq!(verifier.verify(TOPIC, &payload(&verifier, "reset_rain", now, "a3"), now), Err(Rejection::Unknown("reset_rain".to_string())));
+        assert_eq!(verifier.verify(TOPIC, b"prune", now), Err(Rejection::Malformed));
+
+        let forged = String::from_utf8(payload(&verifier, "prune", now, "a4")).unwrap().replace("a4", "a5");
+        assert_eq!(verifier.verify(TOPIC, forged.as_bytes(), now), Err(Rejection::BadSignature));
+
+        // Expired nonces are forgotten.
+        assert_eq!(verifier.verify(TOPIC, &payload(&verifier, "prune", now + 100, "a6"), now + 100), Ok(Command::Prune));
+        assert!(!verifier.seen_nonces.contains_key("a1"));
+    }
+}
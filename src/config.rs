@@ -0,0 +1,982 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use bme280::BME280_DEFAULT_ADDRESS;
+use dht11::{Dht11Config, Dht11RetryPolicy, Dht11SensorType};
+use ds18b20::{ds18b20_is_rom_id, DS18B20_MAX_RESOLUTION, DS18B20_MIN_RESOLUTION};
+use leaf_wetness::LeafWetnessCalibration;
+use pyranometer::PyranometerCalibration;
+use sensor::Quantity;
+use serde::Deserialize;
+use weather_math::ComfortIndex;
+use crate::dashboard::{self, ChartType, DashboardLayout, Panel, Threshold};
+use crate::commands::CommandAuth;
+use crate::connectivity::{self, ConnectivityConfig};
+use crate::error::StationError;
+use crate::evapotranspiration::{GpsConfig, Location};
+use crate::kiosk::KioskConfig;
+use crate::mqtt::{self, MqttConfig, ReportByException, TopicTree};
+use crate::precision::Precision;
+use crate::public::PublicProfile;
+use crate::units::TemperatureUnit;
+
+pub const DEFAULT_CONFIG_PATH: &str = "config.toml";
+/// Highest GPIO number on the Raspberry Pi header.
+const MAX_GPIO_PIN: u8 = 27;
+/// Sensor id used when no `[[sensors]]` are configured, as stored by earlier versions.
+const DEFAULT_SENSOR_NAME: &str = "dht11";
+/// Shorter shared secrets could be guessed from a captured signed command.
+const MIN_COMMAND_SECRET_LENGTH: usize = 16;
+const DEFAULT_COMMAND_MAX_AGE_S: u64 = 60;
+/// Probes take up to seconds each when offline.
+const MIN_PROBE_INTERVAL_S: u64 = 10;
+/// Wetness in % at which a leaf counts as wet, where most calibrations put it.
+const LEAF_WETNESS_DEFAULT_THRESHOLD: f64 = 50.0;
+/// JSN-SR04T range, the HC-SR04 only reaches 400 cm.
+const ULTRASONIC_MAX_RANGE_CM: f64 = 600.0;
+/// The header's UART, where GPS HATs are wired.
+const GPS_DEFAULT_DEVICE: &str = "/dev/serial0";
+/// What NMEA 0183 modules send at unless reconfigured.
+const GPS_DEFAULT_BAUD_RATE: u32 = 9600;
+
+/// Layout of `config.toml`, every key is optional.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    sensor: SensorSection,
+    sensors: Vec<SensorEntry>,
+    storage: StorageSection,
+    csv: Option<CsvSection>,
+    http: Option<HttpSection>,
+    mqtt: Option<MqttSection>,
+    signing: Option<SigningSection>,
+    public: Option<PublicSection>,
+    dashboard: Option<DashboardSection>,
+    kiosk: Option<KioskSection>,
+    connectivity: Option<ConnectivitySection>,
+    location: Option<LocationSection>,
+    gps: Option<GpsSection>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SensorSection {
+    pin: u8,
+    model: String,
+    interval_s: u64,
+    align_to_clock: bool,
+    max_attempts: u32,
+    retry_delay_s: u64,
+    edge_capture: bool,
+    /// The model's start signal length when not set.
+    start_time_us: Option<u32>,
+    timeout_us: u32,
+    bit_threshold_us: u32,
+    comfort_index: Option<String>,
+}
+
+impl Default for SensorSection {
+    fn default() -> Self {
+        let timings = Dht11Config::default();
+        let retries = Dht11RetryPolicy::default();
+        SensorSection {
+            pin: 23,
+            model: "dht11".to_string(),
+            interval_s: 60,
+            align_to_clock: false,
+            max_attempts: retries.max_attempts,
+            retry_delay_s: Duration::from_micros(retries.retry_delay_us.into()).as_secs(),
+            edge_capture: false,
+            start_time_us: None,
+            timeout_us: timings.timeout_us,
+            bit_threshold_us: timings.bit_threshold_us,
+            comfort_index: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SensorEntry {
+    name: String,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    pin: Option<u8>,
+    address: Option<u8>,
+    rom_id: Option<String>,
+    resolution: Option<u8>,
+    adc_device: Option<u8>,
+    adc_channel: Option<u8>,
+    full_scale_raw: Option<u16>,
+    full_scale_mv: Option<f64>,
+    sensitivity: Option<f64>,
+    offset_mv: Option<f64>,
+    dry_raw: Option<u16>,
+    wet_raw: Option<u16>,
+    wet_threshold: Option<f64>,
+    trigger_pin: Option<u8>,
+    echo_pin: Option<u8>,
+    baseline_cm: Option<f64>,
+    temperature_sensor: Option<String>,
+    location: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct StorageSection {
+    database: String,
+    retention_days: u32,
+}
+
+impl Default for StorageSection {
+    fn default() -> Self {
+        StorageSection { database: "weather_station.db".to_string(), retention_days: 365 }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CsvSection {
+    directory: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SigningSection {
+    key_file: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PublicSection {
+    #[serde(default)]
+    exclude_sensors: Vec<String>,
+    metrics: Option<Vec<String>>,
+    decimals: Option<usize>,
+    time_resolution_s: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThresholdEntry {
+    value: f64,
+    color: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PanelEntry {
+    title: Option<String>,
+    metric: String,
+    #[serde(default)]
+    sensors: Vec<String>,
+    chart: Option<String>,
+    range_h: Option<u32>,
+    #[serde(default)]
+    thresholds: Vec<ThresholdEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DashboardSection {
+    #[serde(default)]
+    panels: Vec<PanelEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LocationSection {
+    latitude: f64,
+    #[serde(default)]
+    elevation_m: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GpsSection {
+    device: Option<String>,
+    baud_rate: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConnectivitySection {
+    target: Option<String>,
+    probe: Option<bool>,
+    interval_s: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KioskSection {
+    utc_offset_h: Option<f64>,
+    night_start_h: Option<u32>,
+    night_end_h: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HttpSection {
+    address: String,
+    admin_password: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MirrorSection {
+    prefix: String,
+    temperature_unit: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReportByExceptionSection {
+    max_silence_s: u64,
+    #[serde(default)]
+    deltas: HashMap<String, f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CommandsSection {
+    secret: String,
+    max_age_s: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MqttSection {
+    broker: String,
+    username: Option<String>,
+    password: Option<String>,
+    qos: Option<u8>,
+    #[serde(default)]
+    retain: bool,
+    #[serde(default)]
+    tls: bool,
+    ca_file: Option<String>,
+    topic_prefix: Option<String>,
+    temperature_unit: Option<String>,
+    #[serde(default)]
+    mirrors: Vec<MirrorSection>,
+    #[serde(default)]
+    ha_discovery: bool,
+    discovery_prefix: Option<String>,
+    commands: Option<CommandsSection>,
+    report_by_exception: Option<ReportByExceptionSection>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SensorKind {
+    /// Bit-banged on a GPIO pin.
+    Dht11 { pin: u8 },
+    /// On the header's I2C bus.
+    Bme280 { address: u8 },
+    /// 1-Wire probe read through the kernel's w1-therm driver, temperature only.
+    /// The probe's resolution is left alone when not set.
+    Ds18b20 { rom_id: String, resolution: Option<u8> },
+    /// Analog pyranometer on a channel of an ADC driven by the kernel's
+    /// industrial I/O subsystem, `/sys/bus/iio/devices/iio:device<adc_device>`.
+    Pyranometer { adc_device: u8, adc_channel: u8, calibration: PyranometerCalibration },
+    /// HC-SR04 or JSN-SR04T ultrasonic distance sensor looking down at the
+    /// ground from `baseline_cm` above it, for snow depth. The speed of sound is
+    /// compensated with the temperature of `temperature_sensor`.
+    Ultrasonic { trigger_pin: u8, echo_pin: u8, baseline_cm: f64, temperature_sensor: Option<String> },
+    /// Resistive leaf wetness sensor on a channel of an industrial I/O ADC.
+    LeafWetness { adc_device: u8, adc_channel: u8, calibration: LeafWetnessCalibration },
+}
+
+impl SensorKind {
+    /// GPIO pins the sensor claims.
+    fn pins(&self) -> Vec<u8> {
+        match self {
+            SensorKind::Dht11 { pin } => vec![*pin],
+            SensorKind::Ultrasonic { trigger_pin, echo_pin, .. } => vec![*trigger_pin, *echo_pin],
+            SensorKind::Bme280 { .. } | SensorKind::Ds18b20 { .. } | SensorKind::Pyranometer { .. } | SensorKind::LeafWetness { .. } => Vec::new(),
+        }
+    }
+
+    /// (device, channel) of the industrial I/O ADC the sensor is read through.
+    fn adc_channel(&self) -> Option<(u8, u8)> {
+        match self {
+            SensorKind::Pyranometer { adc_device, adc_channel, .. } | SensorKind::LeafWetness { adc_device, adc_channel, .. } => Some((*adc_device, *adc_channel)),
+            _ => None,
+        }
+    }
+
+    fn measures_temperature(&self) -> bool {
+        matches!(self, SensorKind::Dht11 { .. } | SensorKind::Bme280 { .. } | SensorKind::Ds18b20 { .. })
+    }
+}
+
+/// One sensor of the station.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SensorConfig {
+    /// Sensor id stored with every reading and used in MQTT topics and metric labels.
+    pub name: String,
+    pub kind: SensorKind,
+    /// Free-form tag such as `living room` or `north wall`.
+    pub location: Option<String>,
+}
+
+impl SensorConfig {
+    /// The only sensor of a station configured with `sensor.pin` or `--pin`.
+    pub fn single(pin: u8) -> Self {
+        SensorConfig { name: DEFAULT_SENSOR_NAME.to_string(), kind: SensorKind::Dht11 { pin }, location: None }
+    }
+}
+
+/// Validated station settings, from `config.toml` with command line overrides on top.
+pub struct StationConfig {
+    /// Never empty, read one after another every interval.
+    pub sensors: Vec<SensorConfig>,
+    pub interval: Duration,
+    /// Sample on multiples of `interval` since the epoch instead of relative to the start.
+    pub align_to_clock: bool,
+    pub retry_policy: Dht11RetryPolicy,
+    /// Time DHT11 pulses with GPIO interrupts instead of busy polling the pin.
+    pub edge_capture: bool,
+    /// Protocol timings of DHT11 readouts.
+    pub dht11: Dht11Config,
+    pub comfort_index: Option<ComfortIndex>,
+    pub database_path: String,
+    pub retention_days: u32,
+    pub csv_directory: Option<String>,
+    /// ed25519 key exports and finished CSV log files are signed with.
+    pub signing_key_file: Option<String>,
+    pub http_address: Option<SocketAddr>,
+    /// Enables the admin area and protects the full API, see `http_api`.
+    pub http_admin_password: Option<String>,
+    /// Applied to readings served on the public routes.
+    pub public: PublicProfile,
+    pub dashboard: DashboardLayout,
+    pub kiosk: KioskConfig,
+    pub connectivity: ConnectivityConfig,
+    /// Enables the daily reference evapotranspiration, never served publicly.
+    pub location: Option<Location>,
+    /// Where `location` comes from when it is not configured.
+    pub gps: Option<GpsConfig>,
+    pub mqtt: MqttConfig,
+}
+
+pub fn parse_comfort_index(value: &str) -> Option<ComfortIndex> {
+    match value {
+        "humidex" => Some(ComfortIndex::Humidex),
+        "thw" => Some(ComfortIndex::Thw),
+        _ => None,
+    }
+}
+
+fn invalid(key: &str, value: impl std::fmt::Display, expected: &str) -> String {
+    format!("invalid {} = {}, expected {}", key, value, expected)
+}
+
+pub fn parse_sensor_model(value: &str) -> Option<Dht11SensorType> {
+    match value {
+        "dht11" => Some(Dht11SensorType::Dht11),
+        "dht22" | "am2302" => Some(Dht11SensorType::Dht22),
+        _ => None,
+    }
+}
+
+/// Rejects an interval shorter than `model`'s minimum sampling period, the
+/// same for `sensor.interval_s` and `--interval`.
+pub fn check_interval(key: &str, interval_s: u64, model: Dht11SensorType) -> Result<Duration, String> {
+    let minimum = Duration::from_micros(model.min_sampling_period_us().into());
+    let interval = Duration::from_secs(interval_s);
+    if interval < minimum {
+        return Err(invalid(key, interval_s, &format!("at least {}", minimum.as_secs())));
+    }
+    Ok(interval)
+}
+
+fn parse_temperature_unit(key: &str, value: &str) -> Result<TemperatureUnit, String> {
+    TemperatureUnit::parse(value).ok_or_else(|| invalid(key, value, "\"celsius\" or \"fahrenheit\""))
+}
+
+fn convert_mqtt(section: MqttSection) -> Result<MqttConfig, String> {
+    let mut config = MqttConfig {
+        broker: Some(section.broker),
+        username: section.username,
+        password: section.password,
+        retain: section.retain,
+        tls: section.tls,
+        ca_file: section.ca_file,
+        ..MqttConfig::default()
+    };
+    if let Some(qos) = section.qos {
+        config.qos = mqtt::parse_qos(&qos.to_string()).ok_or_else(|| invalid("mqtt.qos", qos, "0, 1 or 2"))?;
+    }
+    if let Some(topic_prefix) = section.topic_prefix {
+        config.topic_prefix = topic_prefix;
+    }
+    if let Some(unit) = section.temperature_unit {
+        config.temperature_unit = parse_temperature_unit("mqtt.temperature_unit", &unit)?;
+    }
+    for mirror in section.mirrors {
+        let temperature_unit = parse_temperature_unit("mqtt.mirrors.temperature_unit", &mirror.temperature_unit)?;
+        config.mirrors.push(TopicTree { prefix: mirror.prefix, temperature_unit });
+    }
+    if section.ha_discovery {
+        config.enable_discovery();
+    }
+    if section.discovery_prefix.is_some() {
+        config.discovery_prefix = section.discovery_prefix;
+    }
+    if let Some(exception) = section.report_by_exception {
+        if let Some((name, delta)) = exception.deltas.iter().find(|(_, delta)| **delta < 0.0) {
+            return Err(invalid(&format!("mqtt.report_by_exception.deltas.{}", name), delta, "a non-negative number"));
+        }
+        config.report_by_exception = Some(ReportByException { deltas: exception.deltas, max_silence: Duration::from_secs(exception.max_silence_s) });
+    }
+    if let Some(commands) = section.commands {
+        if commands.secret.len() < MIN_COMMAND_SECRET_LENGTH {
+            return Err(invalid("mqtt.commands.secret", "(hidden)", &format!("at least {} characters", MIN_COMMAND_SECRET_LENGTH)));
+        }
+        let max_age_s = commands.max_age_s.unwrap_or(DEFAULT_COMMAND_MAX_AGE_S);
+        if max_age_s == 0 {
+            return Err(invalid("mqtt.commands.max_age_s", max_age_s, "at least 1"));
+        }
+        config.commands = Some(CommandAuth { secret: commands.secret, max_age_s });
+    }
+    Ok(config)
+}
+
+fn check_pin(key: &str, pin: u8) -> Result<(), String> {
+    if pin > MAX_GPIO_PIN {
+        return Err(invalid(key, pin, &format!("a GPIO number from 0 to {}", MAX_GPIO_PIN)));
+    }
+    Ok(())
+}
+
+/// Names end up in MQTT topics and metric labels, so they are restricted to
+/// characters that are safe in both.
+fn is_valid_sensor_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn convert_sensor_kind(entry: &SensorEntry) -> Result<SensorKind, String> {
+    match entry.kind.as_deref().unwrap_or("dht11") {
+        "dht11" => {
+            let pin = entry.pin.ok_or_else(|| format!("sensor {} needs a pin", entry.name))?;
+            check_pin("sensors.pin", pin)?;
+            Ok(SensorKind::Dht11 { pin })
+        }
+        "bme280" => {
+            let address = entry.address.unwrap_or(BME280_DEFAULT_ADDRESS);
+            if address != 0x76 && address != 0x77 {
+                return Err(invalid("sensors.address", format!("{:#04x}", address), "0x76 or 0x77"));
+            }
+            Ok(SensorKind::Bme280 { address })
+        }
+        "ds18b20" => {
+            let rom_id = entry.rom_id.clone().ok_or_else(|| format!("sensor {} needs a rom_id", entry.name))?;
+            if !ds18b20_is_rom_id(&rom_id) {
+                return Err(invalid("sensors.rom_id", &rom_id, "28- followed by 12 hex digits, see /sys/bus/w1/devices"));
+            }
+            if let Some(bits) = entry.resolution.filter(|bits| !(DS18B20_MIN_RESOLUTION..=DS18B20_MAX_RESOLUTION).contains(bits)) {
+                return Err(invalid("sensors.resolution", bits, "9 to 12 bits"));
+            }
+            Ok(SensorKind::Ds18b20 { rom_id, resolution: entry.resolution })
+        }
+        "pyranometer" => {
+            let adc_channel = entry.adc_channel.ok_or_else(|| format!("sensor {} needs an adc_channel", entry.name))?;
+            // Both ends of the ADC range and the certificate's sensitivity, no defaults fit every sensor.
+            let positive = |key: &str, value: Option<f64>| {
+                let value = value.ok_or_else(|| format!("sensor {} needs {}", entry.name, key))?;
+                if value > 0.0 {
+                    Ok(value)
+                } else {
+                    Err(invalid(&format!("sensors.{}", key), value, "a positive number"))
+                }
+            };
+            let calibration = PyranometerCalibration {
+                full_scale_raw: positive("full_scale_raw", entry.full_scale_raw.map(f64::from))? as u16,
+                full_scale_mv: positive("full_scale_mv", entry.full_scale_mv)?,
+                sensitivity: positive("sensitivity", entry.sensitivity)?,
+                offset_mv: entry.offset_mv.unwrap_or(0.0),
+            };
+            Ok(SensorKind::Pyranometer { adc_device: entry.adc_device.unwrap_or(0), adc_channel, calibration })
+        }
+        "ultrasonic" => {
+            let trigger_pin = entry.trigger_pin.ok_or_else(|| format!("sensor {} needs a trigger_pin", entry.name))?;
+            let echo_pin = entry.echo_pin.ok_or_else(|| format!("sensor {} needs an echo_pin", entry.name))?;
+            check_pin("sensors.trigger_pin", trigger_pin)?;
+            check_pin("sensors.echo_pin", echo_pin)?;
+            if echo_pin == trigger_pin {
+                return Err(invalid("sensors.echo_pin", echo_pin, "a pin other than trigger_pin"));
+            }
+            let baseline_cm = entry.baseline_cm.ok_or_else(|| format!("sensor {} needs a baseline_cm", entry.name))?;
+            if !(baseline_cm > 0.0 && baseline_cm <= ULTRASONIC_MAX_RANGE_CM) {
+                return Err(invalid("sensors.baseline_cm", baseline_cm, &format!("a distance up to {} cm", ULTRASONIC_MAX_RANGE_CM)));
+            }
+            Ok(SensorKind::Ultrasonic { trigger_pin, echo_pin, baseline_cm, temperature_sensor: entry.temperature_sensor.clone() })
+        }
+        "leaf_wetness" => {
+            let adc_channel = entry.adc_channel.ok_or_else(|| format!("sensor {} needs an adc_channel", entry.name))?;
+            let dry_raw = entry.dry_raw.ok_or_else(|| format!("sensor {} needs dry_raw", entry.name))?;
+            let wet_raw = entry.wet_raw.ok_or_else(|| format!("sensor {} needs wet_raw", entry.name))?;
+            if wet_raw == dry_raw {
+                return Err(invalid("sensors.wet_raw", wet_raw, "a raw value other than dry_raw"));
+            }
+            let wet_threshold = entry.wet_threshold.unwrap_or(LEAF_WETNESS_DEFAULT_THRESHOLD);
+            if !(0.0..=100.0).contains(&wet_threshold) {
+                return Err(invalid("sensors.wet_threshold", wet_threshold, "0 to 100 %"));
+            }
+            let calibration = LeafWetnessCalibration { dry_raw, wet_raw, wet_threshold };
+            Ok(SensorKind::LeafWetness { adc_device: entry.adc_device.unwrap_or(0), adc_channel, calibration })
+        }
+        other => Err(invalid("sensors.type", other, "\"dht11\", \"bme280\", \"ds18b20\", \"pyranometer\", \"ultrasonic\" or \"leaf_wetness\"")),
+    }
+}
+
+fn convert_sensors(entries: Vec<SensorEntry>) -> Result<Vec<SensorConfig>, String> {
+    let mut names = HashSet::new();
+    let mut sensors: Vec<SensorConfig> = Vec::new();
+    for entry in entries {
+        if !is_valid_sensor_name(&entry.name) {
+            return Err(invalid("sensors.name", &entry.name, "letters, digits, '_' or '-'"));
+        }
+        if !names.insert(entry.name.clone()) {
+            return Err(invalid("sensors.name", &entry.name, "a name not used by another sensor"));
+        }
+        let kind = convert_sensor_kind(&entry)?;
+        if let Some(pin) = kind.pins().into_iter().find(|pin| sensors.iter().any(|sensor| sensor.kind.pins().contains(pin))) {
+            return Err(invalid("sensors.pin", pin, "a pin not used by another sensor"));
+        }
+        if let Some((_, channel)) = kind.adc_channel().filter(|adc| sensors.iter().any(|sensor| sensor.kind.adc_channel() == Some(*adc))) {
+            return Err(invalid("sensors.adc_channel", channel, "a channel not used by another sensor"));
+        }
+        let conflict = sensors.iter().find_map(|sensor| match (&sensor.kind, &kind) {
+            (SensorKind::Bme280 { address }, SensorKind::Bme280 { address: other }) if address == other => {
+                Some(invalid("sensors.address", format!("{:#04x}", address), "an address not used by another sensor"))
+            }
+            (SensorKind::Ds18b20 { rom_id, .. }, SensorKind::Ds18b20 { rom_id: other, .. }) if rom_id == other => {
+                Some(invalid("sensors.rom_id", rom_id, "a probe not used by another sensor"))
+            }
+            _ => None,
+        });
+        if let Some(conflict) = conflict {
+            return Err(conflict);
+        }
+        sensors.push(SensorConfig { name: entry.name, kind, location: entry.location });
+    }
+
+    // Ultrasonic sensors compensate with the first thermometer unless told otherwise.
+    let thermometers: Vec<String> = sensors.iter().filter(|sensor| sensor.kind.measures_temperature()).map(|sensor| sensor.name.clone()).collect();
+    for sensor in &mut sensors {
+        if let SensorKind::Ultrasonic { temperature_sensor, .. } = &mut sensor.kind {
+            match temperature_sensor {
+                Some(name) if !thermometers.contains(name) => {
+                    return Err(invalid("sensors.temperature_sensor", name, "the name of a configured sensor measuring temperature"));
+                }
+                Some(_) => {}
+                None => *temperature_sensor = thermometers.first().cloned(),
+            }
+        }
+    }
+    Ok(sensors)
+}
+
+fn convert_public(section: PublicSection, sensors: &[SensorConfig]) -> Result<PublicProfile, String> {
+    let mut profile = PublicProfile::default();
+    if let Some(name) = section.exclude_sensors.iter().find(|name| !sensors.iter().any(|sensor| sensor.name == **name)) {
+        return Err(invalid("public.exclude_sensors", name, "the name of a configured sensor"));
+    }
+    profile.exclude_sensors = section.exclude_sensors;
+    if let Some(metrics) = section.metrics {
+        profile.metrics = metrics
+            .iter()
+            .map(|name| Quantity::parse(name).ok_or_else(|| invalid("public.metrics", name, "\"temperature\", \"humidity\", \"pressure\", \"irradiance\", \"snow_depth\" or \"leaf_wetness\"")))
+            .collect::<Result<_, _>>()?;
+    }
+    if let Some(decimals) = section.decimals {
+        profile.precision = Precision { decimals };
+    }
+    if let Some(time_resolution_s) = section.time_resolution_s {
+        if time_resolution_s < 1 {
+            return Err(invalid("public.time_resolution_s", time_resolution_s, "at least 1"));
+        }
+        profile.time_resolution_s = time_resolution_s;
+    }
+    Ok(profile)
+}
+
+fn convert_location(section: LocationSection) -> Result<Location, String> {
+    if !(-90.0..=90.0).contains(&section.latitude) {
+        return Err(invalid("location.latitude", section.latitude, "-90 to 90"));
+    }
+    Ok(Location { latitude: section.latitude, elevation_m: section.elevation_m })
+}
+
+fn convert_gps(section: GpsSection) -> Result<GpsConfig, String> {
+    let baud_rate = section.baud_rate.unwrap_or(GPS_DEFAULT_BAUD_RATE);
+    if baud_rate == 0 {
+        return Err(invalid("gps.baud_rate", baud_rate, "a positive baud rate"));
+    }
+    Ok(GpsConfig { device: section.device.unwrap_or_else(|| GPS_DEFAULT_DEVICE.to_string()), baud_rate })
+}
+
+fn convert_connectivity(section: ConnectivitySection) -> Result<ConnectivityConfig, String> {
+    let mut config = ConnectivityConfig::default();
+    if let Some(target) = section.target {
+        if !connectivity::is_probe_target(&target) {
+            return Err(invalid("connectivity.target", &target, "<host>:<port>"));
+        }
+        config.target = Some(target);
+    }
+    if section.probe == Some(false) {
+        config.target = None;
+    }
+    if let Some(interval_s) = section.interval_s {
+        if interval_s < MIN_PROBE_INTERVAL_S {
+            return Err(invalid("connectivity.interval_s", interval_s, &format!("at least {}", MIN_PROBE_INTERVAL_S)));
+        }
+        config.interval = Duration::from_secs(interval_s);
+    }
+    Ok(config)
+}
+
+fn convert_kiosk(section: KioskSection) -> Result<KioskConfig, String> {
+    let mut kiosk = KioskConfig::default();
+    if let Some(offset) = section.utc_offset_h {
+        if !(-12.0..=14.0).contains(&offset) {
+            return Err(invalid("kiosk.utc_offset_h", offset, "-12 to 14"));
+        }
+        kiosk.utc_offset_s = (offset * 3600.0).round() as i64;
+    }
+    for (key, hour, field) in [("kiosk.night_start_h", section.night_start_h, &mut kiosk.night_start_h), ("kiosk.night_end_h", section.night_end_h, &mut kiosk.night_end_h)] {
+        match hour {
+            Some(hour) if hour > 23 => return Err(invalid(key, hour, "0 to 23")),
+            Some(hour) => *field = hour,
+            None => {}
+        }
+    }
+    Ok(kiosk)
+}
+
+/// The dashboard is built from the public routes, so panels may only show what
+/// the public profile lets through.
+fn convert_panel(entry: PanelEntry, public: &PublicProfile, sensors: &[SensorConfig]) -> Result<Panel, String> {
+    let metric = Quantity::parse(&entry.metric).ok_or_else(|| invalid("dashboard.panels.metric", &entry.metric, "\"temperature\", \"humidity\", \"pressure\", \"irradiance\", \"snow_depth\" or \"leaf_wetness\""))?;
+    if !public.metrics.contains(&metric) {
+        return Err(invalid("dashboard.panels.metric", &entry.metric, "a metric listed in public.metrics"));
+    }
+    if let Some(name) = entry.sensors.iter().find(|name| !sensors.iter().any(|sensor| sensor.name == **name) || public.exclude_sensors.contains(name)) {
+        return Err(invalid("dashboard.panels.sensors", name, "the name of a configured sensor not in public.exclude_sensors"));
+    }
+    let chart = match entry.chart {
+        Some(chart) => ChartType::parse(&chart).ok_or_else(|| invalid("dashboard.panels.chart", &chart, "\"line\", \"bar\" or \"value\""))?,
+        None => ChartType::Line,
+    };
+    let range_h = entry.range_h.unwrap_or(24);
+    if range_h == 0 {
+        return Err(invalid("dashboard.panels.range_h", range_h, "at least 1"));
+    }
+    let mut thresholds = Vec::new();
+    for threshold in entry.thresholds {
+        if !dashboard::is_css_color(&threshold.color) {
+            return Err(invalid("dashboard.panels.thresholds.color", &threshold.color, "#rgb, #rrggbb or a color name"));
+        }
+        thresholds.push(Threshold { value: threshold.value, color: threshold.color });
+    }
+    thresholds.sort_by(|a, b| a.value.total_cmp(&b.value));
+    Ok(Panel { title: entry.title.unwrap_or_else(|| metric.name().to_string()), metric, sensors: entry.sensors, chart, range_h, thresholds })
+}
+
+impl StationConfig {
+    fn from_file(file: ConfigFile) -> Result<Self, String> {
+        let sensor = file.sensor;
+        check_pin("sensor.pin", sensor.pin)?;
+        let sensors = if file.sensors.is_empty() { vec![SensorConfig::single(sensor.pin)] } else { convert_sensors(file.sensors)? };
+        if sensor.max_attempts == 0 {
+            return Err(invalid("sensor.max_attempts", 0, "at least 1"));
+        }
+        if sensor.bit_threshold_us == 0 || sensor.bit_threshold_us >= sensor.timeout_us {
+            return Err(invalid("sensor.bit_threshold_us", sensor.bit_threshold_us, "between 1 and sensor.timeout_us"));
+        }
+        let model = parse_sensor_model(&sensor.model).ok_or_else(|| invalid("sensor.model", &sensor.model, "\"dht11\" or \"dht22\""))?;
+        let interval = check_interval("sensor.interval_s", sensor.interval_s, model)?;
+        let timings = Dht11Config::new(model);
+        let comfort_index = match sensor.comfort_index {
+            Some(value) => Some(parse_comfort_index(&value).ok_or_else(|| invalid("sensor.comfort_index", &value, "\"humidex\" or \"thw\""))?),
+            None => None,
+        };
+        let public = file.public.map(|section| convert_public(section, &sensors)).transpose()?.unwrap_or_default();
+        let dashboard = match file.dashboard.filter(|section| !section.panels.is_empty()) {
+            Some(section) => DashboardLayout {
+                panels: section.panels.into_iter().map(|entry| convert_panel(entry, &public, &sensors)).collect::<Result<_, _>>()?,
+            },
+            // No irradiance, snow depth or leaf wetness chart that stays empty without a sensor measuring it.
+            None => {
+                let has_sensor = |matches: fn(&SensorKind) -> bool| sensors.iter().any(|sensor| matches(&sensor.kind));
+                let measured = |metric: &Quantity| match metric {
+                    Quantity::Irradiance => has_sensor(|kind| matches!(kind, SensorKind::Pyranometer { .. })),
+                    Quantity::SnowDepth => has_sensor(|kind| matches!(kind, SensorKind::Ultrasonic { .. })),
+                    Quantity::LeafWetness => has_sensor(|kind| matches!(kind, SensorKind::LeafWetness { .. })),
+                    Quantity::Temperature | Quantity::RelativeHumidity | Quantity::Pressure => true,
+                };
+                let metrics: Vec<Quantity> = public.metrics.iter().copied().filter(measured).collect();
+                DashboardLayout::for_metrics(&metrics)
+            }
+        };
+        let (http_address, http_admin_password) = match file.http {
+            Some(http) => {
+                if http.admin_password.as_deref() == Some("") {
+                    return Err(invalid("http.admin_password", "\"\"", "a non-empty password"));
+                }
+                (Some(http.address.parse().map_err(|_| invalid("http.address", &http.address, "<ip>:<port>"))?), http.admin_password)
+            }
+            None => (None, None),
+        };
+
+        Ok(StationConfig {
+            sensors,
+            interval,
+            align_to_clock: sensor.align_to_clock,
+            retry_policy: Dht11RetryPolicy {
+                max_attempts: sensor.max_attempts,
+                retry_delay_us: Duration::from_secs(sensor.retry_delay_s).as_micros().min(u32::MAX as u128) as u32,
+            },
+            edge_capture: sensor.edge_capture,
+            dht11: Dht11Config {
+                start_time_us: sensor.start_time_us.unwrap_or(timings.start_time_us),
+                timeout_us: sensor.timeout_us,
+                bit_threshold_us: sensor.bit_threshold_us,
+                ..timings
+            },
+            comfort_index,
+            database_path: file.storage.database,
+            retention_days: file.storage.retention_days,
+            csv_directory: file.csv.map(|csv| csv.directory),
+            signing_key_file: file.signing.map(|signing| signing.key_file),
+            http_address,
+            http_admin_password,
+            public,
+            dashboard,
+            kiosk: file.kiosk.map(convert_kiosk).transpose()?.unwrap_or_default(),
+            connectivity: file.connectivity.map(convert_connectivity).transpose()?.unwrap_or_default(),
+            location: file.location.map(convert_location).transpose()?,
+            gps: file.gps.map(convert_gps).transpose()?,
+            mqtt: file.mqtt.map(convert_mqtt).transpose()?.unwrap_or_default(),
+        })
+    }
+
+    /// Replaces the configured sensors with a single one on `pin`, for `--pin`.
+    pub fn use_pin(&mut self, pin: u8) {
+        self.sensors = vec![SensorConfig::single(pin)];
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let file: ConfigFile = toml::from_str(text).map_err(|error| error.to_string())?;
+        StationConfig::from_file(file)
+    }
+
+    /// Loads the configuration at `path`. A missing file is only accepted for the
+    /// default path, the built-in defaults are used then.
+    pub fn load(path: Option<&str>) -> Result<Self, StationError> {
+        let text = match fs::read_to_string(path.unwrap_or(DEFAULT_CONFIG_PATH)) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound && path.is_none() => String::new(),
+            Err(error) => return Err(StationError::Config(format!("cannot read {}: {}", path.unwrap_or(DEFAULT_CONFIG_PATH), error))),
+        };
+        StationConfig::parse(&text).map_err(|error| StationError::Config(format!("{}: {}", path.unwrap_or(DEFAULT_CONFIG_PATH), error)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_without_file() {
+        let config = StationConfig::parse("").unwrap();
+        assert_eq!(config.sensors, vec![SensorConfig::single(23)]);
+        assert_eq!(config.interval, Duration::from_secs(60));
+        assert_eq!(config.retry_policy.max_attempts, 3);
+        assert_eq!(config.dht11, Dht11Config::new(Dht11SensorType::Dht11));
+        assert!(config.mqtt.broker.is_none());
+        assert!(StationConfig::parse(include_str!("../config.example.toml")).is_ok());
+    }
+
+    #[test]
+    fn sections_are_applied() {
+        let config = StationConfig::parse(
+            "[sensor]\npin = 4\ninterval_s = 300\nalign_to_clock = true\ncomfort_index = \"thw\"\n\
+             [storage]\ndatabase = \"/var/lib/weather.db\"\n\
+             [http]\naddress = \"0.0.0.0:8080\"\n\
+             [mqtt]\nbroker = \"broker.local\"\nqos = 2\nha_discovery = true\n\
+             [mqtt.report_by_exception]\nmax_silence_s = 900\ndeltas = { temperature = 0.2 }\n\
+             [[mqtt.mirrors]]\nprefix = \"legacy\"\ntemperature_unit = \"fahrenheit\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.sensors[0].kind, SensorKind::Dht11 { pin: 4 });
+        assert!(config.align_to_clock);
+        assert_eq!(config.comfort_index, Some(ComfortIndex::Thw));
+        assert_eq!(config.database_path, "/var/lib/weather.db");
+        assert_eq!(config.http_address, Some("0.0.0.0:8080".parse().unwrap()));
+        assert_eq!(config.mqtt.discovery_prefix.as_deref(), Some("homeassistant"));
+        assert_eq!(config.mqtt.mirrors[0].temperature_unit, TemperatureUnit::Fahrenheit);
+        let exception = config.mqtt.report_by_exception.unwrap();
+        assert_eq!(exception.max_silence, Duration::from_secs(900));
+        assert_eq!(exception.deltas["temperature"], 0.2);
+    }
+
+    #[test]
+    fn dht22_gets_its_own_timings_and_minimum_interval() {
+        let config = StationConfig::parse("[sensor]\nmodel = \"dht22\"\ninterval_s = 2").unwrap();
+        assert_eq!(config.dht11, Dht11Config::new(Dht11SensorType::Dht22));
+        assert_eq!(config.interval, Duration::from_secs(2));
+        let config = StationConfig::parse("[sensor]\nmodel = \"dht22\"\nstart_time_us = 2000").unwrap();
+        assert_eq!((config.dht11.sensor_type, config.dht11.start_time_us), (Dht11SensorType::Dht22, 2000));
+        assert_eq!(StationConfig::parse("[sensor]\nmodel = \"dht22\"\ninterval_s = 1").err().unwrap(), "invalid sensor.interval_s = 1, expected at least 2");
+        assert_eq!(StationConfig::parse("[sensor]\nmodel = \"dht33\"").err().unwrap(), "invalid sensor.model = dht33, expected \"dht11\" or \"dht22\"");
+    }
+
+    #[test]
+    fn bad_values_are_explained() {
+        assert_eq!(StationConfig::parse("[sensor]\ninterval_s = 0").err().unwrap(), "invalid sensor.interval_s = 0, expected at least 1");
+        assert_eq!(StationConfig::parse("[mqtt]\nbroker = \"b\"\nqos = 3").err().unwrap(), "invalid mqtt.qos = 3, expected 0, 1 or 2");
+        assert_eq!(
+            StationConfig::parse("[mqtt]\nbroker = \"b\"\n[mqtt.commands]\nsecret = \"short\"").err().unwrap(),
+            "invalid mqtt.commands.secret = (hidden), expected at least 16 characters"
+        );
+        assert_eq!(
+            StationConfig::parse("[sensor]\nbit_threshold_us = 0").err().unwrap(),
+            "invalid sensor.bit_threshold_us = 0, expected between 1 and sensor.timeout_us"
+        );
+        assert_eq!(StationConfig::parse("[kiosk]\nnight_end_h = 24").err().unwrap(), "invalid kiosk.night_end_h = 24, expected 0 to 23");
+        assert_eq!(StationConfig::parse("[connectivity]\ntarget = \"1.1.1.1\"").err().unwrap(), "invalid connectivity.target = 1.1.1.1, expected <host>:<port>");
+        assert_eq!(StationConfig::parse("[connectivity]\nprobe = false").unwrap().connectivity.target, None);
+        assert_eq!(StationConfig::parse("[location]\nlatitude = 50.8").unwrap().location, Some(Location { latitude: 50.8, elevation_m: 0.0 }));
+        assert_eq!(StationConfig::parse("[location]\nlatitude = 95.0").err().unwrap(), "invalid location.latitude = 95, expected -90 to 90");
+        assert_eq!(StationConfig::parse("[gps]").unwrap().gps, Some(GpsConfig { device: "/dev/serial0".to_string(), baud_rate: 9600 }));
+        assert_eq!(StationConfig::parse("[gps]\nbaud_rate = 0").err().unwrap(), "invalid gps.baud_rate = 0, expected a positive baud rate");
+        assert!(StationConfig::parse("[sensor]\npine = 4").err().unwrap().contains("unknown field `pine`"));
+        assert_eq!(
+            StationConfig::parse("[public]\nexclude_sensors = [\"indoor\"]").err().unwrap(),
+            "invalid public.exclude_sensors = indoor, expected the name of a configured sensor"
+        );
+    }
+
+    #[test]
+    fn dashboard_panels_are_configurable() {
+        let config = StationConfig::parse("").unwrap();
+        assert_eq!(config.dashboard.panels.len(), 3);
+
+        let config = StationConfig::parse(
+            "[[sensors]]\nname = \"outdoor\"\npin = 24\n\
+             [[dashboard.panels]]\nmetric = \"temperature\"\nchart = \"value\"\nsensors = [\"outdoor\"]\n\
+             thresholds = [{ value = 30, color = \"red\" }, { value = 0, color = \"#1565c0\" }]\n",
+        )
+        .unwrap();
+        let panel = &config.dashboard.panels[0];
+        assert_eq!((panel.title.as_str(), panel.chart, panel.range_h), ("temperature", ChartType::Value, 24));
+        assert_eq!(panel.thresholds.iter().map(|threshold| threshold.value).collect::<Vec<_>>(), [0.0, 30.0]);
+
+        assert_eq!(
+            StationConfig::parse("[public]\nmetrics = [\"temperature\"]\n[[dashboard.panels]]\nmetric = \"humidity\"").err().unwrap(),
+            "invalid dashboard.panels.metric = humidity, expected a metric listed in public.metrics"
+        );
+        assert!(StationConfig::parse("[[dashboard.panels]]\nmetric = \"humidity\"\nchart = \"pie\"").is_err());
+    }
+
+    #[test]
+    fn sensor_list_replaces_the_single_sensor() {
+        let config = StationConfig::parse(
+            "[[sensors]]\nname = \"indoor\"\npin = 23\nlocation = \"living room\"\n\
+             [[sensors]]\nname = \"outdoor\"\npin = 24\n\
+             [[sensors]]\nname = \"attic\"\ntype = \"bme280\"\naddress = 0x77\n\
+             [[sensors]]\nname = \"soil\"\ntype = \"ds18b20\"\nrom_id = \"28-0316a2795aff\"\nresolution = 10\n",
+        )
+        .unwrap();
+        assert_eq!(config.sensors.len(), 4);
+        assert_eq!(config.sensors[3].kind, SensorKind::Ds18b20 { rom_id: "28-0316a2795aff".to_string(), resolution: Some(10) });
+        assert_eq!(config.sensors[0].location.as_deref(), Some("living room"));
+        assert_eq!(config.sensors[1], SensorConfig { name: "outdoor".to_string(), kind: SensorKind::Dht11 { pin: 24 }, location: None });
+        assert_eq!(config.sensors[2].kind, SensorKind::Bme280 { address: 0x77 });
+
+        let duplicate_pin = "[[sensors]]\nname = \"a\"\npin = 23\n[[sensors]]\nname = \"b\"\npin = 23\n";
+        assert_eq!(StationConfig::parse(duplicate_pin).err().unwrap(), "invalid sensors.pin = 23, expected a pin not used by another sensor");
+        assert!(StationConfig::parse("[[sensors]]\nname = \"out door\"\npin = 23\n").is_err());
+        assert_eq!(
+            StationConfig::parse("[[sensors]]\nname = \"a\"\ntype = \"bme280\"\naddress = 0x40\n").err().unwrap(),
+            "invalid sensors.address = 0x40, expected 0x76 or 0x77"
+        );
+        assert_eq!(
+            StationConfig::parse("[[sensors]]\nname = \"soil\"\ntype = \"ds18b20\"\nrom_id = \"28-0316a2795aff\"\nresolution = 8\n").err().unwrap(),
+            "invalid sensors.resolution = 8, expected 9 to 12 bits"
+        );
+    }
+
+    #[test]
+    fn ultrasonic_pins_and_compensation() {
+        let config = StationConfig::parse(
+            "[[sensors]]\nname = \"snow\"\ntype = \"ultrasonic\"\ntrigger_pin = 5\necho_pin = 6\nbaseline_cm = 150.0\n\
+             [[sensors]]\nname = \"outdoor\"\ntype = \"bme280\"\n",
+        )
+        .unwrap();
+        let kind = SensorKind::Ultrasonic { trigger_pin: 5, echo_pin: 6, baseline_cm: 150.0, temperature_sensor: Some("outdoor".to_string()) };
+        assert_eq!(config.sensors[0].kind, kind);
+        assert_eq!(config.dashboard.panels.iter().map(|panel| panel.metric.name()).collect::<Vec<_>>(), ["temperature", "humidity", "pressure", "snow_depth"]);
+
+        let shared_pin = "[[sensors]]\nname = \"a\"\npin = 6\n[[sensors]]\nname = \"snow\"\ntype = \"ultrasonic\"\ntrigger_pin = 5\necho_pin = 6\nbaseline_cm = 150.0\n";
+        assert_eq!(StationConfig::parse(shared_pin).err().unwrap(), "invalid sensors.pin = 6, expected a pin not used by another sensor");
+        assert_eq!(
+            StationConfig::parse("[[sensors]]\nname = \"snow\"\ntype = \"ultrasonic\"\ntrigger_pin = 5\necho_pin = 6\nbaseline_cm = 150.0\ntemperature_sensor = \"snow\"\n")
+                .err()
+                .unwrap(),
+            "invalid sensors.temperature_sensor = snow, expected the name of a configured sensor measuring temperature"
+        );
+        assert_eq!(
+            StationConfig::parse("[[sensors]]\nname = \"snow\"\ntype = \"ultrasonic\"\ntrigger_pin = 5\necho_pin = 6\nbaseline_cm = 800.0\n").err().unwrap(),
+            "invalid sensors.baseline_cm = 800, expected a distance up to 600 cm"
+        );
+    }
+
+    #[test]
+    fn pyranometer_needs_its_calibration() {
+        let pyranometer = "[[sensors]]\nname = \"sun\"\ntype = \"pyranometer\"\nadc_channel = 1\nfull_scale_raw = 32767\nfull_scale_mv = 4096.0\nsensitivity = 1.67\n";
+        let config = StationConfig::parse(pyranometer).unwrap();
+        assert_eq!(config.dashboard.panels.len(), 4);
+        let calibration = PyranometerCalibration { full_scale_raw: 32767, full_scale_mv: 4096.0, sensitivity: 1.67, offset_mv: 0.0 };
+        assert_eq!(config.sensors[0].kind, SensorKind::Pyranometer { adc_device: 0, adc_channel: 1, calibration });
+
+        assert_eq!(
+            StationConfig::parse(&pyranometer.replace("sensitivity = 1.67\n", "")).err().unwrap(),
+            "sensor sun needs sensitivity"
+        );
+        assert_eq!(
+            StationConfig::parse(&pyranometer.replace("full_scale_mv = 4096.0", "full_scale_mv = 0.0")).err().unwrap(),
+            "invalid sensors.full_scale_mv = 0, expected a positive number"
+        );
+        assert_eq!(
+            StationConfig::parse(&format!("{}{}", pyranometer, pyranometer.replace("\"sun\"", "\"shade\""))).err().unwrap(),
+            "invalid sensors.adc_channel = 1, expected a channel not used by another sensor"
+        );
+    }
+
+    #[test]
+    fn leaf_wetness_shares_the_adc_with_pyranometers() {
+        let leaf = "[[sensors]]\nname = \"leaf\"\ntype = \"leaf_wetness\"\nadc_channel = 2\ndry_raw = 1000\nwet_raw = 200\n";
+        let config = StationConfig::parse(leaf).unwrap();
+        let calibration = LeafWetnessCalibration { dry_raw: 1000, wet_raw: 200, wet_threshold: 50.0 };
+        assert_eq!(config.sensors[0].kind, SensorKind::LeafWetness { adc_device: 0, adc_channel: 2, calibration });
+
+        let pyranometer = "[[sensors]]\nname = \"sun\"\ntype = \"pyranometer\"\nadc_channel = 2\nfull_scale_raw = 32767\nfull_scale_mv = 4096.0\nsensitivity = 1.67\n";
+        assert_eq!(
+            StationConfig::parse(&format!("{}{}", leaf, pyranometer)).err().unwrap(),
+            "invalid sensors.adc_channel = 2, expected a channel not used by another sensor"
+        );
+        assert_eq!(
+            StationConfig::parse(&format!("{}{}", leaf, pyranometer.replace("adc_channel = 2", "adc_device = 1\nadc_channel = 2"))).unwrap().sensors.len(),
+            2
+        );
+        assert_eq!(
+            StationConfig::parse(&leaf.replace("wet_raw = 200", "wet_raw = 1000")).err().unwrap(),
+            "invalid sensors.wet_raw = 1000, expected a raw value other than dry_raw"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use std::fs;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+const WIRELESS_PATH: &str = "/proc/net/wireless";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+pub const DEFAULT_PROBE_TARGET: &str = "1.1.1.1:53";
+pub const DEFAULT_PROBE_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectivityConfig {
+    /// `host:port` reached with a TCP connection, the probe is disabled when not set.
+    pub target: Option<String>,
+    pub interval: Duration,
+}
+
+impl Default for ConnectivityConfig {
+    fn default() -> Self {
+        ConnectivityConfig { target: Some(DEFAULT_PROBE_TARGET.to_string()), interval: DEFAULT_PROBE_INTERVAL }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Probe {
+    Disabled,
+    /// The first probe hasn't finished yet.
+    Pending,
+    Unreachable,
+    ///
+    /// # Unit
+    /// Milliseconds to establish the probe connection.
+    Reachable(f64),
+}
+
+impl Probe {
+    pub fn latency(self) -> Option<f64> {
+        match self {
+            Probe::Reachable(latency) => Some(latency),
+            _ => None,
+        }
+    }
+}
+
+pub struct Connectivity {
+    pub probe: Probe,
+
+    ///
+    /// # Unit
+    /// dBm.
+    pub wifi_rssi: Option<f64>,
+}
+
+/// # Returns
+/// Signal level of the first wireless interface listed in `/proc/net/wireless`.
+fn parse_wifi_rssi(wireless: &str) -> Option<f64> {
+    // Two header lines, then `iface: status link level noise ...`
+    let line = wireless.lines().nth(2)?;
+    let (_, values) = line.split_once(':')?;
+    let level = values.split_whitespace().nth(2)?;
+    level.trim_end_matches('.').parse().ok()
+}
+
+/// Blocks for up to `PROBE_TIMEOUT` per address of `target`.
+fn probe(target: &str) -> Probe {
+    let Ok(addresses) = target.to_socket_addrs() else {
+        return Probe::Unreachable;
+    };
+    for address in addresses.collect::<Vec<SocketAddr>>() {
+        let start = Instant::now();
+        if TcpStream::connect_timeout(&address, PROBE_TIMEOUT).is_ok() {
+            return Probe::Reachable(start.elapsed().as_secs_f64() * 1000.0);
+        }
+    }
+    Probe::Unreachable
+}
+
+/// `host:port` with a port number, the host is only resolved when probing.
+pub fn is_probe_target(target: &str) -> bool {
+    target.rsplit_once(':').is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+}
+
+/// Probes the target every interval on its own thread, so an offline station
+/// never delays its readouts by the probe timeout.
+pub struct ProbeMonitor {
+    latest: Arc<Mutex<Probe>>,
+}
+
+impl ProbeMonitor {
+    pub fn spawn(config: &ConnectivityConfig) -> Self {
+        let Some(target) = config.target.clone() else {
+            return ProbeMonitor { latest: Arc::new(Mutex::new(Probe::Disabled)) };
+        };
+        let latest = Arc::new(Mutex::new(Probe::Pending));
+        let shared = Arc::clone(&latest);
+        let interval = config.interval;
+        let spawned = thread::Builder::new().name("connectivity".to_string()).spawn(move || loop {
+            let result = probe(&target);
+            *shared.lock().unwrap() = result;
+            thread::sleep(interval);
+        });
+        if let Err(error) = spawned {
+            warn!(%error, "starting the connectivity probe failed");
+        }
+        ProbeMonitor { latest }
+    }
+
+    pub fn latest(&self) -> Probe {
+        *self.latest.lock().unwrap()
+    }
+}
+
+impl Connectivity {
+    /// Probes right away, blocking, for one-shot commands.
+    pub fn check(config: &ConnectivityConfig) -> Self {
+        Connectivity::with_probe(config.target.as_deref().map_or(Probe::Disabled, probe))
+    }
+
+    pub fn with_probe(probe: Probe) -> Self {
+        Connectivity { probe, wifi_rssi: fs::read_to_string(WIRELESS_PATH).ok().and_then(|content| parse_wifi_rssi(&content)) }
+    }
+
+    pub fn print(&self) {
+        match self.probe {
+            Probe::Reachable(latency) => println!("Network: reachable ({:.1} ms)", latency),
+            Probe::Unreachable => println!("Network: unreachable"),
+            Probe::Disabled | Probe::Pending => {}
+        }
+
+        if let Some(rssi) = self.wifi_rssi {
+            println!("WiFi RSSI: {} dBm", rssi);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wifi_rssi_from_proc_net_wireless() {
+        let wireless = "Inter-| sta-|   Quality        |   Discarded packets               | Missed | WE\n \
+            face | tus | link level noise |  nwid  crypt   frag  retry   misc | beacon | 22\n \
+            wlan0: 0000   70.  -40.  -256        0      0      0      0      0        0\n";
+        assert_eq!(parse_wifi_rssi(wireless), Some(-40.0));
+        assert_eq!(parse_wifi_rssi(&wireless[..wireless.find("wlan0").unwrap()]), None);
+    }
+
+    #[test]
+    fn disabled_probe_never_connects() {
+        let config = ConnectivityConfig { target: None, ..Default::default() };
+        assert_eq!(ProbeMonitor::spawn(&config).latest(), Probe::Disabled);
+        assert_eq!(Connectivity::check(&config).probe, Probe::Disabled);
+        assert!(is_probe_target("1.1.1.1:53") && is_probe_target("[::1]:53") && is_probe_target("example.org:443"));
+        assert!(!is_probe_target("1.1.1.1") && !is_probe_target(":53") && !is_probe_target("host:dns"));
+    }
+}
@@ -0,0 +1,218 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use crate::error::StationError;
+use crate::signing::{self, ArchiveSigner};
+use crate::storage::{civil_from_days, Reading};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+pub const CSV_HEADER: &str = "timestamp,sensor_id,temperature,humidity,status,pressure,irradiance,snow_depth,leaf_wetness\n";
+
+fn format_optional(value: Option<f64>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+pub fn format_line(reading: &Reading) -> String {
+    format!(
+        "{},{},{},{},{},{},{},{},{}\n",
+        reading.timestamp,
+        reading.sensor_id,
+        format_optional(reading.temperature),
+        format_optional(reading.humidity),
+        reading.status,
+        format_optional(reading.pressure),
+        format_optional(reading.irradiance),
+        format_optional(reading.snow_depth),
+        format_optional(reading.leaf_wetness)
+    )
+}
+
+/// Whether the file at `path` is empty or starts with `CSV_HEADER`.
+fn has_current_header(path: &Path) -> Result<bool, StationError> {
+    let mut first_line = String::new();
+    BufReader::new(File::open(path)?).read_line(&mut first_line)?;
+    Ok(first_line.is_empty() || first_line == CSV_HEADER)
+}
+
+/// Appends readings to `readings-YYYY-MM-DD.csv` files, starting a new file every
+/// day (UTC). Every line is synced to disk so a power cut loses at most one sample.
+/// With a signer, each day's file is signed once the next day's is started, and
+/// on the first append after a restart every unsigned file of an earlier day is.
+/// A file of the day written with other columns, by an older version, is moved
+/// to `readings-YYYY-MM-DD.N.csv` rather than appended to.
+pub struct CsvLogger {
+    directory: PathBuf,
+    signer: Option<ArchiveSigner>,
+    current_day: Option<i64>,
+    file: Option<File>,
+}
+
+impl CsvLogger {
+    pub fn new(directory: &str, signer: Option<ArchiveSigner>) -> Self {
+        CsvLogger { directory: PathBuf::from(directory), signer, current_day: None, file: None }
+    }
+
+    fn base_name(day: i64) -> String {
+        let (year, month, day_of_month) = civil_from_days(day);
+        format!("readings-{:04}-{:02}-{:02}", year, month, day_of_month)
+    }
+
+    fn path(&self, day: i64) -> PathBuf {
+        self.directory.join(format!("{}.csv", CsvLogger::base_name(day)))
+    }
+
+    /// Moves the file of `day` to the first free `readings-YYYY-MM-DD.N.csv`,
+    /// together with its signature. Unsigned files are signed, they are final now.
+    fn move_aside(&self, day: i64) -> Result<(), StationError> {
+        let path = self.path(day);
+        let rotated = (1..)
+            .map(|index| self.directory.join(format!("{}.{}.csv", CsvLogger::base_name(day), index)))
+            .find(|candidate| !candidate.exists())
+            .unwrap_or_default();
+        fs::rename(&path, &rotated)?;
+        info!(from = %path.display(), to = %rotated.display(), "CSV log has other columns, moved aside");
+        if signing::signature_path(&path).exists() {
+            fs::rename(signing::signature_path(&path), signing::signature_path(&rotated))?;
+        } else if let Some(signer) = &self.signer {
+            signer.sign_file(&rotated)?;
+        }
+        Ok(())
+    }
+
+    /// Signs the files of days before `day` the station stopped before signing.
+    fn sign_earlier_days(&self, signer: &ArchiveSigner, day: i64) {
+        let today = CsvLogger::base_name(day);
+        let Ok(entries) = fs::read_dir(&self.directory) else {
+            return;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            // ISO dates of the same width sort like the days they name.
+            let is_earlier_log = name.ends_with(".csv") && name.get(..today.len()).is_some_and(|base_name| base_name.starts_with("readings-") && base_name < today.as_str());
+            if is_earlier_log && !signing::signature_path(&path).exists() {
+                match signer.sign_file(&path) {
+                    Ok(()) => info!(file = %path.display(), "signed the CSV log of an earlier day"),
+                    Err(error) => warn!(%error, file = %path.display(), "signing the CSV log of an earlier day failed"),
+                }
+            }
+        }
+    }
+
+    fn open_file(&self, day: i64) -> Result<File, StationError> {
+        fs::create_dir_all(&self.directory)?;
+        let path = self.path(day);
+        if path.exists() && !has_current_header(&path)? {
+            self.move_aside(day)?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            file.write_all(CSV_HEADER.as_bytes())?;
+        }
+        Ok(file)
+    }
+
+    fn file_for_day(&mut self, day: i64) -> Result<&mut File, StationError> {
+        let file = match self.file.take() {
+            Some(file) if self.current_day == Some(day) => file,
+            previous => {
+                if let (Some(signer), None) = (&self.signer, self.current_day) {
+                    self.sign_earlier_days(signer, day);
+                }
+                if let (Some(signer), Some(_), Some(previous_day)) = (&self.signer, previous, self.current_day) {
+                    if let Err(error) = signer.sign_file(&self.path(previous_day)) {
+                        warn!(%error, day = previous_day, "signing the CSV log of the previous day failed");
+                    }
+                }
+                self.open_file(day)?
+            }
+        };
+        self.current_day = Some(day);
+        Ok(self.file.insert(file))
+    }
+
+    pub fn append(&mut self, reading: &Reading) -> Result<(), StationError> {
+        let line = format_line(reading);
+        let file = self.file_for_day(reading.timestamp.div_euclid(SECONDS_PER_DAY))?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn rotates_daily() {
+        let directory = env::temp_dir().join(format!("weather_station_csv_{}", std::process::id()));
+        let mut logger = CsvLogger::new(directory.to_str().unwrap(), None);
+        let day = 19844 * SECONDS_PER_DAY;
+        for timestamp in [day + 10, day + 20, day + SECONDS_PER_DAY] {
+            logger
+                .append(&Reading { timestamp, sensor_id: "dht11".to_string(), temperature: Some(21.5), humidity: None, status: "ok".to_string(), pressure: Some(1013.2), irradiance: None, snow_depth: None, leaf_wetness: None })
+                .unwrap();
+        }
+
+        let first = fs::read_to_string(directory.join("readings-2024-05-01.csv")).unwrap();
+        assert_eq!(first, format!("{}{},dht11,21.5,,ok,1013.2,,,\n{},dht11,21.5,,ok,1013.2,,,\n", CSV_HEADER, day + 10, day + 20));
+        assert!(directory.join("readings-2024-05-02.csv").exists());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn files_with_other_columns_are_moved_aside() {
+        let directory = env::temp_dir().join(format!("weather_station_csv_header_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let old = "timestamp,sensor_id,temperature,humidity,status\n1714521600,dht11,21.5,,ok\n";
+        fs::write(directory.join("readings-2024-05-01.csv"), old).unwrap();
+        fs::write(directory.join("readings-2024-05-01.1.csv"), old).unwrap();
+
+        let mut logger = CsvLogger::new(directory.to_str().unwrap(), None);
+        let timestamp = 19844 * SECONDS_PER_DAY + 10;
+        for _ in 0..2 {
+            logger
+                .append(&Reading { timestamp, sensor_id: "dht11".to_string(), temperature: Some(21.5), humidity: None, status: "ok".to_string(), pressure: None, irradiance: None, snow_depth: None, leaf_wetness: None })
+                .unwrap();
+        }
+
+        let line = format!("{},dht11,21.5,,ok,,,,\n", timestamp);
+        assert_eq!(fs::read_to_string(directory.join("readings-2024-05-01.csv")).unwrap(), format!("{}{}{}", CSV_HEADER, line, line));
+        assert_eq!(fs::read_to_string(directory.join("readings-2024-05-01.2.csv")).unwrap(), old);
+
+        // A logger restarted later in the day keeps appending to the current file.
+        let mut logger = CsvLogger::new(directory.to_str().unwrap(), None);
+        logger
+            .append(&Reading { timestamp, sensor_id: "dht11".to_string(), temperature: Some(21.5), humidity: None, status: "ok".to_string(), pressure: None, irradiance: None, snow_depth: None, leaf_wetness: None })
+            .unwrap();
+        assert!(!directory.join("readings-2024-05-01.3.csv").exists());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn unsigned_earlier_days_are_signed_after_a_restart() {
+        let directory = env::temp_dir().join(format!("weather_station_csv_signing_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let key_file = directory.join("signing.key");
+        fs::write(&key_file, "4f".repeat(32)).unwrap();
+        for name in ["readings-2024-04-30.csv", "readings-2024-04-29.1.csv", "readings-2024-05-01.csv", "notes.csv"] {
+            fs::write(directory.join(name), CSV_HEADER).unwrap();
+        }
+
+        let mut logger = CsvLogger::new(directory.to_str().unwrap(), Some(ArchiveSigner::load(key_file.to_str().unwrap()).unwrap()));
+        logger
+            .append(&Reading { timestamp: 19844 * SECONDS_PER_DAY + 10, sensor_id: "dht11".to_string(), temperature: Some(21.5), humidity: None, status: "ok".to_string(), pressure: None, irradiance: None, snow_depth: None, leaf_wetness: None })
+            .unwrap();
+
+        assert!(directory.join("readings-2024-04-30.csv.sig").exists());
+        assert!(directory.join("readings-2024-04-29.1.csv.sig").exists());
+        assert!(!directory.join("readings-2024-05-01.csv.sig").exists());
+        assert!(!directory.join("notes.csv.sig").exists());
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,100 @@
+use sensor::Quantity;
+use serde::{Serialize, Serializer};
+
+/// How a dashboard panel shows its metric.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChartType {
+    /// History of every sensor on one chart.
+    Line,
+    /// Latest value of every sensor side by side.
+    Bar,
+    /// Latest value of every sensor as a number.
+    Value,
+}
+
+impl ChartType {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "line" => Some(ChartType::Line),
+            "bar" => Some(ChartType::Bar),
+            "value" => Some(ChartType::Value),
+            _ => None,
+        }
+    }
+}
+
+/// Values at or above `value` are drawn in `color`, unless a higher threshold matches too.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Threshold {
+    pub value: f64,
+    /// CSS color, `#rgb`, `#rrggbb` or a name like `red`.
+    pub color: String,
+}
+
+fn serialize_quantity<S: Serializer>(quantity: &Quantity, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(quantity.name())
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Panel {
+    pub title: String,
+    #[serde(serialize_with = "serialize_quantity")]
+    pub metric: Quantity,
+    /// Every public sensor when empty.
+    pub sensors: Vec<String>,
+    pub chart: ChartType,
+    ///
+    /// # Unit
+    /// Hours of history a line chart spans.
+    pub range_h: u32,
+    /// Sorted by value.
+    pub thresholds: Vec<Threshold>,
+}
+
+/// Panels of the public dashboard, in display order. The page fetches it from
+/// the server, so changing the layout needs no rebuild.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DashboardLayout {
+    pub panels: Vec<Panel>,
+}
+
+impl DashboardLayout {
+    /// A 24 hour line chart of every sensor per metric, used without configured panels.
+    pub fn for_metrics(metrics: &[Quantity]) -> Self {
+        let panel = |metric: &Quantity| Panel {
+            title: metric.name().to_string(),
+            metric: *metric,
+            sensors: Vec::new(),
+            chart: ChartType::Line,
+            range_h: 24,
+            thresholds: Vec::new(),
+        };
+        DashboardLayout { panels: metrics.iter().map(panel).collect() }
+    }
+}
+
+/// Colors end up in the page's styles, so only plain ones are accepted.
+pub fn is_css_color(color: &str) -> bool {
+    match color.strip_prefix('#') {
+        Some(hex) => matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => !color.is_empty() && color.chars().all(|c| c.is_ascii_alphabetic()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_is_served_as_json() {
+        let layout = DashboardLayout::for_metrics(&[Quantity::Temperature, Quantity::RelativeHumidity]);
+        let json = serde_json::to_value(&layout).unwrap();
+        assert_eq!(json["panels"][1]["metric"], "humidity");
+        assert_eq!(json["panels"][0]["chart"], "line");
+        assert_eq!(json["panels"][0]["range_h"], 24);
+
+        assert!(is_css_color("#c62828") && is_css_color("#fff") && is_css_color("red"));
+        assert!(!is_css_color("#ff") && !is_css_color("red;background:url(x)") && !is_css_color(""));
+    }
+}
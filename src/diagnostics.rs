@@ -0,0 +1,240 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use sensor::SensorCounter;
+use serde::Serialize;
+use tracing::warn;
+
+const CPU_GOVERNOR_PATH: &str = "/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor";
+const LOAD_AVERAGE_PATH: &str = "/proc/loadavg";
+const THROTTLED_PATH: &str = "/sys/devices/platform/soc/soc:firmware/get_throttled";
+
+const THROTTLED_UNDER_VOLTAGE: u32 = 1 << 0;
+const THROTTLED_FREQUENCY_CAPPED: u32 = 1 << 1;
+const THROTTLED_THROTTLED: u32 = 1 << 2;
+const THROTTLED_SOFT_TEMPERATURE_LIMIT: u32 = 1 << 3;
+
+/// Checksum errors are correlated with the warnings over this long.
+const CHECKSUM_WINDOW: Duration = Duration::from_secs(60 * 60);
+/// Share of readout attempts failing the checksum that counts as a spike, a
+/// DHT11 read with accurate timing stays far below.
+const CHECKSUM_SPIKE_RATE: f64 = 0.2;
+
+/// A runtime condition known to break bit-banged readouts, together with how to fix it.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ReliabilityWarning {
+    /// Which check raised it, e.g. `load_average`.
+    pub check: &'static str,
+    pub problem: String,
+    pub suggestion: &'static str,
+}
+
+fn read_trimmed(path: &str) -> Option<String> {
+    fs::read_to_string(path).ok().map(|content| content.trim().to_string())
+}
+
+/// Decodes the currently active flags reported by the firmware `get_throttled` value.
+pub fn throttled_problems(flags: u32) -> Vec<&'static str> {
+    let mut problems = Vec::new();
+    if flags & THROTTLED_UNDER_VOLTAGE != 0 {
+        problems.push("under-voltage");
+    }
+    if flags & THROTTLED_FREQUENCY_CAPPED != 0 {
+        problems.push("arm frequency capped");
+    }
+    if flags & THROTTLED_THROTTLED != 0 {
+        problems.push("throttled");
+    }
+    if flags & THROTTLED_SOFT_TEMPERATURE_LIMIT != 0 {
+        problems.push("soft temperature limit");
+    }
+    problems
+}
+
+/// # Returns
+/// Raw firmware throttling flags, `None` when not running on a Raspberry Pi.
+pub fn read_throttled_flags() -> Option<u32> {
+    let value = read_trimmed(THROTTLED_PATH)?;
+    u32::from_str_radix(value.trim_start_matches("0x"), 16).ok()
+}
+
+fn check_cpu_governor(warnings: &mut Vec<ReliabilityWarning>) {
+    if let Some(governor) = read_trimmed(CPU_GOVERNOR_PATH) {
+        if governor == "powersave" {
+            warnings.push(ReliabilityWarning {
+                check: "cpu_governor",
+                problem: format!("cpu governor is {}", governor),
+                suggestion: "set the governor to performance, e.g. `echo performance | sudo tee /sys/devices/system/cpu/cpu*/cpufreq/scaling_governor`",
+            });
+        }
+    }
+}
+
+fn check_load_average(warnings: &mut Vec<ReliabilityWarning>) {
+    let cpus = thread::available_parallelism().map(|count| count.get()).unwrap_or(1) as f64;
+    let load = read_trimmed(LOAD_AVERAGE_PATH)
+        .and_then(|content| content.split_whitespace().next().and_then(|value| value.parse::<f64>().ok()));
+
+    if let Some(load) = load {
+        if load > cpus {
+            warnings.push(ReliabilityWarning {
+                check: "load_average",
+                problem: format!("load average {:.2} exceeds {} cpus", load, cpus),
+                suggestion: "stop or renice other busy processes running on the station",
+            });
+        }
+    }
+}
+
+fn check_throttling(warnings: &mut Vec<ReliabilityWarning>) {
+    if let Some(flags) = read_throttled_flags() {
+        let problems = throttled_problems(flags);
+        if !problems.is_empty() {
+            warnings.push(ReliabilityWarning {
+                check: "throttling",
+                problem: format!("firmware reports {}", problems.join(", ")),
+                suggestion: "use an official power supply and improve cooling of the board",
+            });
+        }
+    }
+}
+
+/// Checks conditions that degrade the timing of bit-banged sensor readouts.
+pub fn reliability_warnings() -> Vec<ReliabilityWarning> {
+    let mut warnings = Vec::new();
+    check_cpu_governor(&mut warnings);
+    check_load_average(&mut warnings);
+    check_throttling(&mut warnings);
+    warnings
+}
+
+pub fn log_reliability_warnings(warnings: &[ReliabilityWarning]) {
+    for warning in warnings {
+        warn!(check = warning.check, problem = %warning.problem, suggestion = %warning.suggestion, "readouts may be unreliable");
+    }
+}
+
+/// Share of readout attempts failing the checksum over the last
+/// [`CHECKSUM_WINDOW`], from the counters of the drivers that keep them.
+pub struct ChecksumErrorRate {
+    /// Cumulative attempts and checksum errors with the time they were taken,
+    /// oldest first. The oldest one is the baseline of the window.
+    totals: VecDeque<(Instant, u64, u64)>,
+}
+
+impl ChecksumErrorRate {
+    pub fn new(start: Instant) -> Self {
+        ChecksumErrorRate { totals: VecDeque::from([(start, 0, 0)]) }
+    }
+
+    /// Takes the totals of `readout_attempts` and `checksum_errors` of every sensor.
+    pub fn record(&mut self, now: Instant, counters: &[SensorCounter]) {
+        let total = |name| counters.iter().filter(|counter| counter.name == name).map(|counter| counter.value).sum::<u64>();
+        self.totals.push_back((now, total("readout_attempts"), total("checksum_errors")));
+        while self.totals.len() > 2 && now.duration_since(self.totals[1].0) >= CHECKSUM_WINDOW {
+            self.totals.pop_front();
+        }
+    }
+
+    /// # Returns
+    /// `None` while no attempt was made in the window.
+    pub fn rate(&self) -> Option<f64> {
+        let (&(_, first_attempts, first_errors), &(_, attempts, errors)) = (self.totals.front()?, self.totals.back()?);
+        let attempts = attempts.saturating_sub(first_attempts);
+        (attempts > 0).then(|| errors.saturating_sub(first_errors) as f64 / attempts as f64)
+    }
+}
+
+/// Warnings in effect and the checksum error rate they are correlated with.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct ReliabilityStatus {
+    pub warnings: Vec<ReliabilityWarning>,
+    /// Share of readout attempts failing the checksum over the last hour, see [`ChecksumErrorRate`].
+    pub checksum_error_rate: Option<f64>,
+    /// The checksum errors spike while warnings are active, so they are the likely cause.
+    pub checksum_errors_explained: bool,
+}
+
+impl ReliabilityStatus {
+    pub fn new(warnings: Vec<ReliabilityWarning>, checksum_error_rate: Option<f64>) -> Self {
+        let spike = checksum_error_rate.is_some_and(|rate| rate >= CHECKSUM_SPIKE_RATE);
+        let checksum_errors_explained = spike && !warnings.is_empty();
+        ReliabilityStatus { warnings, checksum_error_rate, checksum_errors_explained }
+    }
+
+    pub fn print(&self) {
+        for warning in &self.warnings {
+            println!("Warning: {}, {}", warning.problem, warning.suggestion);
+        }
+        if let Some(rate) = self.checksum_error_rate {
+            println!("Checksum errors: {:.0}%", rate * 100.0);
+        }
+        if self.checksum_errors_explained {
+            println!("The checksum errors are likely caused by the warnings above");
+        }
+    }
+}
+
+/// Latest [`ReliabilityStatus`], shared between the sampling loop and the HTTP API.
+#[derive(Default)]
+pub struct ReliabilityMonitor {
+    status: Mutex<ReliabilityStatus>,
+}
+
+impl ReliabilityMonitor {
+    /// Logs warnings that were not active before and a checksum spike they
+    /// explain, so a lasting condition is logged once.
+    pub fn update(&self, status: ReliabilityStatus) {
+        let mut current = self.status.lock().unwrap();
+        let raised: Vec<_> = status.warnings.iter().filter(|warning| current.warnings.iter().all(|active| active.check != warning.check)).cloned().collect();
+        log_reliability_warnings(&raised);
+        if status.checksum_errors_explained && !current.checksum_errors_explained {
+            warn!(checksum_error_rate = status.checksum_error_rate, "the checksum errors are likely caused by the reliability warnings");
+        }
+        *current = status;
+    }
+
+    pub fn status(&self) -> ReliabilityStatus {
+        self.status.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttled_flags_decoding() {
+        assert!(throttled_problems(0).is_empty());
+        assert_eq!(throttled_problems(0x50005), vec!["under-voltage", "throttled"]);
+    }
+
+    #[test]
+    fn checksum_errors_are_counted_over_the_window() {
+        let counters = |attempts, checksum_errors| [
+            SensorCounter { name: "readout_attempts", help: "", value: attempts },
+            SensorCounter { name: "checksum_errors", help: "", value: checksum_errors },
+            SensorCounter { name: "timeouts", help: "", value: 7 },
+        ];
+        let start = Instant::now();
+        let mut rate = ChecksumErrorRate::new(start);
+        assert_eq!(rate.rate(), None);
+        rate.record(start + Duration::from_secs(60), &counters(10, 0));
+        assert_eq!(rate.rate(), Some(0.0));
+        rate.record(start + CHECKSUM_WINDOW, &counters(20, 5));
+        assert_eq!(rate.rate(), Some(0.25));
+        // The first minute falls out of the window, its attempts no longer count.
+        rate.record(start + CHECKSUM_WINDOW + Duration::from_secs(120), &counters(30, 10));
+        assert_eq!(rate.rate(), Some(0.5));
+    }
+
+    #[test]
+    fn only_a_spike_with_active_warnings_is_explained() {
+        let warning = ReliabilityWarning { check: "cpu_governor", problem: "cpu governor is powersave".to_string(), suggestion: "" };
+        assert!(ReliabilityStatus::new(vec![warning.clone()], Some(0.3)).checksum_errors_explained);
+        assert!(!ReliabilityStatus::new(vec![warning], Some(0.01)).checksum_errors_explained);
+        assert!(!ReliabilityStatus::new(Vec::new(), Some(0.3)).checksum_errors_explained);
+    }
+}
@@ -0,0 +1,31 @@
+use dht11::emulator::{dht11_emulate_readout, Dht11Emulation, Dht11EmulatorFault};
+use dht11::Dht11Error;
+use crate::cli::EmulateArgs;
+use crate::error::StationError;
+use crate::{IoPinDht, Timing};
+
+/// Answers readouts of a DHT11 reader connected to `--pin` with fixed values,
+/// so the reader driver can be tested end to end against a second Pi or a jumper.
+pub fn emulate_dht11(args: &EmulateArgs) -> Result<(), StationError> {
+    let pin_number = args.pin;
+    let emulation = Dht11Emulation {
+        humidity: args.rh,
+        temperature: args.temp,
+        fault: args.fault.map(Dht11EmulatorFault::from).unwrap_or(Dht11EmulatorFault::None),
+    };
+
+    println!("Emulating DHT11 on pin {}: {}% {}*C", pin_number, emulation.humidity, emulation.temperature);
+    let mut pin = IoPinDht::new(pin_number)?;
+    let timing = Timing::new();
+    let mut answered: u64 = 0;
+    loop {
+        match dht11_emulate_readout(&mut pin, &timing, &emulation) {
+            Ok(()) => {
+                answered += 1;
+                println!("Answered readout {}", answered);
+            }
+            Err(Dht11Error::Timeout) => {}
+            Err(error) => println!("Emulation failed: {:?}", error),
+        }
+    }
+}
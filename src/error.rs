@@ -0,0 +1,76 @@
+use std::fmt;
+use std::io;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use rppal::{gpio, i2c, uart};
+use sensor::SensorError;
+
+#[derive(Debug)]
+pub enum StationError {
+    Gpio(gpio::Error),
+    I2c(i2c::Error),
+    Uart(uart::Error),
+    Readout(SensorError),
+    Storage(rusqlite::Error),
+    Io(io::Error),
+    Config(String),
+    Install(String),
+}
+
+impl fmt::Display for StationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StationError::Gpio(error) => write!(f, "GPIO error: {}", error),
+            StationError::I2c(error) => write!(f, "I2C error: {}", error),
+            StationError::Uart(error) => write!(f, "UART error: {}", error),
+            StationError::Readout(error) => write!(f, "readout error: {}", error),
+            StationError::Storage(error) => write!(f, "storage error: {}", error),
+            StationError::Io(error) => write!(f, "I/O error: {}", error),
+            StationError::Config(error) => write!(f, "configuration error: {}", error),
+            StationError::Install(error) => write!(f, "service installation failed: {}", error),
+        }
+    }
+}
+
+impl From<gpio::Error> for StationError {
+    fn from(error: gpio::Error) -> Self {
+        StationError::Gpio(error)
+    }
+}
+
+impl From<i2c::Error> for StationError {
+    fn from(error: i2c::Error) -> Self {
+        StationError::I2c(error)
+    }
+}
+
+impl From<uart::Error> for StationError {
+    fn from(error: uart::Error) -> Self {
+        StationError::Uart(error)
+    }
+}
+
+impl From<SensorError> for StationError {
+    fn from(error: SensorError) -> Self {
+        StationError::Readout(error)
+    }
+}
+
+impl From<rusqlite::Error> for StationError {
+    fn from(error: rusqlite::Error) -> Self {
+        StationError::Storage(error)
+    }
+}
+
+impl From<io::Error> for StationError {
+    fn from(error: io::Error) -> Self {
+        StationError::Io(error)
+    }
+}
+
+/// Failed HTTP API requests answer with the error as plain text.
+impl IntoResponse for StationError {
+    fn into_response(self) -> Response {
+        (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()).into_response()
+    }
+}
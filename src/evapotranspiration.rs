@@ -0,0 +1,238 @@
+use gps::{gps_perform_readout, GpsError, GpsSerial};
+use leaf_wetness::{LeafWetnessReadout, WetHoursAccumulator};
+use pyranometer::{DailyRadiation, PyranometerReadout, RadiationAccumulator};
+use sensor::Quantity;
+use tracing::warn;
+use weather_math::DailyWeather;
+use crate::storage::{self, Reading};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+/// Days before the first of each month in a common year.
+const DAYS_BEFORE_MONTH: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+/// Readings of a day have to span this much for its extremes to mean anything.
+const MIN_DAY_COVERAGE_S: i64 = 18 * 60 * 60;
+/// Longest gap between two readings that is integrated, a longer one is an
+/// outage rather than a sampling interval.
+const MAX_SAMPLE_GAP_S: i64 = 30 * 60;
+/// A system clock further off GPS time than this is not kept by NTP.
+const MAX_CLOCK_SKEW_S: i64 = 60;
+
+/// Where the station stands, needed for the solar radiation reaching it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    /// Degrees, north positive.
+    pub latitude: f64,
+    ///
+    /// # Unit
+    /// Meters above sea level.
+    pub elevation_m: f64,
+}
+
+impl Location {
+    /// Location of the GPS fix read from `serial`. Warns when the system clock
+    /// disagrees with GPS time, as it does on a station without NTP.
+    pub fn from_gps(serial: &mut dyn GpsSerial, system_time: i64) -> Result<Location, GpsError> {
+        let fix = gps_perform_readout(serial)?;
+        if let Some(skew_s) = fix.clock_skew(system_time).filter(|skew| skew.abs() > MAX_CLOCK_SKEW_S) {
+            warn!(skew_s, "the system clock disagrees with GPS time, is NTP reachable?");
+        }
+        Ok(Location { latitude: fix.latitude, elevation_m: fix.elevation })
+    }
+}
+
+/// NMEA GPS module on a serial port, the station's location when `[location]`
+/// doesn't set it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GpsConfig {
+    pub device: String,
+    pub baud_rate: u32,
+}
+
+/// 1..=366 for a day counted from the unix epoch.
+fn day_of_year(days: i64) -> u32 {
+    let (year, month, day) = storage::civil_from_days(days);
+    let is_leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    DAYS_BEFORE_MONTH[month as usize - 1] + day + u32::from(is_leap && month > 2)
+}
+
+/// Solar radiation total and sunshine duration of one UTC day, from the
+/// irradiance readings of one pyranometer during it. Each reading counts until
+/// the next one.
+///
+/// # Returns
+/// `None` when the readings don't cover most of the day.
+pub fn daily_radiation(readings: &[Reading], days: i64) -> Option<DailyRadiation> {
+    let mut accumulator = RadiationAccumulator::new(days as u64);
+    let covered = integrate(readings, Quantity::Irradiance, |irradiance, elapsed| {
+        accumulator.add_sample(days as u64, &PyranometerReadout { irradiance }, elapsed);
+    });
+    (covered >= MIN_DAY_COVERAGE_S).then(|| accumulator.totals())
+}
+
+/// Hours one UTC day's leaf wetness readings of one sensor were at or above
+/// `wet_threshold`. Each reading counts until the next one.
+///
+/// # Returns
+/// `None` when the readings don't cover most of the day.
+pub fn daily_wet_hours(readings: &[Reading], days: i64, wet_threshold: f64) -> Option<f64> {
+    let mut accumulator = WetHoursAccumulator::new(days as u64);
+    let covered = integrate(readings, Quantity::LeafWetness, |wetness, elapsed| {
+        accumulator.add_sample(days as u64, &LeafWetnessReadout { wetness, is_wet: wetness >= wet_threshold }, elapsed);
+    });
+    (covered >= MIN_DAY_COVERAGE_S).then(|| accumulator.wet_hours())
+}
+
+/// Passes every value of `quantity` with the seconds until the next reading to
+/// `add`, skipping outages.
+///
+/// # Returns
+/// Seconds covered.
+fn integrate(readings: &[Reading], quantity: Quantity, mut add: impl FnMut(f64, f64)) -> i64 {
+    let samples: Vec<(i64, f64)> = readings.iter().filter_map(|reading| Some((reading.timestamp, reading.value(quantity)?))).collect();
+    let mut covered = 0;
+    for pair in samples.windows(2) {
+        let ((timestamp, value), (next, _)) = (pair[0], pair[1]);
+        let elapsed = next - timestamp;
+        if elapsed <= MAX_SAMPLE_GAP_S {
+            add(value, elapsed as f64);
+            covered += elapsed;
+        }
+    }
+    covered
+}
+
+/// Reference evapotranspiration of one UTC day in mm, from the readings of one
+/// sensor during it. With the day's `radiation` this is the FAO-56 Penman-Monteith
+/// equation, with the FAO-56 default wind speed as no wind is measured; without
+/// it Hargreaves' estimate.
+///
+/// # Returns
+/// `None` when the temperatures don't cover most of the day.
+pub fn daily_et0(readings: &[Reading], location: &Location, days: i64, radiation: Option<&DailyRadiation>) -> Option<f64> {
+    let temperatures: Vec<(i64, f64)> = readings.iter().filter_map(|reading| Some((reading.timestamp, reading.temperature?))).collect();
+    let span = temperatures.last()?.0 - temperatures.first()?.0;
+    if span < MIN_DAY_COVERAGE_S {
+        return None;
+    }
+    let extremes = |values: &mut dyn Iterator<Item = f64>| values.fold(None, |extremes: Option<(f64, f64)>, value| match extremes {
+        None => Some((value, value)),
+        Some((min, max)) => Some((min.min(value), max.max(value))),
+    });
+    let (temperature_min, temperature_max) = extremes(&mut temperatures.iter().map(|(_, value)| *value))?;
+    let humidity = extremes(&mut readings.iter().filter_map(|reading| reading.humidity));
+    let day = DailyWeather {
+        temperature_min,
+        temperature_max,
+        humidity_min: humidity.map(|(min, _)| min),
+        humidity_max: humidity.map(|(_, max)| max),
+        wind_speed: radiation.map(|_| weather_math::DEFAULT_WIND_SPEED),
+        solar_radiation: radiation.map(|radiation| radiation.radiation),
+    };
+    Some(weather_math::reference_evapotranspiration(&day, location.latitude, location.elevation_m, day_of_year(days)))
+}
+
+/// Start of the UTC day `days`, in seconds since the unix epoch.
+pub fn day_start(days: i64) -> i64 {
+    days * SECONDS_PER_DAY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(timestamp: i64, temperature: f64) -> Reading {
+        Reading { timestamp, sensor_id: "outdoor".to_string(), temperature: Some(temperature), humidity: Some(60.0), status: "ok".to_string(), pressure: None, irradiance: None, snow_depth: None, leaf_wetness: None }
+    }
+
+    #[test]
+    fn et0_of_a_covered_day() {
+        // 2024-07-06, the FAO-56 example day at 50.8° N.
+        let days = 19910;
+        assert_eq!(day_of_year(days), 188);
+        assert_eq!(day_of_year(19358), 1);
+        assert_eq!(day_of_year(20088), 366);
+
+        let location = Location { latitude: 50.8, elevation_m: 100.0 };
+        let start = day_start(days);
+        let readings = [reading(start + 600, 12.3), reading(start + 14 * 3600, 21.5), reading(start + 23 * 3600, 15.0)];
+        let et0 = daily_et0(&readings, &location, days, None).unwrap();
+        assert_eq!(et0, weather_math::et0_hargreaves(12.3, 21.5, 50.8, 188));
+        assert_eq!(daily_et0(&readings[..2], &location, days, None), None);
+
+        let radiation = DailyRadiation { radiation: 22.07, sunshine_hours: 9.25 };
+        let et0 = daily_et0(&readings, &location, days, Some(&radiation)).unwrap();
+        assert!(et0 > 0.0 && et0 != weather_math::et0_hargreaves(12.3, 21.5, 50.8, 188));
+    }
+
+    struct ScriptedSerial(Vec<&'static str>);
+
+    impl GpsSerial for ScriptedSerial {
+        fn read_sentence(&mut self) -> Option<String> {
+            (!self.0.is_empty()).then(|| self.0.remove(0).to_string())
+        }
+    }
+
+    #[test]
+    fn location_from_a_gps_fix() {
+        let sentences = vec![
+            "$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47",
+            "$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A",
+        ];
+        let location = Location::from_gps(&mut ScriptedSerial(sentences), 764_426_119).unwrap();
+        assert!((location.latitude - 48.1173).abs() < 1e-9);
+        assert_eq!(location.elevation_m, 545.4);
+        assert_eq!(Location::from_gps(&mut ScriptedSerial(Vec::new()), 0), Err(GpsError::NoData));
+    }
+
+    #[test]
+    fn radiation_of_a_covered_day() {
+        let days = 19910;
+        let start = day_start(days);
+        let irradiance = |timestamp: i64, irradiance: f64| Reading {
+            timestamp,
+            sensor_id: "sun".to_string(),
+            temperature: None,
+            humidity: None,
+            status: "ok".to_string(),
+            pressure: None,
+            irradiance: Some(irradiance),
+            snow_depth: None,
+            leaf_wetness: None,
+        };
+        // Dark until 06:00, 500 W/m² until 16:00, dark again until the end of the day.
+        let readings: Vec<Reading> = (0..24 * 6)
+            .map(|sample| start + sample * 600)
+            .map(|timestamp| irradiance(timestamp, if (6 * 3600..16 * 3600).contains(&(timestamp - start)) { 500.0 } else { 0.0 }))
+            .collect();
+        let radiation = daily_radiation(&readings, days).unwrap();
+        assert!((radiation.radiation - 18.0).abs() < 1e-9);
+        assert_eq!(radiation.sunshine_hours, 10.0);
+
+        // A six hour outage in the afternoon.
+        let outage: Vec<Reading> = readings.iter().filter(|reading| !(12 * 3600..18 * 3600).contains(&(reading.timestamp - start))).cloned().collect();
+        assert_eq!(daily_radiation(&outage, days), None);
+    }
+
+    #[test]
+    fn wet_hours_of_a_covered_day() {
+        let days = 19910;
+        let start = day_start(days);
+        // Dew until 07:30, dry afterwards.
+        let readings: Vec<Reading> = (0..24 * 4)
+            .map(|sample| start + sample * 900)
+            .map(|timestamp| Reading {
+                timestamp,
+                sensor_id: "leaf".to_string(),
+                temperature: None,
+                humidity: None,
+                status: "ok".to_string(),
+                pressure: None,
+                irradiance: None,
+                snow_depth: None,
+                leaf_wetness: Some(if timestamp - start < 7 * 3600 + 1800 { 80.0 } else { 10.0 }),
+            })
+            .collect();
+        assert_eq!(daily_wet_hours(&readings, days, 50.0), Some(7.5));
+        assert_eq!(daily_wet_hours(&readings[..60], days, 50.0), None);
+    }
+}
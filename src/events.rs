@@ -0,0 +1,73 @@
+use tokio::sync::broadcast;
+use crate::storage::Reading;
+
+/// Events a slow client may fall behind by before it misses some.
+const EVENT_CAPACITY: usize = 64;
+
+/// Something that happened at the station, as streamed to clients.
+#[derive(Clone)]
+pub enum StationEvent {
+    /// A readout was stored, failed ones included.
+    Reading(Reading),
+}
+
+impl StationEvent {
+    /// Event type clients filter on.
+    pub fn name(&self) -> &'static str {
+        match self {
+            StationEvent::Reading(_) => "reading",
+        }
+    }
+}
+
+/// Hands events of the acquisition loop to every subscribed client. Publishing
+/// never blocks the loop: without subscribers events are dropped, clients that
+/// fall behind lose the oldest ones.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<StationEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus { sender: broadcast::channel(EVENT_CAPACITY).0 }
+    }
+}
+
+impl EventBus {
+    pub fn publish(&self, event: StationEvent) {
+        // Fails only when nobody listens.
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StationEvent> {
+        self.sender.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    fn reading(timestamp: i64) -> Reading {
+        Reading { timestamp, sensor_id: "outdoor".to_string(), temperature: Some(5.0), humidity: None, status: "ok".to_string(), pressure: None, irradiance: None, snow_depth: None, leaf_wetness: None }
+    }
+
+    #[test]
+    fn subscribers_get_events_published_after_subscribing() {
+        let bus = EventBus::default();
+        bus.publish(StationEvent::Reading(reading(1)));
+        let mut receiver = bus.subscribe();
+        bus.publish(StationEvent::Reading(reading(2)));
+
+        let StationEvent::Reading(received) = receiver.try_recv().unwrap();
+        assert_eq!(received.timestamp, 2);
+        assert!(matches!(receiver.try_recv(), Err(TryRecvError::Empty)));
+
+        for timestamp in 0..EVENT_CAPACITY as i64 + 1 {
+            bus.publish(StationEvent::Reading(reading(timestamp)));
+        }
+        assert!(matches!(receiver.try_recv(), Err(TryRecvError::Lagged(1))));
+    }
+}
@@ -0,0 +1,40 @@
+use crate::cli::{ExportArgs, OutputFormat};
+use crate::config::StationConfig;
+use std::fs;
+use crate::csv_log;
+use crate::error::StationError;
+use crate::signing::{self, ArchiveSigner};
+use crate::storage::{self, Storage};
+
+const DEFAULT_EXPORT_SPAN_S: i64 = 24 * 60 * 60;
+
+/// Prints the readings stored between `--from` and `--to`, as a JSON array or as
+/// CSV in the layout of the CSV log. Exports written to `--output` are signed
+/// when a signing key is configured.
+pub fn export(config: &StationConfig, args: &ExportArgs) -> Result<(), StationError> {
+    let storage = Storage::open(args.database.as_deref().unwrap_or(&config.database_path))?;
+    let to = args.to.unwrap_or_else(storage::unix_time_now);
+    let readings = storage.readings_between(args.from.unwrap_or(to - DEFAULT_EXPORT_SPAN_S), to)?;
+
+    let text = match args.format {
+        OutputFormat::Json => serde_json::to_string(&readings).unwrap_or_default() + "\n",
+        OutputFormat::Text => {
+            let mut text = csv_log::CSV_HEADER.to_string();
+            for reading in &readings {
+                text.push_str(&csv_log::format_line(reading));
+            }
+            text
+        }
+    };
+    match &args.output {
+        Some(path) => {
+            fs::write(path, text)?;
+            if let Some(key_file) = &config.signing_key_file {
+                ArchiveSigner::load(key_file)?.sign_file(path)?;
+                eprintln!("Signed {}", signing::signature_path(path).display());
+            }
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::fs;
+use bme280::bme280_read_chip_id;
+use rppal::system::DeviceInfo;
+use sensor::{Measurement, SensorError};
+use crate::config::{SensorConfig, SensorKind, StationConfig};
+use tracing::info;
+use crate::I2cBme280;
+
+const KERNEL_RELEASE_PATH: &str = "/proc/sys/kernel/osrelease";
+
+fn board_model() -> String {
+    match DeviceInfo::new() {
+        Ok(info) => format!("{} ({})", info.model(), info.soc()),
+        Err(error) => format!("unknown ({})", error),
+    }
+}
+
+fn kernel_release() -> String {
+    fs::read_to_string(KERNEL_RELEASE_PATH)
+        .map(|release| release.trim().to_string())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Read straight from the chip, so a BMP280 answering on a BME280 address
+/// shows up even though its readouts fail.
+fn bme280_chip_id(address: u8) -> String {
+    match I2cBme280::new(address).map(|mut bus| bme280_read_chip_id(&mut bus)) {
+        Ok(Ok(chip_id)) => format!("{:#04x}", chip_id),
+        _ => "unknown".to_string(),
+    }
+}
+
+fn probe_result(probe: &Result<Measurement, SensorError>) -> String {
+    match probe {
+        Ok(_) => "ok".to_string(),
+        Err(error) => format!("failed ({})", error),
+    }
+}
+
+/// Logs the settings that ended up in effect after defaults and validation.
+fn log_effective_config(options: &StationConfig) {
+    let metrics: Vec<_> = options.public.metrics.iter().map(|quantity| quantity.name()).collect();
+    info!(
+        interval_s = options.interval.as_secs_f64(),
+        database = %options.database_path,
+        csv_directory = options.csv_directory.as_deref().unwrap_or("disabled"),
+        http = %options.http_address.map(|address| address.to_string()).unwrap_or_else(|| "disabled".to_string()),
+        mqtt_broker = options.mqtt.broker.as_deref().unwrap_or("disabled"),
+        mqtt_topic_prefix = %options.mqtt.topic_prefix,
+        public_exclude_sensors = %options.public.exclude_sensors.join(","),
+        public_metrics = %metrics.join(","),
+        public_decimals = options.public.precision.decimals,
+        public_time_resolution_s = options.public.time_resolution_s,
+        "effective config"
+    );
+}
+
+/// Logs the report that should accompany every bug report: board, kernel,
+/// GPIO backend, the effective config and the outcome of probing each
+/// configured sensor.
+pub fn log_hardware_report(options: &StationConfig, probes: &[(&SensorConfig, &Result<Measurement, SensorError>)]) {
+    info!(board = %board_model(), kernel = %kernel_release(), gpio_backend = "rppal", "hardware report");
+    log_effective_config(options);
+    for (sensor, probe) in probes {
+        let probe = probe_result(probe);
+        match &sensor.kind {
+            SensorKind::Dht11 { pin } => info!(sensor = %sensor.name, driver = "dht11", pin, %probe, "sensor probed"),
+            SensorKind::Bme280 { address } => {
+                let chip_id = bme280_chip_id(*address);
+                info!(sensor = %sensor.name, driver = "bme280", address = %format!("{:#04x}", address), %chip_id, %probe, "sensor probed");
+            }
+            SensorKind::Ds18b20 { rom_id, resolution } => {
+                let resolution = resolution.map(|bits| format!("{}bit", bits)).unwrap_or_else(|| "unchanged".to_string());
+                info!(sensor = %sensor.name, driver = "ds18b20", %rom_id, %resolution, %probe, "sensor probed");
+            }
+            SensorKind::Pyranometer { adc_device, adc_channel, .. } => {
+                info!(sensor = %sensor.name, driver = "pyranometer", adc_device, adc_channel, %probe, "sensor probed");
+            }
+            SensorKind::LeafWetness { adc_device, adc_channel, .. } => {
+                info!(sensor = %sensor.name, driver = "leaf_wetness", adc_device, adc_channel, %probe, "sensor probed");
+            }
+            SensorKind::Ultrasonic { trigger_pin, echo_pin, temperature_sensor, .. } => {
+                let temperature_sensor = temperature_sensor.as_deref().unwrap_or("none");
+                info!(sensor = %sensor.name, driver = "ultrasonic", trigger_pin, echo_pin, temperature_sensor, %probe, "sensor probed");
+            }
+        }
+    }
+}
@@ -0,0 +1,475 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use axum::extract::{Query, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use futures_util::stream::{self, Stream};
+use sensor::Quantity;
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+use crate::capabilities::CapabilityReport;
+use crate::config::StationConfig;
+use crate::dashboard::DashboardLayout;
+use crate::diagnostics::{ReliabilityMonitor, ReliabilityStatus};
+use crate::error::StationError;
+use crate::events::{EventBus, StationEvent};
+use crate::kiosk::{self, KioskConfig};
+use crate::metrics::StationMetrics;
+use crate::public::PublicProfile;
+use crate::storage::{self, Annotation, Reading, Storage};
+use crate::supervisor;
+
+const DEFAULT_HISTORY_SPAN_S: i64 = 24 * 60 * 60;
+/// Missing this many samples in a row makes the station unhealthy.
+const STALE_AFTER_INTERVALS: u32 = 3;
+/// The kiosk page never reloads more often, however short the sampling interval.
+const MIN_KIOSK_REFRESH_S: u64 = 30;
+/// User name of the admin area, only the password is configured.
+const ADMIN_USER: &str = "admin";
+const PUBLIC_DASHBOARD: &str = include_str!("dashboard.html");
+const ADMIN_DASHBOARD: &str = include_str!("admin.html");
+
+struct ApiState {
+    storage: Mutex<Storage>,
+    metrics: Arc<StationMetrics>,
+    reliability: Arc<ReliabilityMonitor>,
+    interval: Duration,
+    /// Serialized once, the configuration can't change while the station runs.
+    capabilities: serde_json::Value,
+    public: PublicProfile,
+    dashboard: DashboardLayout,
+    kiosk: KioskConfig,
+    events: EventBus,
+    retention_days: u32,
+    /// Expected `Authorization` header, `None` when no admin password is set.
+    admin_authorization: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    from: Option<i64>,
+    to: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct SeriesQuery {
+    metric: String,
+    /// Comma separated sensor names, every sensor when left out.
+    sensors: Option<String>,
+    from: Option<i64>,
+    to: Option<i64>,
+}
+
+/// Values of one metric of one sensor, as `[timestamp, value]` pairs.
+#[derive(Serialize, Debug, PartialEq)]
+struct Series {
+    sensor_id: String,
+    points: Vec<(i64, f64)>,
+}
+
+#[derive(Serialize)]
+struct Comparison {
+    metric: &'static str,
+    unit: &'static str,
+    series: Vec<Series>,
+}
+
+#[derive(Serialize)]
+struct Health {
+    status: &'static str,
+    last_reading: Option<Reading>,
+    /// Conditions currently degrading bit-banged readouts, with suggested fixes.
+    reliability: ReliabilityStatus,
+}
+
+/// HTTP basic authorization header of the admin user.
+fn admin_authorization(password: &str) -> String {
+    format!("Basic {}", STANDARD.encode(format!("{}:{}", ADMIN_USER, password)))
+}
+
+/// Compares in constant time, so response times don't leak the password.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+fn is_authorized(state: &ApiState, headers: &HeaderMap) -> bool {
+    let given = headers.get(header::AUTHORIZATION).map(|value| value.as_bytes()).unwrap_or_default();
+    state.admin_authorization.as_ref().is_some_and(|expected| constant_time_eq(expected.as_bytes(), given))
+}
+
+fn unauthorized() -> Response {
+    (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Basic realm=\"weather station admin\"")], "admin password required").into_response()
+}
+
+/// The admin area only exists once a password is configured.
+async fn require_admin(State(state): State<Arc<ApiState>>, request: Request, next: Next) -> Response {
+    if state.admin_authorization.is_none() {
+        return (StatusCode::FORBIDDEN, "the admin area is disabled, set http.admin_password").into_response();
+    }
+    if !is_authorized(&state, request.headers()) {
+        return unauthorized();
+    }
+    next.run(request).await
+}
+
+/// The full fidelity API stays open unless an admin password is configured.
+async fn protect_api(State(state): State<Arc<ApiState>>, request: Request, next: Next) -> Response {
+    if state.admin_authorization.is_some() && !is_authorized(&state, request.headers()) {
+        return unauthorized();
+    }
+    next.run(request).await
+}
+
+fn health_status(last_reading: Option<&Reading>, interval: Duration, now: i64) -> &'static str {
+    match last_reading {
+        None => "no_data",
+        Some(reading) if now - reading.timestamp > (interval * STALE_AFTER_INTERVALS).as_secs() as i64 => "stale",
+        Some(reading) if reading.status != "ok" => "failing",
+        Some(_) => "ok",
+    }
+}
+
+async fn current(State(state): State<Arc<ApiState>>) -> Result<Json<Option<Reading>>, StationError> {
+    Ok(Json(state.storage.lock().unwrap().latest_reading()?))
+}
+
+async fn history(State(state): State<Arc<ApiState>>, Query(query): Query<HistoryQuery>) -> Result<Json<Vec<Reading>>, StationError> {
+    let to = query.to.unwrap_or_else(storage::unix_time_now);
+    let from = query.from.unwrap_or(to - DEFAULT_HISTORY_SPAN_S);
+    Ok(Json(state.storage.lock().unwrap().readings_between(from, to)?))
+}
+
+/// Splits `readings` into one series of `metric` per sensor. Requested sensors
+/// keep their order and get a series even without data, so chart legends stay
+/// stable; otherwise sensors appear in the order of their first reading.
+fn split_series(readings: &[Reading], metric: Quantity, sensors: Option<&str>) -> Vec<Series> {
+    let mut series: Vec<Series> = sensors
+        .into_iter()
+        .flat_map(|sensors| sensors.split(','))
+        .map(|sensor_id| Series { sensor_id: sensor_id.trim().to_string(), points: Vec::new() })
+        .collect();
+    for reading in readings {
+        let Some(value) = reading.value(metric) else { continue };
+        match series.iter_mut().find(|series| series.sensor_id == reading.sensor_id) {
+            Some(series) => series.points.push((reading.timestamp, value)),
+            None if sensors.is_none() => series.push(Series { sensor_id: reading.sensor_id.clone(), points: vec![(reading.timestamp, value)] }),
+            None => {}
+        }
+    }
+    series
+}
+
+fn comparison(readings: &[Reading], query: &SeriesQuery) -> Response {
+    let Some(metric) = Quantity::parse(&query.metric) else {
+        return (StatusCode::BAD_REQUEST, format!("unknown metric {}", query.metric)).into_response();
+    };
+    Json(Comparison { metric: metric.name(), unit: metric.unit(), series: split_series(readings, metric, query.sensors.as_deref()) }).into_response()
+}
+
+/// Multi-series variant of `history`, one metric of several sensors for comparing them.
+async fn series(State(state): State<Arc<ApiState>>, Query(query): Query<SeriesQuery>) -> Result<Response, StationError> {
+    let to = query.to.unwrap_or_else(storage::unix_time_now);
+    let from = query.from.unwrap_or(to - DEFAULT_HISTORY_SPAN_S);
+    let readings = state.storage.lock().unwrap().readings_between(from, to)?;
+    Ok(comparison(&readings, &query))
+}
+
+async fn public_series(State(state): State<Arc<ApiState>>, Query(query): Query<SeriesQuery>) -> Result<Response, StationError> {
+    let to = query.to.unwrap_or_else(storage::unix_time_now);
+    let from = query.from.unwrap_or(to - DEFAULT_HISTORY_SPAN_S);
+    let readings = state.storage.lock().unwrap().readings_between(from, to)?;
+    let readings: Vec<Reading> = readings.iter().filter_map(|reading| state.public.apply(reading)).collect();
+    Ok(comparison(&readings, &query))
+}
+
+/// Latest reading of every public sensor, reduced by the public profile.
+async fn public_current(State(state): State<Arc<ApiState>>) -> Result<Json<Vec<Reading>>, StationError> {
+    let readings = state.storage.lock().unwrap().latest_reading_per_sensor()?;
+    Ok(Json(readings.iter().filter_map(|reading| state.public.apply(reading)).collect()))
+}
+
+async fn public_history(State(state): State<Arc<ApiState>>, Query(query): Query<HistoryQuery>) -> Result<Json<Vec<Reading>>, StationError> {
+    let to = query.to.unwrap_or_else(storage::unix_time_now);
+    let from = query.from.unwrap_or(to - DEFAULT_HISTORY_SPAN_S);
+    let readings = state.storage.lock().unwrap().readings_between(from, to)?;
+    Ok(Json(readings.iter().filter_map(|reading| state.public.apply(reading)).collect()))
+}
+
+async fn annotations(State(state): State<Arc<ApiState>>, Query(query): Query<HistoryQuery>) -> Result<Json<Vec<Annotation>>, StationError> {
+    let to = query.to.unwrap_or_else(storage::unix_time_now);
+    let from = query.from.unwrap_or(to - DEFAULT_HISTORY_SPAN_S);
+    Ok(Json(state.storage.lock().unwrap().annotations_between(from, to)?))
+}
+
+/// Public like the dashboard, so the status covers every sensor but the reading
+/// shown is the latest one the public profile lets through.
+async fn health(State(state): State<Arc<ApiState>>) -> Result<(StatusCode, Json<Health>), StationError> {
+    let (latest, per_sensor) = {
+        let storage = state.storage.lock().unwrap();
+        (storage.latest_reading()?, storage.latest_reading_per_sensor()?)
+    };
+    let status = health_status(latest.as_ref(), state.interval, storage::unix_time_now());
+    let code = if status == "ok" { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    let last_reading = per_sensor.iter().filter_map(|reading| state.public.apply(reading)).max_by_key(|reading| reading.timestamp);
+    Ok((code, Json(Health { status, last_reading, reliability: state.reliability.status() })))
+}
+
+/// Panels the dashboard page lays out, from `[[dashboard.panels]]`.
+async fn dashboard_layout(State(state): State<Arc<ApiState>>) -> Json<DashboardLayout> {
+    Json(state.dashboard.clone())
+}
+
+/// Public readings of today and the last three hours, for the pressure tendency.
+async fn kiosk_page(State(state): State<Arc<ApiState>>) -> Result<Html<String>, StationError> {
+    let now = storage::unix_time_now();
+    let from = state.kiosk.start_of_day(now).min(now - kiosk::TENDENCY_SPAN_S);
+    let (current, recent) = {
+        let storage = state.storage.lock().unwrap();
+        (storage.latest_reading_per_sensor()?, storage.readings_between(from, now)?)
+    };
+    let current: Vec<Reading> = current.iter().filter_map(|reading| state.public.apply(reading)).collect();
+    let recent: Vec<Reading> = recent.iter().filter_map(|reading| state.public.apply(reading)).collect();
+    let refresh_s = state.interval.as_secs().max(MIN_KIOSK_REFRESH_S);
+    Ok(Html(kiosk::render(&state.kiosk, &current, &recent, now, refresh_s)))
+}
+
+/// Events as they are published, `public` ones through the public profile. A
+/// client that fell behind gets a `lagged` event with the number it missed.
+fn event_stream(state: Arc<ApiState>, public: bool) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = state.events.subscribe();
+    let events = stream::unfold((state, receiver), move |(state, mut receiver)| async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(StationEvent::Reading(reading)) if public => match state.public.apply(&reading) {
+                    Some(reading) => StationEvent::Reading(reading),
+                    None => continue,
+                },
+                Ok(event) => event,
+                Err(RecvError::Lagged(missed)) => {
+                    warn!(missed, "event stream client fell behind");
+                    return Some((Ok(Event::default().event("lagged").data(missed.to_string())), (state, receiver)));
+                }
+                Err(RecvError::Closed) => return None,
+            };
+            let data = match &event {
+                StationEvent::Reading(reading) => serde_json::to_string(reading).unwrap_or_default(),
+            };
+            return Some((Ok(Event::default().event(event.name()).data(data)), (state, receiver)));
+        }
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+async fn events(State(state): State<Arc<ApiState>>) -> impl IntoResponse {
+    event_stream(state, false)
+}
+
+async fn public_events(State(state): State<Arc<ApiState>>) -> impl IntoResponse {
+    event_stream(state, true)
+}
+
+async fn public_dashboard() -> Html<&'static str> {
+    Html(PUBLIC_DASHBOARD)
+}
+
+async fn admin_dashboard() -> Html<&'static str> {
+    Html(ADMIN_DASHBOARD)
+}
+
+/// Latest reading of every sensor, unfiltered, for the admin sensor status.
+async fn admin_sensors(State(state): State<Arc<ApiState>>) -> Result<Json<Vec<Reading>>, StationError> {
+    Ok(Json(state.storage.lock().unwrap().latest_reading_per_sensor()?))
+}
+
+#[derive(Serialize)]
+struct Pruned {
+    deleted: usize,
+}
+
+/// Records a maintenance window, shaded on the dashboard charts.
+async fn admin_annotate(State(state): State<Arc<ApiState>>, Json(annotation): Json<Annotation>) -> Result<StatusCode, StationError> {
+    if annotation.end < annotation.start {
+        return Ok(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    state.storage.lock().unwrap().insert_annotation(&annotation)?;
+    Ok(StatusCode::CREATED)
+}
+
+/// Deletes readings past the retention period now instead of at the daily prune.
+async fn admin_prune(State(state): State<Arc<ApiState>>) -> Result<Json<Pruned>, StationError> {
+    let deleted = state.storage.lock().unwrap().prune(state.retention_days, storage::unix_time_now())?;
+    Ok(Json(Pruned { deleted }))
+}
+
+async fn capabilities(State(state): State<Arc<ApiState>>) -> Json<serde_json::Value> {
+    Json(state.capabilities.clone())
+}
+
+async fn metrics(State(state): State<Arc<ApiState>>) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], state.metrics.render())
+}
+
+fn router(state: Arc<ApiState>) -> Router {
+    let admin = Router::new()
+        .route("/admin", get(admin_dashboard))
+        .route("/api/admin/sensors", get(admin_sensors))
+        .route("/api/admin/prune", post(admin_prune))
+        .route("/api/admin/annotations", post(admin_annotate))
+        .route_layer(middleware::from_fn_with_state(Arc::clone(&state), require_admin));
+    let api = Router::new()
+        .route("/api/current", get(current))
+        .route("/api/history", get(history))
+        .route("/api/series", get(series))
+        .route("/api/v1/capabilities", get(capabilities))
+        .route("/api/v1/events", get(events))
+        .route("/metrics", get(metrics))
+        .route_layer(middleware::from_fn_with_state(Arc::clone(&state), protect_api));
+    Router::new()
+        .route("/", get(public_dashboard))
+        .route("/kiosk", get(kiosk_page))
+        .route("/api/public/current", get(public_current))
+        .route("/api/public/history", get(public_history))
+        .route("/api/public/series", get(public_series))
+        .route("/api/public/annotations", get(annotations))
+        .route("/api/public/dashboard", get(dashboard_layout))
+        .route("/api/public/events", get(public_events))
+        .route("/api/health", get(health))
+        .merge(admin)
+        .merge(api)
+        .with_state(state)
+}
+
+async fn serve(address: SocketAddr, state: Arc<ApiState>) -> Result<(), StationError> {
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    axum::serve(listener, router(state)).await?;
+    Ok(())
+}
+
+/// Serves the station's database, `metrics` and capabilities on a background thread.
+/// The dashboard at `/` and the public routes only serve what the public profile
+/// lets through and need no password, the admin area at `/admin` does.
+/// The API uses its own connection so requests never wait for a readout.
+/// The server is restarted when it fails, e.g. while the address is still taken.
+pub fn spawn(address: SocketAddr, options: &StationConfig, metrics: Arc<StationMetrics>, reliability: Arc<ReliabilityMonitor>, events: &EventBus) -> Result<(), StationError> {
+    let state = Arc::new(ApiState {
+        storage: Mutex::new(Storage::open(&options.database_path)?),
+        metrics,
+        reliability,
+        interval: options.interval,
+        capabilities: serde_json::to_value(CapabilityReport::new(options)).unwrap_or_default(),
+        public: options.public.clone(),
+        dashboard: options.dashboard.clone(),
+        kiosk: options.kiosk.clone(),
+        events: events.clone(),
+        retention_days: options.retention_days,
+        admin_authorization: options.http_admin_password.as_deref().map(admin_authorization),
+    });
+    supervisor::spawn("http_api", move || {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_io().enable_time().build()?;
+        runtime.block_on(serve(address, Arc::clone(&state)))
+    })?;
+    info!(%address, "HTTP API listening");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use crate::diagnostics::ReliabilityWarning;
+
+    fn reading(timestamp: i64, status: &str) -> Reading {
+        Reading { timestamp, sensor_id: "dht11".to_string(), temperature: None, humidity: None, status: status.to_string(), pressure: None, irradiance: None, snow_depth: None, leaf_wetness: None }
+    }
+
+    #[test]
+    fn health_reflects_last_reading() {
+        let interval = Duration::from_secs(60);
+        assert_eq!(health_status(None, interval, 1000), "no_data");
+        assert_eq!(health_status(Some(&reading(990, "ok")), interval, 1000), "ok");
+        assert_eq!(health_status(Some(&reading(990, "timeout")), interval, 1000), "failing");
+        assert_eq!(health_status(Some(&reading(700, "ok")), interval, 1000), "stale");
+    }
+
+    #[test]
+    fn health_hides_excluded_sensors_and_shows_reliability_warnings() {
+        let storage = Storage::open(":memory:").unwrap();
+        let now = storage::unix_time_now();
+        storage.insert_reading(&Reading { sensor_id: "outdoor".to_string(), temperature: Some(4.53), ..reading(now - 20, "ok") }).unwrap();
+        storage.insert_reading(&Reading { sensor_id: "indoor".to_string(), temperature: Some(21.37), ..reading(now - 10, "ok") }).unwrap();
+        let state = Arc::new(ApiState {
+            storage: Mutex::new(storage),
+            metrics: Arc::new(StationMetrics::default()),
+            reliability: Arc::new(ReliabilityMonitor::default()),
+            interval: Duration::from_secs(60),
+            capabilities: serde_json::Value::Null,
+            public: PublicProfile { exclude_sensors: vec!["indoor".to_string()], ..PublicProfile::default() },
+            dashboard: DashboardLayout { panels: Vec::new() },
+            kiosk: KioskConfig::default(),
+            events: EventBus::default(),
+            retention_days: 30,
+            admin_authorization: None,
+        });
+        let warning = ReliabilityWarning { check: "cpu_governor", problem: "cpu governor is powersave".to_string(), suggestion: "set the governor to performance" };
+        state.reliability.update(ReliabilityStatus::new(vec![warning], Some(0.25)));
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        listener.set_nonblocking(true).unwrap();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_io().build().unwrap();
+            runtime.block_on(async { axum::serve(tokio::net::TcpListener::from_std(listener).unwrap(), router(state)).await })
+        });
+        let mut connection = std::net::TcpStream::connect(address).unwrap();
+        connection.write_all(b"GET /api/health HTTP/1.1\r\nHost: station\r\nConnection: close\r\n\r\n").unwrap();
+        let mut response = String::new();
+        connection.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        let body: serde_json::Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["last_reading"]["sensor_id"], "outdoor");
+        assert_eq!(body["last_reading"]["temperature"], 5.0);
+        assert!(!response.contains("indoor"));
+        assert_eq!(body["reliability"]["warnings"][0]["check"], "cpu_governor");
+        assert_eq!(body["reliability"]["checksum_error_rate"], 0.25);
+        assert_eq!(body["reliability"]["checksum_errors_explained"], true);
+    }
+
+    #[test]
+    fn readings_are_split_per_sensor() {
+        let readings = [
+            Reading { temperature: Some(21.0), ..reading(100, "ok") },
+            Reading { sensor_id: "outdoor".to_string(), temperature: Some(4.5), ..reading(100, "ok") },
+            reading(160, "timeout"),
+            Reading { temperature: Some(21.5), ..reading(220, "ok") },
+        ];
+        let series = split_series(&readings, Quantity::Temperature, None);
+        assert_eq!(series, [
+            Series { sensor_id: "dht11".to_string(), points: vec![(100, 21.0), (220, 21.5)] },
+            Series { sensor_id: "outdoor".to_string(), points: vec![(100, 4.5)] },
+        ]);
+
+        let series = split_series(&readings, Quantity::Temperature, Some("outdoor, greenhouse"));
+        assert_eq!(series.iter().map(|series| (series.sensor_id.as_str(), series.points.len())).collect::<Vec<_>>(), [("outdoor", 1), ("greenhouse", 0)]);
+        assert!(split_series(&readings, Quantity::Pressure, None).is_empty());
+    }
+
+    #[test]
+    fn admin_credentials_use_basic_authorization() {
+        assert_eq!(admin_authorization("pw"), "Basic YWRtaW46cHc=");
+        assert_eq!(admin_authorization("secret"), "Basic YWRtaW46c2VjcmV0");
+        assert!(constant_time_eq(b"Basic YWRtaW46c2VjcmV0", admin_authorization("secret").as_bytes()));
+        assert!(!constant_time_eq(b"Basic YWRtaW46c2VjcmV0", b"Basic YWRtaW46c2VjcmV"));
+    }
+}
@@ -0,0 +1,165 @@
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use crate::cli::InstallServiceArgs;
+use crate::config::DEFAULT_CONFIG_PATH;
+use crate::error::StationError;
+use crate::preflight;
+
+const SERVICE_NAME: &str = "weather_station.service";
+const SYSTEM_UNIT_DIRECTORY: &str = "/etc/systemd/system";
+const SYSTEM_STATE_DIRECTORY: &str = "/var/lib/weather_station";
+/// Device access the sensors need besides GPIO memory.
+const HARDWARE_GROUPS: [&str; 3] = ["gpio", "i2c", "spi"];
+/// Readable by the service group only, readings reveal when people are home.
+const STATE_DIRECTORY_MODE: u32 = 0o750;
+
+struct ServiceUnit {
+    executable: PathBuf,
+    state_directory: PathBuf,
+    /// `None` for user services, they run as the owning user anyway.
+    user: Option<String>,
+}
+
+impl ServiceUnit {
+    fn config_path(&self) -> PathBuf {
+        self.state_directory.join(DEFAULT_CONFIG_PATH)
+    }
+
+    fn render(&self) -> String {
+        let mut unit = String::new();
+        unit.push_str("[Unit]\nDescription=Weather station\n");
+        unit.push_str("Wants=network-online.target\nAfter=network-online.target time-sync.target\n");
+        // Keep restarting a station with a loose sensor wire, just not in a tight loop.
+        unit.push_str("StartLimitIntervalSec=600\nStartLimitBurst=5\n\n");
+
+        // The station reports readiness and feeds the watchdog, see systemd.rs.
+        // It sets the timeout from its interval, this applies before it does.
+        unit.push_str("[Service]\nType=notify\nWatchdogSec=600\n");
+        unit.push_str(&format!("ExecStart={} --config {}\n", self.executable.display(), self.config_path().display()));
+        unit.push_str(&format!("WorkingDirectory={}\n", self.state_directory.display()));
+        if let Some(user) = &self.user {
+            unit.push_str(&format!("User={}\nSupplementaryGroups={}\n", user, HARDWARE_GROUPS.join(" ")));
+        }
+        unit.push_str("Restart=always\nRestartSec=10\n\n");
+
+        let target = if self.user.is_some() { "multi-user.target" } else { "default.target" };
+        unit.push_str(&format!("[Install]\nWantedBy={}\n", target));
+        unit
+    }
+}
+
+fn home_directory() -> Result<PathBuf, StationError> {
+    env::var_os("HOME").map(PathBuf::from).ok_or_else(|| StationError::Install("HOME is not set".to_string()))
+}
+
+fn systemctl(user_scope: bool, args: &[&str]) -> Result<(), StationError> {
+    let mut command = Command::new("systemctl");
+    if user_scope {
+        command.arg("--user");
+    }
+    let status = command.args(args).status()?;
+    if !status.success() {
+        return Err(StationError::Install(format!("systemctl {} failed with {}", args.join(" "), status)));
+    }
+    Ok(())
+}
+
+fn create_state_directory(unit: &ServiceUnit) -> Result<(), StationError> {
+    fs::create_dir_all(&unit.state_directory)?;
+    fs::set_permissions(&unit.state_directory, fs::Permissions::from_mode(STATE_DIRECTORY_MODE))?;
+    if let Some(user) = &unit.user {
+        let status = Command::new("chown").arg(format!("{}:{}", user, user)).arg(&unit.state_directory).status()?;
+        if !status.success() {
+            return Err(StationError::Install(format!("cannot hand {} over to {}", unit.state_directory.display(), user)));
+        }
+    }
+
+    // The service passes --config explicitly, which requires the file to exist.
+    let config_path = unit.config_path();
+    if !config_path.exists() {
+        if Path::new(DEFAULT_CONFIG_PATH).exists() {
+            fs::copy(DEFAULT_CONFIG_PATH, &config_path)?;
+            println!("Copied {} to {}", DEFAULT_CONFIG_PATH, config_path.display());
+        } else {
+            fs::write(&config_path, "# Defaults apply, see config.example.toml for the available keys.\n")?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the systemd unit, prepares the state directory and enables the service,
+/// so a fresh Pi goes from a copied binary to a running station in one command.
+pub fn install_service(args: &InstallServiceArgs) -> Result<(), StationError> {
+    let (unit_directory, default_state_directory, user) = if args.user {
+        let home = home_directory()?;
+        (home.join(".config/systemd/user"), home.join(".local/state/weather_station"), None)
+    } else {
+        let user = args.run_as.clone().or_else(|| env::var("SUDO_USER").ok()).unwrap_or_else(|| "pi".to_string());
+        (PathBuf::from(SYSTEM_UNIT_DIRECTORY), PathBuf::from(SYSTEM_STATE_DIRECTORY), Some(user))
+    };
+    let unit = ServiceUnit {
+        executable: env::current_exe()?,
+        state_directory: args.state_dir.clone().map(PathBuf::from).unwrap_or(default_state_directory),
+        user,
+    };
+
+    if args.dry_run {
+        print!("{}", unit.render());
+        return Ok(());
+    }
+
+    create_state_directory(&unit)?;
+    fs::create_dir_all(&unit_directory)?;
+    let unit_path = unit_directory.join(SERVICE_NAME);
+    fs::write(&unit_path, unit.render())?;
+    println!("Wrote {}", unit_path.display());
+
+    if args.user {
+        // User services can't request groups, the user has to be in them.
+        for group in HARDWARE_GROUPS {
+            if preflight::is_in_group(group) == Some(false) {
+                println!("Note: add yourself to the {} group with `sudo usermod -aG {} $USER` and log in again", group, group);
+            }
+        }
+        println!("Note: run `loginctl enable-linger $USER` so the station keeps running after logout");
+    }
+
+    systemctl(args.user, &["daemon-reload"])?;
+    if !args.no_enable {
+        systemctl(args.user, &["enable", "--now", SERVICE_NAME])?;
+        println!("{} enabled and started", SERVICE_NAME);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn system_unit_runs_as_user_with_hardware_groups() {
+        let unit = ServiceUnit {
+            executable: PathBuf::from("/usr/local/bin/weather_station"),
+            state_directory: PathBuf::from(SYSTEM_STATE_DIRECTORY),
+            user: Some("pi".to_string()),
+        };
+        let text = unit.render();
+
+        assert!(text.contains("ExecStart=/usr/local/bin/weather_station --config /var/lib/weather_station/config.toml\n"));
+        assert!(text.contains("User=pi\nSupplementaryGroups=gpio i2c spi\n"));
+        assert!(text.contains("Type=notify\nWatchdogSec=600\n"));
+        assert!(text.ends_with("WantedBy=multi-user.target\n"));
+    }
+
+    #[test]
+    fn user_unit_has_no_user_or_groups() {
+        let unit = ServiceUnit { executable: PathBuf::from("/home/pi/weather_station"), state_directory: PathBuf::from("/home/pi/.local/state/weather_station"), user: None };
+        let text = unit.render();
+
+        assert!(!text.contains("User="));
+        assert!(text.ends_with("WantedBy=default.target\n"));
+    }
+}
@@ -0,0 +1,207 @@
+use std::fmt::Write;
+use weather_math::PressureTendency;
+use crate::storage::Reading;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+/// Span the pressure tendency is computed over, as barometers do.
+pub const TENDENCY_SPAN_S: i64 = 3 * 60 * 60;
+/// Shorter histories say too little about the tendency.
+const MIN_TENDENCY_SPAN_S: i64 = 60 * 60;
+/// Sizes follow the screen width, so any tablet is filled.
+const PAGE_HEAD: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta http-equiv="refresh" content="REFRESH_S">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Weather station</title>
+<style>
+  body { font-family: sans-serif; margin: 0; padding: 2vw; background: #fff; color: #111; }
+  body.night { background: #000; color: #bbb; }
+  header { display: flex; justify-content: space-between; font-size: 6vw; }
+  .sensor { display: inline-block; vertical-align: top; margin: 2vw 4vw 0 0; }
+  .name { font-size: 3vw; }
+  .temperature { font-size: 14vw; line-height: 1; }
+  .details { font-size: 4vw; }
+</style>
+</head>
+"#;
+
+/// Local time of the wall display. A fixed offset, so it needs adjusting when
+/// daylight saving time starts or ends.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KioskConfig {
+    ///
+    /// # Unit
+    /// Seconds east of UTC.
+    pub utc_offset_s: i64,
+    /// Local hour the page turns dark.
+    pub night_start_h: u32,
+    /// Local hour the page turns light again.
+    pub night_end_h: u32,
+}
+
+impl Default for KioskConfig {
+    fn default() -> Self {
+        KioskConfig { utc_offset_s: 0, night_start_h: 20, night_end_h: 7 }
+    }
+}
+
+impl KioskConfig {
+    fn seconds_of_day(&self, timestamp: i64) -> i64 {
+        (timestamp + self.utc_offset_s).rem_euclid(SECONDS_PER_DAY)
+    }
+
+    /// Local midnight before `now`, in seconds since the unix epoch.
+    pub fn start_of_day(&self, now: i64) -> i64 {
+        now - self.seconds_of_day(now)
+    }
+
+    fn is_night(&self, now: i64) -> bool {
+        let hour = (self.seconds_of_day(now) / 3600) as u32;
+        if self.night_start_h <= self.night_end_h {
+            (self.night_start_h..self.night_end_h).contains(&hour)
+        } else {
+            hour >= self.night_start_h || hour < self.night_end_h
+        }
+    }
+
+    fn clock(&self, timestamp: i64) -> String {
+        let seconds = self.seconds_of_day(timestamp);
+        format!("{:02}:{:02}", seconds / 3600, seconds % 3600 / 60)
+    }
+}
+
+/// Three hour tendency of the first sensor measuring pressure, extrapolated
+/// from at least an hour of readings.
+fn pressure_tendency(readings: &[Reading], now: i64) -> Option<PressureTendency> {
+    let sensor_id = &readings.iter().find(|reading| reading.pressure.is_some())?.sensor_id;
+    let mut samples = readings
+        .iter()
+        .filter(|reading| reading.sensor_id == *sensor_id && reading.timestamp >= now - TENDENCY_SPAN_S)
+        .filter_map(|reading| Some((reading.timestamp, reading.pressure?)));
+    let first = samples.next()?;
+    let last = samples.next_back()?;
+    let span = last.0 - first.0;
+    if span < MIN_TENDENCY_SPAN_S {
+        return None;
+    }
+    Some(PressureTendency::from_change((last.1 - first.1) * TENDENCY_SPAN_S as f64 / span as f64))
+}
+
+fn forecast(tendency: PressureTendency) -> (&'static str, &'static str) {
+    match tendency {
+        PressureTendency::Rising => ("\u{2600}", "Improving"),
+        PressureTendency::Steady => ("\u{26C5}", "Steady"),
+        PressureTendency::Falling => ("\u{2602}", "Worsening"),
+    }
+}
+
+/// Lowest and highest temperature of `sensor_id` in `readings` since `since`.
+fn temperature_extremes(readings: &[Reading], sensor_id: &str, since: i64) -> Option<(f64, f64)> {
+    readings
+        .iter()
+        .filter(|reading| reading.sensor_id == sensor_id && reading.timestamp >= since)
+        .filter_map(|reading| reading.temperature)
+        .fold(None, |extremes, value| match extremes {
+            None => Some((value, value)),
+            Some((min, max)) => Some((f64::min(min, value), f64::max(max, value))),
+        })
+}
+
+/// Full screen page for a wall mounted tablet, without scripts so old browsers
+/// show it too. It reloads itself every `refresh_s`.
+///
+/// # Parameters
+/// current = latest reading per sensor, recent = readings since the start of
+/// the day or the last three hours, whichever is longer
+pub fn render(config: &KioskConfig, current: &[Reading], recent: &[Reading], now: i64, refresh_s: u64) -> String {
+    let mut html = String::new();
+    html.push_str(&PAGE_HEAD.replace("REFRESH_S", &refresh_s.to_string()));
+    let _ = write!(html, "<body class=\"{}\">\n<header><span>{}</span>", if config.is_night(now) { "night" } else { "day" }, config.clock(now));
+    if let Some((icon, text)) = pressure_tendency(recent, now).map(forecast) {
+        let _ = write!(html, "<span>{} {}</span>", icon, text);
+    }
+    html.push_str("</header>\n");
+
+    for reading in current {
+        let _ = write!(html, "<div class=\"sensor\"><div class=\"name\">{}</div>", reading.sensor_id);
+        match reading.temperature {
+            Some(temperature) => {
+                let _ = write!(html, "<div class=\"temperature\">{}&deg;</div>", temperature);
+            }
+            None => html.push_str("<div class=\"temperature\">&ndash;</div>"),
+        }
+        html.push_str("<div class=\"details\">");
+        if let Some(humidity) = reading.humidity {
+            let _ = write!(html, "{}% ", humidity);
+        }
+        if let Some(pressure) = reading.pressure {
+            let _ = write!(html, "{} hPa ", pressure);
+        }
+        if let Some(irradiance) = reading.irradiance {
+            let _ = write!(html, "{} W/m&sup2; ", irradiance);
+        }
+        if let Some(snow_depth) = reading.snow_depth {
+            let _ = write!(html, "Snow {} cm ", snow_depth);
+        }
+        if let Some(leaf_wetness) = reading.leaf_wetness {
+            let _ = write!(html, "Leaves {}% wet", leaf_wetness);
+        }
+        if let Some((min, max)) = temperature_extremes(recent, &reading.sensor_id, config.start_of_day(now)) {
+            let _ = write!(html, "<br>Today {}&deg; &ndash; {}&deg;", min, max);
+        }
+        match reading.status.as_str() {
+            "ok" => {
+                let _ = write!(html, "<br>{}", config.clock(reading.timestamp));
+            }
+            status => {
+                let _ = write!(html, "<br>{}", status);
+            }
+        }
+        html.push_str("</div></div>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(timestamp: i64, temperature: f64, pressure: Option<f64>) -> Reading {
+        Reading { timestamp, sensor_id: "outdoor".to_string(), temperature: Some(temperature), humidity: Some(60.0), status: "ok".to_string(), pressure, irradiance: None, snow_depth: None, leaf_wetness: None }
+    }
+
+    #[test]
+    fn local_day_and_night() {
+        let config = KioskConfig { utc_offset_s: 2 * 3600, ..Default::default() };
+        // 2023-11-14 22:13:20 UTC, past midnight locally.
+        let now = 1_700_000_000;
+        assert_eq!(config.clock(now), "00:13");
+        assert_eq!(config.start_of_day(now), now - 13 * 60 - 20);
+        assert!(config.is_night(now));
+        assert!(!config.is_night(now + 10 * 3600));
+        assert!(KioskConfig { night_start_h: 0, night_end_h: 6, ..config }.is_night(now));
+    }
+
+    #[test]
+    fn page_shows_extremes_and_forecast() {
+        let now = 1_700_000_000;
+        let recent = [
+            reading(now - 3 * 3600, 4.0, Some(1015.0)),
+            reading(now - 3600, 7.5, Some(1013.0)),
+            reading(now - 600, 6.0, Some(1011.0)),
+        ];
+        assert_eq!(pressure_tendency(&recent, now), Some(PressureTendency::Falling));
+        // Less than an hour of history.
+        assert_eq!(pressure_tendency(&recent[1..], now), None);
+
+        let html = render(&KioskConfig::default(), &recent[2..], &recent, now, 60);
+        assert!(html.contains("<meta http-equiv=\"refresh\" content=\"60\">"));
+        assert!(html.contains("Worsening"));
+        assert!(html.contains("<div class=\"temperature\">6&deg;</div>"));
+        assert!(html.contains("Today 4&deg; &ndash; 7.5&deg;"));
+        assert!(html.contains("<body class=\"night\">"));
+    }
+}
@@ -0,0 +1,6 @@
+//! Storage and the daily values computed from it, a library so the benchmarks
+//! in `benches/` can reach them. The station binary uses them from here.
+
+pub mod error;
+pub mod evapotranspiration;
+pub mod storage;
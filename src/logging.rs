@@ -0,0 +1,18 @@
+use std::io;
+use tracing_subscriber::EnvFilter;
+use crate::cli::OutputFormat;
+
+/// Verbosity when `RUST_LOG` is not set.
+const DEFAULT_FILTER: &str = "info";
+
+/// Sends events to stderr, so stdout keeps only the output of commands.
+/// `RUST_LOG` (e.g. `weather_station=debug`) filters them, JSON events suit
+/// ingestion by journald or Loki.
+pub fn init(format: OutputFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter).with_writer(io::stderr);
+    match format {
+        OutputFormat::Text => subscriber.init(),
+        OutputFormat::Json => subscriber.json().init(),
+    }
+}
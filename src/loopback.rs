@@ -0,0 +1,84 @@
+use std::process;
+use std::time::{Duration, Instant};
+use rppal::gpio::{Gpio, InputPin, Level, OutputPin};
+use crate::cli::LoopbackArgs;
+use crate::preflight;
+
+const LOOPBACK_ROUNDS: u32 = 100;
+const PROPAGATION_TIMEOUT: Duration = Duration::from_millis(10);
+
+struct LoopbackResult {
+    failures: u32,
+    latencies_us: Vec<f64>,
+}
+
+fn measure_propagation(output: &mut OutputPin, input: &InputPin, level: Level) -> Option<f64> {
+    let start = Instant::now();
+    output.write(level);
+    while input.read() != level {
+        if start.elapsed() > PROPAGATION_TIMEOUT {
+            return None;
+        }
+    }
+    Some(start.elapsed().as_secs_f64() * 1_000_000.0)
+}
+
+fn run_rounds(output: &mut OutputPin, input: &InputPin) -> LoopbackResult {
+    let mut result = LoopbackResult { failures: 0, latencies_us: Vec::new() };
+    for _ in 0..LOOPBACK_ROUNDS {
+        for level in [Level::High, Level::Low] {
+            match measure_propagation(output, input, level) {
+                Some(latency) => result.latencies_us.push(latency),
+                None => result.failures += 1,
+            }
+        }
+    }
+    result
+}
+
+fn fail(message: String) -> ! {
+    println!("Loopback test failed: {}", message);
+    process::exit(1);
+}
+
+/// Drives `--output` and checks that `--input`, jumpered to it, follows. A pass
+/// here with a failing sensor points at the sensor rather than the GPIO setup.
+pub fn loopback_test(args: &LoopbackArgs) {
+    let (output_pin, input_pin) = (args.output, args.input);
+
+    let gpio = Gpio::new().unwrap_or_else(|error| {
+        preflight::log_preflight_problems(&preflight::run_preflight());
+        fail(format!("cannot access GPIO ({})", error))
+    });
+    let mut output = gpio
+        .get(output_pin)
+        .unwrap_or_else(|error| fail(format!("cannot claim output pin {} ({})", output_pin, error)))
+        .into_output_low();
+    let input = gpio
+        .get(input_pin)
+        .unwrap_or_else(|error| fail(format!("cannot claim input pin {} ({})", input_pin, error)))
+        .into_input();
+
+    let result = run_rounds(&mut output, &input);
+    if result.latencies_us.is_empty() {
+        fail(format!("pin {} does not follow pin {}, check the jumper and pin numbers", input_pin, output_pin));
+    }
+
+    let min = result.latencies_us.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = result.latencies_us.iter().cloned().fold(0.0, f64::max);
+    let average = result.latencies_us.iter().sum::<f64>() / result.latencies_us.len() as f64;
+    println!(
+        "Loopback {} -> {}: {} of {} transitions propagated, latency min {:.1} us, avg {:.1} us, max {:.1} us",
+        output_pin,
+        input_pin,
+        result.latencies_us.len(),
+        LOOPBACK_ROUNDS * 2,
+        min,
+        average,
+        max
+    );
+
+    if result.failures > 0 {
+        fail(format!("{} transitions did not propagate, the connection is unreliable", result.failures));
+    }
+}
@@ -1,26 +1,83 @@
+use std::process;
 use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use dht11::{dht11_perform_readout, Dht11Pin, Dht11Timing};
-use rppal::gpio::{Gpio, IoPin, Mode};
+use std::time::{Duration, Instant};
+use clap::Parser;
+use cli::{Cli, Command, OutputFormat, RunArgs};
+use config::StationConfig;
+use bme280::{Bme280Bus, Bme280Error, Bme280Timing};
+use dht11::{Dht11Edge, Dht11EdgeCapture, Dht11Pin, Dht11Timing};
+use error::StationError;
+use gps::GpsSerial;
+use leaf_wetness::{LeafWetnessAdc, LeafWetnessError};
+use pyranometer::{PyranometerAdc, PyranometerError};
+use rppal::gpio::{Gpio, IoPin, Mode, Trigger};
+use rppal::i2c::I2c;
+use rppal::uart::{Parity, Uart};
+use tracing::error;
+use ultrasonic::{UltrasonicPin, UltrasonicTiming};
+use weather_station::{error, evapotranspiration, storage};
+
+mod acquisition;
+mod capabilities;
+mod cli;
+mod commands;
+mod config;
+mod connectivity;
+mod csv_log;
+mod dashboard;
+mod diagnostics;
+mod emulate;
+mod events;
+mod export;
+mod hardware_report;
+mod http_api;
+mod install_service;
+mod kiosk;
+mod logging;
+mod loopback;
+mod metrics;
+mod mqtt;
+mod preflight;
+mod precision;
+mod public;
+mod raw_readout;
+mod selftest;
+mod sensors;
+mod shutdown;
+mod signing;
+mod supervisor;
+mod system_metrics;
+mod systemd;
+mod units;
+
+/// Far longer than any pulse of a frame.
+const EDGE_CAPTURE_IDLE: Duration = Duration::from_millis(2);
 
 struct IoPinDht {
     pin: IoPin
 }
 
 impl IoPinDht {
-    fn new(pin_number: u8) -> Self {
-        let gpio: Gpio = Gpio::new().unwrap();
-        IoPinDht{ pin: gpio.get(pin_number).unwrap().into_io(Mode::Output)}
+    fn new(pin_number: u8) -> Result<Self, StationError> {
+        let gpio: Gpio = Gpio::new()?;
+        Ok(IoPinDht{ pin: gpio.get(pin_number)?.into_io(Mode::Output)})
+    }
+}
+
+/// Releases the line, the pull-up keeps an idle sensor's line high.
+impl Drop for IoPinDht {
+    fn drop(&mut self) {
+        self.pin.set_mode(Mode::Input);
     }
 }
 
 impl Dht11Pin for IoPinDht {
     fn is_low(&mut self) -> bool {
-        return self.pin.is_low();
+        self.pin.is_low()
     }
 
     fn is_high(&mut self) -> bool {
-        return self.pin.is_high();
+        self.pin.is_high()
     }
 
     fn set_low(&mut self) {
@@ -40,11 +97,190 @@ impl Dht11Pin for IoPinDht {
     }
 }
 
-struct Timing;
+/// Trigger or echo line of an ultrasonic distance sensor. The JSN-SR04T and
+/// HC-SR04 echo at 5 V, it needs a divider down to 3.3 V.
+struct UltrasonicGpio {
+    pin: IoPin
+}
+
+impl UltrasonicGpio {
+    fn new(pin_number: u8, mode: Mode) -> Result<Self, StationError> {
+        let gpio: Gpio = Gpio::new()?;
+        Ok(UltrasonicGpio{ pin: gpio.get(pin_number)?.into_io(mode) })
+    }
+
+    fn trigger(pin_number: u8) -> Result<Self, StationError> {
+        UltrasonicGpio::new(pin_number, Mode::Output)
+    }
+
+    fn echo(pin_number: u8) -> Result<Self, StationError> {
+        UltrasonicGpio::new(pin_number, Mode::Input)
+    }
+}
+
+impl UltrasonicPin for UltrasonicGpio {
+    fn is_low(&mut self) -> bool {
+        self.pin.is_low()
+    }
+
+    fn is_high(&mut self) -> bool {
+        self.pin.is_high()
+    }
+
+    fn set_low(&mut self) {
+        self.pin.set_low();
+    }
+
+    fn set_high(&mut self) {
+        self.pin.set_high();
+    }
+}
+
+/// Claims the pin only for the duration of a readout, rppal delivers
+/// interrupts to input pins only.
+struct GpioEdgeCapture {
+    gpio: Gpio,
+    pin_number: u8,
+}
+
+impl GpioEdgeCapture {
+    fn new(pin_number: u8) -> Result<Self, StationError> {
+        let gpio = Gpio::new()?;
+        // Fails early when the pin is invalid or in use.
+        gpio.get(pin_number)?;
+        Ok(GpioEdgeCapture{ gpio, pin_number })
+    }
+}
+
+impl Dht11EdgeCapture for GpioEdgeCapture {
+    fn capture(&mut self, starting_time_us: u32, timeout_us: u32, edges: &mut [Dht11Edge]) -> usize {
+        let Ok(pin) = self.gpio.get(self.pin_number) else { return 0 };
+        let mut output = pin.into_output_low();
+        thread::sleep(Duration::from_micros(starting_time_us.into()));
+        output.set_high();
+        drop(output);
+
+        // The kernel timestamps every edge and queues it until polled, so a
+        // late reader only delays decoding.
+        let Ok(pin) = self.gpio.get(self.pin_number) else { return 0 };
+        let mut input = pin.into_input();
+        if input.set_interrupt(Trigger::Both, None).is_err() {
+            return 0;
+        }
+        let deadline = Instant::now() + Duration::from_micros(timeout_us.into());
+        let mut count = 0;
+        while count < edges.len() {
+            // Once the frame started, a quiet line means it ended.
+            let mut remaining = deadline.saturating_duration_since(Instant::now());
+            if count > 0 {
+                remaining = remaining.min(EDGE_CAPTURE_IDLE);
+            }
+            match input.poll_interrupt(false, Some(remaining)) {
+                Ok(Some(event)) => {
+                    edges[count] = Dht11Edge{ rising: event.trigger == Trigger::RisingEdge, time_us: event.timestamp.as_micros() };
+                    count += 1;
+                }
+                Ok(None) | Err(_) => break,
+            }
+        }
+        count
+    }
+}
+
+struct I2cBme280 {
+    i2c: I2c
+}
+
+impl I2cBme280 {
+    fn new(address: u8) -> Result<Self, StationError> {
+        let mut i2c = I2c::new()?;
+        i2c.set_slave_address(address.into())?;
+        Ok(I2cBme280{ i2c })
+    }
+}
+
+impl Bme280Bus for I2cBme280 {
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), Bme280Error> {
+        self.i2c.smbus_write_byte(register, value).map_err(|_| Bme280Error::Bus)
+    }
+
+    fn read_registers(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), Bme280Error> {
+        self.i2c.write_read(&[register], buffer).map_err(|_| Bme280Error::Bus)
+    }
+}
+
+/// A channel of an ADC driven by the kernel's industrial I/O subsystem, e.g. an
+/// ADS1115 enabled with `dtoverlay=ads1115`.
+struct IioAdc {
+    path: String,
+}
+
+impl IioAdc {
+    fn new(device: u8, channel: u8) -> Self {
+        IioAdc { path: format!("/sys/bus/iio/devices/iio:device{}/in_voltage{}_raw", device, channel) }
+    }
+
+    /// Differential channels read slightly negative at zero, e.g. a pyranometer
+    /// in darkness, that is 0.
+    fn read(&self) -> Option<u16> {
+        let raw: i32 = std::fs::read_to_string(&self.path).ok()?.trim().parse().ok()?;
+        Some(raw.clamp(0, u16::MAX.into()) as u16)
+    }
+}
+
+impl PyranometerAdc for IioAdc {
+    fn read_raw(&mut self) -> Result<u16, PyranometerError> {
+        self.read().ok_or(PyranometerError::Adc)
+    }
+}
+
+impl LeafWetnessAdc for IioAdc {
+    fn read_raw(&mut self) -> Result<u16, LeafWetnessError> {
+        self.read().ok_or(LeafWetnessError::Adc)
+    }
+}
+
+/// NMEA sentences are at most 82 characters, longer lines are noise.
+const NMEA_MAX_LENGTH: usize = 82;
+/// A module sends every second, a read that waits longer means it is silent.
+const GPS_READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A GPS module on a serial port, 8N1.
+struct UartGps {
+    uart: Uart,
+}
+
+impl UartGps {
+    fn new(device: &str, baud_rate: u32) -> Result<Self, StationError> {
+        let mut uart = Uart::with_path(device, baud_rate, Parity::None, 8, 1)?;
+        uart.set_read_mode(0, GPS_READ_TIMEOUT)?;
+        Ok(UartGps { uart })
+    }
+}
+
+impl GpsSerial for UartGps {
+    fn read_sentence(&mut self) -> Option<String> {
+        let mut line = Vec::new();
+        let mut byte = [0u8];
+        loop {
+            match self.uart.read(&mut byte) {
+                Ok(1) if byte[0] == b'\n' => return Some(String::from_utf8_lossy(&line).trim_end().to_string()),
+                Ok(1) if line.len() < NMEA_MAX_LENGTH => line.push(byte[0]),
+                Ok(1) => line.clear(),
+                _ => return None,
+            }
+        }
+    }
+}
+
+/// Monotonic, so wall clock adjustments (NTP) can't corrupt pulse measurements.
+struct Timing {
+    start: Instant
+}
 
 impl Timing {
     fn new() -> Self {
-        Timing{}
+        Timing{ start: Instant::now() }
     }
 }
 
@@ -54,18 +290,85 @@ impl Dht11Timing for Timing {
     }
 
     fn get_time_us(&self) -> u128 {
-        let now = SystemTime::now();
-        let duration_since_epoch = now.duration_since(UNIX_EPOCH).expect("Time went backwards");
-        return duration_since_epoch.as_micros();
+        self.start.elapsed().as_micros()
     }
 } 
 
-fn main() {
-    println!("Weather station started!");
-    let mut pin = IoPinDht::new(23);
-    let data = dht11_perform_readout(&mut pin, &Timing::new()).unwrap();
+impl Bme280Timing for Timing {
+    fn wait(&self, microseconds: u32) {
+        thread::sleep(Duration::from_micros(microseconds.into()));
+    }
+}
+
+impl UltrasonicTiming for Timing {
+    fn wait(&self, microseconds: u32) {
+        thread::sleep(Duration::from_micros(microseconds.into()));
+    }
 
-    println!("Weather station readout:");
-    println!("Humidity: {}%", data.humidity);
-    println!("Temperature: {}*C", data.temperature);
+    fn get_time_us(&self) -> u128 {
+        self.start.elapsed().as_micros()
+    }
+}
+
+fn dispatch(cli: Cli) -> Result<(), StationError> {
+    let mut config = StationConfig::load(cli.config.as_deref())?;
+    match cli.command {
+        Some(Command::Read(args)) => {
+            if args.format == OutputFormat::Text {
+                preflight::log_preflight_problems(&preflight::run_preflight());
+            }
+            if let Some(pin) = args.pin {
+                config.use_pin(pin);
+            }
+            acquisition::read_once(&config, args.format)
+        }
+        Some(Command::Export(args)) => export::export(&config, &args),
+        Some(Command::Selftest(args)) => {
+            if let Some(pin) = args.pin {
+                config.use_pin(pin);
+            }
+            if !selftest::selftest(&config, args.format) {
+                process::exit(1);
+            }
+            Ok(())
+        }
+        Some(Command::Verify(args)) => {
+            if !signing::verify(&config, &args)? {
+                process::exit(1);
+            }
+            Ok(())
+        }
+        Some(Command::Features(args)) => {
+            capabilities::CapabilityReport::new(&config).print(&config, args.format);
+            Ok(())
+        }
+        Some(Command::EmulateDht11(args)) => emulate::emulate_dht11(&args),
+        Some(Command::LoopbackTest(args)) => {
+            loopback::loopback_test(&args);
+            Ok(())
+        }
+        Some(Command::RawReadout(args)) => raw_readout::raw_readout(&args),
+        Some(Command::InstallService(args)) => install_service::install_service(&args),
+        Some(Command::Run(args)) => run_station(config, *args),
+        None => run_station(config, cli.run),
+    }
+}
+
+fn run_station(mut config: StationConfig, args: RunArgs) -> Result<(), StationError> {
+    let problems = preflight::run_preflight();
+    preflight::log_preflight_problems(&problems);
+    if problems.iter().any(|problem| problem.fatal) {
+        process::exit(1);
+    }
+    args.apply(&mut config)?;
+    acquisition::run(config)
+}
+
+fn main() {
+    let cli = Cli::parse();
+    logging::init(cli.log_format);
+    if let Err(error) = dispatch(cli) {
+        error!(%error, "weather station failed");
+        process::exit(1);
+    }
 }
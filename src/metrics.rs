@@ -0,0 +1,134 @@
+use std::fmt::Write;
+use std::sync::Mutex;
+use sensor::{Measurement, Quantity, SensorCounter};
+use crate::config::SensorConfig;
+use crate::sensors;
+
+struct SensorMetrics {
+    /// `sensor="..."` and, when set, `location="..."` labels.
+    labels: String,
+    driver: &'static str,
+    last_readout: Option<Measurement>,
+    counters: Vec<SensorCounter>,
+}
+
+/// Latest values and driver counters per sensor, shared between the sampling
+/// loop and the HTTP API which renders them in the Prometheus text format.
+#[derive(Default)]
+pub struct StationMetrics {
+    /// In configuration order, keyed by sensor name.
+    sensors: Mutex<Vec<(String, SensorMetrics)>>,
+}
+
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn labels(sensor: &SensorConfig) -> String {
+    match &sensor.location {
+        Some(location) => format!("sensor=\"{}\",location=\"{}\"", escape_label_value(&sensor.name), escape_label_value(location)),
+        None => format!("sensor=\"{}\"", escape_label_value(&sensor.name)),
+    }
+}
+
+/// Writes one metric family with a sample per sensor that has a value for it.
+fn write_family<'a>(output: &mut String, name: &str, kind: &str, help: &str, samples: impl Iterator<Item = (&'a str, f64)>) {
+    let mut samples = samples.peekable();
+    if samples.peek().is_none() {
+        return;
+    }
+    let _ = writeln!(output, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+    for (labels, value) in samples {
+        let _ = writeln!(output, "{}{{{}}} {}", name, labels, value);
+    }
+}
+
+impl StationMetrics {
+    pub fn update(&self, sensor: &SensorConfig, driver: &'static str, readout: Option<&Measurement>, counters: &[SensorCounter]) {
+        let mut sensors = self.sensors.lock().unwrap();
+        let index = match sensors.iter().position(|(name, _)| *name == sensor.name) {
+            Some(index) => index,
+            None => {
+                sensors.push((sensor.name.clone(), SensorMetrics { labels: labels(sensor), driver, last_readout: None, counters: Vec::new() }));
+                sensors.len() - 1
+            }
+        };
+        let state = &mut sensors[index].1;
+        if let Some(data) = readout {
+            state.last_readout = Some(data.clone());
+        }
+        state.counters = counters.to_vec();
+    }
+
+    pub fn render(&self) -> String {
+        let sensors = self.sensors.lock().unwrap();
+        let mut output = String::new();
+        let values = |quantity| sensors.iter().filter_map(move |(_, state)| Some((state.labels.as_str(), state.last_readout.as_ref()?.get(quantity)?)));
+        write_family(&mut output, "weather_temperature_celsius", "gauge", "Last measured air temperature.", values(Quantity::Temperature));
+        // Humidity and what derives from it, temperature probes have neither.
+        let readouts = || {
+            sensors.iter().filter_map(|(_, state)| {
+                let data = state.last_readout.as_ref()?;
+                Some((state.labels.as_str(), data.get(Quantity::RelativeHumidity)?, sensors::meteo_derived(data)?))
+            })
+        };
+        write_family(&mut output, "weather_humidity_percent", "gauge", "Last measured relative humidity.", readouts().map(|(labels, humidity, _)| (labels, humidity)));
+        write_family(&mut output, "weather_dew_point_celsius", "gauge", "Dew point of the last readout.", readouts().map(|(labels, .., derived)| (labels, derived.dew_point)));
+        write_family(&mut output, "weather_heat_index_celsius", "gauge", "Heat index of the last readout.", readouts().map(|(labels, .., derived)| (labels, derived.heat_index)));
+        write_family(
+            &mut output,
+            "weather_absolute_humidity_grams_per_cubic_meter",
+            "gauge",
+            "Absolute humidity of the last readout.",
+            readouts().map(|(labels, .., derived)| (labels, derived.absolute_humidity)),
+        );
+        write_family(&mut output, "weather_pressure_hectopascals", "gauge", "Last measured station pressure.", values(Quantity::Pressure));
+        write_family(&mut output, "weather_irradiance_watts_per_square_meter", "gauge", "Last measured global solar irradiance.", values(Quantity::Irradiance));
+        write_family(&mut output, "weather_snow_depth_centimeters", "gauge", "Last measured snow depth.", values(Quantity::SnowDepth));
+        write_family(&mut output, "weather_leaf_wetness_percent", "gauge", "Last measured leaf wetness.", values(Quantity::LeafWetness));
+
+        // One family per driver counter, e.g. `weather_dht11_timeouts_total`.
+        let mut families: Vec<(&str, &SensorCounter)> = Vec::new();
+        for (_, state) in sensors.iter() {
+            for counter in &state.counters {
+                if !families.iter().any(|(driver, known)| *driver == state.driver && known.name == counter.name) {
+                    families.push((state.driver, counter));
+                }
+            }
+        }
+        for (driver, family) in families {
+            let samples = sensors.iter().filter(|(_, state)| state.driver == driver).filter_map(|(_, state)| {
+                let counter = state.counters.iter().find(|counter| counter.name == family.name)?;
+                Some((state.labels.as_str(), counter.value as f64))
+            });
+            write_family(&mut output, &format!("weather_{}_{}_total", driver, family.name), "counter", family.help, samples);
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::SensorKind;
+    use super::*;
+
+    #[test]
+    fn renders_prometheus_text() {
+        let metrics = StationMetrics::default();
+        assert!(!metrics.render().contains("weather_temperature_celsius"));
+
+        let indoor = SensorConfig { name: "indoor".to_string(), kind: SensorKind::Dht11 { pin: 23 }, location: Some("living room".to_string()) };
+        let outdoor = SensorConfig { name: "outdoor".to_string(), kind: SensorKind::Dht11 { pin: 24 }, location: None };
+        let attic = SensorConfig { name: "attic".to_string(), kind: SensorKind::Bme280 { address: 0x76 }, location: None };
+        let checksum_errors = |value| vec![SensorCounter { name: "checksum_errors", help: "Readout attempts with a checksum mismatch.", value }];
+        metrics.update(&indoor, "dht11", Some(&Measurement::new().with(Quantity::Temperature, 21.5).with(Quantity::RelativeHumidity, 40.0)), &checksum_errors(2));
+        metrics.update(&outdoor, "dht11", None, &checksum_errors(0));
+        let attic_readout = Measurement::new().with(Quantity::Temperature, 18.0).with(Quantity::RelativeHumidity, 55.0).with(Quantity::Pressure, 1002.5);
+        metrics.update(&attic, "bme280", Some(&attic_readout), &[]);
+        let output = metrics.render();
+        assert!(output.contains("# TYPE weather_temperature_celsius gauge\nweather_temperature_celsius{sensor=\"indoor\",location=\"living room\"} 21.5\nweather_temperature_celsius{sensor=\"attic\"} 18\n# HELP"));
+        assert!(output.contains("weather_humidity_percent{sensor=\"indoor\",location=\"living room\"} 40\n"));
+        assert!(output.contains("weather_dht11_checksum_errors_total{sensor=\"indoor\",location=\"living room\"} 2\nweather_dht11_checksum_errors_total{sensor=\"outdoor\"} 0\n"));
+        assert!(output.contains("# TYPE weather_pressure_hectopascals gauge\nweather_pressure_hectopascals{sensor=\"attic\"} 1002.5\n# HELP"));
+    }
+}
@@ -0,0 +1,590 @@
+use std::collections::HashMap;
+#[cfg(feature = "tls")]
+use std::fs;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use pyranometer::DailyRadiation;
+use rumqttc::{Client, Connection, Event, LastWill, MqttOptions, Outgoing, Packet, QoS, Transport};
+use sensor::{Measurement, Quantity};
+use serde::Serialize;
+use serde_json::json;
+use tracing::{error, info, warn};
+use crate::commands::{Command, CommandAuth, CommandVerifier};
+use crate::config::{SensorConfig, SensorKind};
+use crate::precision::{self, Precision};
+use crate::sensors;
+use crate::storage;
+use crate::units::TemperatureUnit;
+
+const DEFAULT_PORT: u16 = 1883;
+const DEFAULT_TLS_PORT: u16 = 8883;
+const DEFAULT_TOPIC_PREFIX: &str = "weather";
+const DEFAULT_DISCOVERY_PREFIX: &str = "homeassistant";
+const CLIENT_ID: &str = "weather_station";
+const AVAILABILITY_TOPIC: &str = "status";
+const ONLINE: &str = "online";
+const OFFLINE: &str = "offline";
+const COMMAND_TOPIC: &str = "command";
+const EVAPOTRANSPIRATION_TOPIC: &str = "et0";
+const SOLAR_RADIATION_TOPIC: &str = "solar_radiation";
+const SUNSHINE_TOPIC: &str = "sunshine_hours";
+const WET_HOURS_TOPIC: &str = "wet_hours";
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// How long queued messages may take to reach the broker when the station stops.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+/// Publishes queued while the broker is unreachable. Once it is full new
+/// publishes are dropped, and logged, until the broker is back.
+const REQUEST_QUEUE_CAPACITY: usize = 32;
+
+/// Topic tree a reading is published under, with the units its consumer expects.
+#[derive(Clone)]
+pub struct TopicTree {
+    pub prefix: String,
+    pub temperature_unit: TemperatureUnit,
+}
+
+impl TopicTree {
+    /// Parses `<prefix>=<unit>`, e.g. `legacy/weather=fahrenheit`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (prefix, unit) = value.rsplit_once('=')?;
+        Some(TopicTree { prefix: prefix.to_string(), temperature_unit: TemperatureUnit::parse(unit)? })
+    }
+}
+
+/// Report-by-exception: a value is only republished when it moved by more than
+/// its delta since the last publish, or when `max_silence` has passed.
+#[derive(Clone)]
+pub struct ReportByException {
+    /// Per metric name (`temperature`, `humidity`...), in the unit of the topic tree.
+    /// Metrics without a delta are republished on any change.
+    pub deltas: HashMap<String, f64>,
+    pub max_silence: Duration,
+}
+
+#[derive(Default)]
+struct ExceptionFilter {
+    /// Last published value and time per topic.
+    published: HashMap<String, (f64, Instant)>,
+}
+
+impl ExceptionFilter {
+    fn should_publish(&mut self, policy: &ReportByException, topic: &str, name: &str, value: f64, now: Instant) -> bool {
+        let delta = policy.deltas.get(name).copied().unwrap_or(0.0);
+        let publish = match self.published.get(topic) {
+            Some((last_value, last_time)) => (value - last_value).abs() > delta || now.duration_since(*last_time) >= policy.max_silence,
+            None => true,
+        };
+        if publish {
+            self.published.insert(topic.to_string(), (value, now));
+        }
+        publish
+    }
+}
+
+pub struct MqttConfig {
+    /// `host` or `host:port`, publishing is disabled when not set.
+    pub broker: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub qos: QoS,
+    pub retain: bool,
+    /// Requires a build with the `tls` feature.
+    pub tls: bool,
+    /// PEM file the broker certificate is verified against, the system
+    /// certificates when not set.
+    pub ca_file: Option<String>,
+    pub topic_prefix: String,
+    pub temperature_unit: TemperatureUnit,
+    /// Additional trees the readings are mirrored to, e.g. in Fahrenheit for a legacy consumer.
+    pub mirrors: Vec<TopicTree>,
+    /// Home Assistant discovery prefix, discovery is disabled when not set.
+    pub discovery_prefix: Option<String>,
+    /// Every readout is published when not set.
+    pub report_by_exception: Option<ReportByException>,
+    /// Commands on `<topic_prefix>/command` are ignored when not set.
+    pub commands: Option<CommandAuth>,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        MqttConfig {
+            broker: None,
+            username: None,
+            password: None,
+            qos: QoS::AtLeastOnce,
+            retain: false,
+            tls: false,
+            ca_file: None,
+            topic_prefix: DEFAULT_TOPIC_PREFIX.to_string(),
+            temperature_unit: TemperatureUnit::Celsius,
+            mirrors: Vec::new(),
+            discovery_prefix: None,
+            report_by_exception: None,
+            commands: None,
+        }
+    }
+}
+
+impl MqttConfig {
+    pub fn enable_discovery(&mut self) {
+        self.discovery_prefix = Some(DEFAULT_DISCOVERY_PREFIX.to_string());
+    }
+}
+
+pub fn parse_qos(value: &str) -> Option<QoS> {
+    match value {
+        "0" => Some(QoS::AtMostOnce),
+        "1" => Some(QoS::AtLeastOnce),
+        "2" => Some(QoS::ExactlyOnce),
+        _ => None,
+    }
+}
+
+fn split_broker(broker: &str, default_port: u16) -> (String, u16) {
+    match broker.rsplit_once(':').and_then(|(host, port)| Some((host, port.parse().ok()?))) {
+        Some((host, port)) => (host.to_string(), port),
+        None => (broker.to_string(), default_port),
+    }
+}
+
+#[cfg(feature = "tls")]
+fn tls_transport(config: &MqttConfig) -> Result<Transport, String> {
+    match &config.ca_file {
+        Some(path) => {
+            let ca = fs::read(path).map_err(|error| format!("cannot read {}: {}", path, error))?;
+            Ok(Transport::tls(ca, None, None))
+        }
+        None => Ok(Transport::tls_with_default_config()),
+    }
+}
+
+#[cfg(not(feature = "tls"))]
+fn tls_transport(_config: &MqttConfig) -> Result<Transport, String> {
+    Err("the station was built without the tls feature".to_string())
+}
+
+#[derive(Serialize)]
+struct StatePayload<'a> {
+    sensor: &'a str,
+    /// `null` when not finite, JSON has no NaN or infinity.
+    value: Option<f64>,
+    unit: &'a str,
+    timestamp: i64,
+}
+
+fn payload(sensor: &str, value: f64, unit: &str, timestamp: i64) -> String {
+    let value = value.is_finite().then_some(value);
+    serde_json::to_string(&StatePayload { sensor, value, unit, timestamp }).unwrap_or_default()
+}
+
+/// (name, value, unit) of every value published for a readout, converted to
+/// `temperature_unit` and rounded like everywhere values leave the station.
+fn state_values(data: &Measurement, temperature_unit: TemperatureUnit) -> Vec<(&'static str, f64, &'static str)> {
+    let temperature = |value| precision::TEMPERATURE_PRECISION.round(temperature_unit.convert(value));
+    let mut values = Vec::new();
+    for (quantity, value) in data.iter() {
+        match quantity {
+            Quantity::Temperature => values.push((quantity.name(), temperature(value), temperature_unit.symbol())),
+            Quantity::RelativeHumidity | Quantity::Pressure | Quantity::Irradiance | Quantity::SnowDepth | Quantity::LeafWetness => {
+                values.push((quantity.name(), Precision::of(quantity).round(value), quantity.unit()))
+            }
+        }
+    }
+    if let Some(derived) = sensors::meteo_derived(data) {
+        values.push(("dew_point", temperature(derived.dew_point), temperature_unit.symbol()));
+        values.push(("heat_index", temperature(derived.heat_index), temperature_unit.symbol()));
+        values.push(("absolute_humidity", precision::ABSOLUTE_HUMIDITY_PRECISION.round(derived.absolute_humidity), "g/m³"));
+    }
+    values
+}
+
+/// `<prefix>/<name>` for a single sensor, `<prefix>/<sensor>/<name>` when the
+/// station has several so their values don't overwrite each other.
+fn state_topic(prefix: &str, sensor: Option<&str>, name: &str) -> String {
+    match sensor {
+        Some(sensor) => format!("{}/{}/{}", prefix, sensor, name),
+        None => format!("{}/{}", prefix, name),
+    }
+}
+
+/// Home Assistant discovery config messages as (topic, payload) pairs. Several
+/// sensors are announced as one device each, placed in their location's area.
+fn discovery_messages(discovery_prefix: &str, tree: &TopicTree, sensors: &[SensorConfig]) -> Vec<(String, String)> {
+    // Sensors announced to Home Assistant: (name, device_class, unit).
+    let temperature_unit = tree.temperature_unit.symbol();
+    let metrics = [
+        ("temperature", "temperature", temperature_unit),
+        ("humidity", "humidity", "%"),
+        ("dew_point", "temperature", temperature_unit),
+        ("heat_index", "temperature", temperature_unit),
+        ("absolute_humidity", "absolute_humidity", "g/m³"),
+    ];
+    let pressure = ("pressure", "atmospheric_pressure", "hPa");
+    let irradiance = ("irradiance", "irradiance", "W/m²");
+    let snow_depth = ("snow_depth", "distance", "cm");
+    let leaf_wetness = ("leaf_wetness", "moisture", "%");
+    let per_sensor = sensors.len() > 1;
+    let mut messages = Vec::new();
+    for sensor in sensors {
+        let mut sensor_metrics = metrics.to_vec();
+        let model = match sensor.kind {
+            SensorKind::Dht11 { .. } => "DHT11",
+            SensorKind::Bme280 { .. } => {
+                sensor_metrics.push(pressure);
+                "BME280"
+            }
+            SensorKind::Ds18b20 { .. } => {
+                sensor_metrics.truncate(1);
+                "DS18B20"
+            }
+            SensorKind::Pyranometer { .. } => {
+                sensor_metrics.clear();
+                sensor_metrics.push(irradiance);
+                "Pyranometer"
+            }
+            SensorKind::Ultrasonic { .. } => {
+                sensor_metrics.clear();
+                sensor_metrics.push(snow_depth);
+                "Ultrasonic snow depth"
+            }
+            SensorKind::LeafWetness { .. } => {
+                sensor_metrics.clear();
+                sensor_metrics.push(leaf_wetness);
+                "Leaf wetness"
+            }
+        };
+        let topic_sensor = per_sensor.then_some(sensor.name.as_str());
+        let (device_id, device_name) = match topic_sensor {
+            Some(name) => (format!("{}_{}", CLIENT_ID, name), format!("Weather station {}", name)),
+            None => (CLIENT_ID.to_string(), "Weather station".to_string()),
+        };
+        let mut device = json!({ "identifiers": [device_id], "name": device_name, "model": model });
+        if let Some(location) = &sensor.location {
+            device["suggested_area"] = location.as_str().into();
+        }
+        for (name, device_class, unit) in sensor_metrics {
+            let unique_id = format!("{}_{}", device_id, name);
+            let topic = format!("{}/sensor/{}/config", discovery_prefix, unique_id);
+            let payload = json!({
+                "name": name,
+                "unique_id": unique_id,
+                "state_topic": state_topic(&tree.prefix, topic_sensor, name),
+                "value_template": "{{ value_json.value }}",
+                "unit_of_measurement": unit,
+                "device_class": device_class,
+                "state_class": "measurement",
+                "availability_topic": format!("{}/{}", tree.prefix, AVAILABILITY_TOPIC),
+                "device": device,
+            });
+            messages.push((topic, payload.to_string()));
+        }
+    }
+    messages
+}
+
+/// Verified commands go to the sampling loop, which carries them out between readouts.
+struct CommandChannel {
+    topic: String,
+    verifier: CommandVerifier,
+    sender: Sender<Command>,
+}
+
+impl CommandChannel {
+    fn receive(&mut self, payload: &[u8]) {
+        match self.verifier.verify(&self.topic, payload, storage::unix_time_now()) {
+            Ok(command) => {
+                info!(?command, "command received");
+                let _ = self.sender.send(command);
+            }
+            Err(rejection) => warn!(%rejection, "command rejected"),
+        }
+    }
+}
+
+/// Drives the connection, rumqttc reconnects on the next poll after an error.
+/// Discovery configs are (re)sent and the command topic subscribed on every
+/// connection so a restarted Home Assistant or broker picks the sensors up again.
+fn run_event_loop(mut connection: Connection, client: Client, discovery: Vec<(String, String)>, mut commands: Option<CommandChannel>) {
+    let mut connected = true;
+    for event in connection.iter() {
+        if let Ok(Event::Incoming(Packet::ConnAck(_))) = event {
+            for (topic, payload) in &discovery {
+                if let Err(error) = client.try_publish(topic, QoS::AtLeastOnce, true, payload.clone()) {
+                    warn!(%topic, %error, "publishing discovery config failed");
+                }
+            }
+            if let Some(commands) = &commands {
+                if let Err(error) = client.try_subscribe(&commands.topic, QoS::AtLeastOnce) {
+                    warn!(topic = %commands.topic, %error, "subscribing to commands failed");
+                }
+            }
+        }
+        if let (Ok(Event::Incoming(Packet::Publish(publish))), Some(commands)) = (&event, &mut commands) {
+            if publish.topic == commands.topic {
+                commands.receive(&publish.payload);
+            }
+        }
+        match event {
+            // Everything queued before the disconnect was sent.
+            Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+            Ok(_) if !connected => {
+                info!("MQTT broker reconnected");
+                connected = true;
+            }
+            Ok(_) => {}
+            Err(error) => {
+                if connected {
+                    warn!(%error, "MQTT connection lost");
+                    connected = false;
+                }
+                thread::sleep(RECONNECT_DELAY);
+            }
+        }
+    }
+}
+
+pub struct MqttPublisher {
+    client: Client,
+    qos: QoS,
+    retain: bool,
+    /// The primary tree first, it also carries availability and discovery.
+    trees: Vec<TopicTree>,
+    report_by_exception: Option<ReportByException>,
+    exception_filter: ExceptionFilter,
+    /// Whether topics carry the sensor name, see [`state_topic`].
+    per_sensor_topics: bool,
+    event_loop: JoinHandle<()>,
+    /// `None` when commands are disabled.
+    commands: Option<Receiver<Command>>,
+}
+
+impl MqttPublisher {
+    /// Returns `None` when no broker is configured.
+    pub fn connect(config: &MqttConfig, sensors: &[SensorConfig]) -> Option<Self> {
+        let default_port = if config.tls { DEFAULT_TLS_PORT } else { DEFAULT_PORT };
+        let (host, port) = split_broker(config.broker.as_deref()?, default_port);
+        let mut options = MqttOptions::new(CLIENT_ID, host, port);
+        if config.tls {
+            match tls_transport(config) {
+                Ok(transport) => options.set_transport(transport),
+                Err(error) => {
+                    error!(%error, "MQTT over TLS unavailable, publishing disabled");
+                    return None;
+                }
+            };
+        }
+        options.set_keep_alive(KEEP_ALIVE);
+        let availability_topic = format!("{}/{}", config.topic_prefix, AVAILABILITY_TOPIC);
+        options.set_last_will(LastWill::new(availability_topic, OFFLINE, QoS::AtLeastOnce, true));
+        if let Some(username) = &config.username {
+            options.set_credentials(username, config.password.as_deref().unwrap_or_default());
+        }
+
+        let primary = TopicTree { prefix: config.topic_prefix.clone(), temperature_unit: config.temperature_unit };
+        let (client, connection) = Client::new(options, REQUEST_QUEUE_CAPACITY);
+        let discovery = match &config.discovery_prefix {
+            Some(discovery_prefix) => discovery_messages(discovery_prefix, &primary, sensors),
+            None => Vec::new(),
+        };
+        let (command_channel, commands) = match &config.commands {
+            Some(auth) => {
+                let (sender, receiver) = mpsc::channel();
+                let topic = format!("{}/{}", config.topic_prefix, COMMAND_TOPIC);
+                (Some(CommandChannel { topic, verifier: CommandVerifier::new(auth.clone()), sender }), Some(receiver))
+            }
+            None => (None, None),
+        };
+        let event_loop_client = client.clone();
+        let event_loop = thread::spawn(move || run_event_loop(connection, event_loop_client, discovery, command_channel));
+        Some(MqttPublisher {
+            client,
+            qos: config.qos,
+            retain: config.retain,
+            trees: [primary].into_iter().chain(config.mirrors.iter().cloned()).collect(),
+            report_by_exception: config.report_by_exception.clone(),
+            exception_filter: ExceptionFilter::default(),
+            per_sensor_topics: sensors.len() > 1,
+            event_loop,
+            commands,
+        })
+    }
+
+    /// Marks the station offline, the broker only sends the last will when the
+    /// connection drops, and disconnects once the queued messages went out.
+    /// Gives up after `SHUTDOWN_TIMEOUT` when the broker is unreachable.
+    pub fn shutdown(self) {
+        self.set_available(false);
+        if let Err(error) = self.client.try_disconnect() {
+            warn!(%error, "disconnecting from the MQTT broker failed");
+            return;
+        }
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        while !self.event_loop.is_finished() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(50));
+        }
+        if !self.event_loop.is_finished() {
+            warn!("MQTT broker unreachable, queued messages were dropped");
+        }
+    }
+
+    /// The event loop only ends after [`MqttPublisher::shutdown`], or when it
+    /// panicked, then nothing is published until the publisher reconnects.
+    pub fn is_running(&self) -> bool {
+        !self.event_loop.is_finished()
+    }
+
+    /// Verified commands received since the last call.
+    pub fn received_commands(&self) -> Vec<Command> {
+        self.commands.iter().flat_map(Receiver::try_iter).collect()
+    }
+
+    fn publish_value(&mut self, tree: usize, sensor: &str, name: &str, value: f64, unit: &str, timestamp: i64) {
+        let topic = state_topic(&self.trees[tree].prefix, self.per_sensor_topics.then_some(sensor), name);
+        if let Some(policy) = &self.report_by_exception {
+            if !self.exception_filter.should_publish(policy, &topic, name, value, Instant::now()) {
+                return;
+            }
+        }
+        // Never block the sampling loop on a dead broker.
+        if let Err(error) = self.client.try_publish(&topic, self.qos, self.retain, payload(sensor, value, unit, timestamp)) {
+            warn!(%topic, %error, "publishing failed");
+        }
+    }
+
+    /// Daily values are never held back by report-by-exception.
+    fn publish_daily(&self, sensor: &str, name: &str, value: f64, unit: &str, day_start: i64) {
+        for tree in &self.trees {
+            let topic = state_topic(&tree.prefix, self.per_sensor_topics.then_some(sensor), name);
+            if let Err(error) = self.client.try_publish(&topic, self.qos, self.retain, payload(sensor, value, unit, day_start)) {
+                warn!(%topic, %error, "publishing failed");
+            }
+        }
+    }
+
+    /// Reference evapotranspiration of the UTC day starting at `day_start`, in mm.
+    pub fn publish_evapotranspiration(&self, sensor: &str, et0: f64, day_start: i64) {
+        self.publish_daily(sensor, EVAPOTRANSPIRATION_TOPIC, precision::EVAPOTRANSPIRATION_PRECISION.round(et0), "mm", day_start);
+    }
+
+    /// Solar radiation total and sunshine duration of the UTC day starting at `day_start`.
+    pub fn publish_radiation(&self, sensor: &str, radiation: &DailyRadiation, day_start: i64) {
+        self.publish_daily(sensor, SOLAR_RADIATION_TOPIC, precision::RADIATION_PRECISION.round(radiation.radiation), "MJ/m²", day_start);
+        self.publish_daily(sensor, SUNSHINE_TOPIC, precision::SUNSHINE_PRECISION.round(radiation.sunshine_hours), "h", day_start);
+    }
+
+    /// Hours the leaves were wet during the UTC day starting at `day_start`.
+    pub fn publish_wet_hours(&self, sensor: &str, wet_hours: f64, day_start: i64) {
+        self.publish_daily(sensor, WET_HOURS_TOPIC, precision::WET_HOURS_PRECISION.round(wet_hours), "h", day_start);
+    }
+
+    /// Publishes the retained availability state so Home Assistant marks the
+    /// sensors unavailable while readouts fail. Sent every sample because the
+    /// broker replaces it with the last will whenever the connection drops.
+    /// The station is available while at least one of its sensors answers.
+    pub fn set_available(&self, available: bool) {
+        let topic = format!("{}/{}", self.trees[0].prefix, AVAILABILITY_TOPIC);
+        let payload = if available { ONLINE } else { OFFLINE };
+        if let Err(error) = self.client.try_publish(&topic, QoS::AtLeastOnce, true, payload) {
+            warn!(%topic, %error, "publishing failed");
+        }
+    }
+
+    pub fn publish(&mut self, sensor: &str, data: &Measurement, timestamp: i64) {
+        for tree in 0..self.trees.len() {
+            for (name, value, unit) in state_values(data, self.trees[tree].temperature_unit) {
+                self.publish_value(tree, sensor, name, value, unit, timestamp);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broker_address() {
+        assert_eq!(split_broker("broker.local", DEFAULT_PORT), ("broker.local".to_string(), DEFAULT_PORT));
+        assert_eq!(split_broker("broker.local", DEFAULT_TLS_PORT), ("broker.local".to_string(), 8883));
+        assert_eq!(split_broker("10.0.0.2:1884", DEFAULT_TLS_PORT), ("10.0.0.2".to_string(), 1884));
+    }
+
+    #[test]
+    fn json_payload() {
+        assert_eq!(payload("dht11", 21.5, "°C", 1700000000), "{\"sensor\":\"dht11\",\"value\":21.5,\"unit\":\"°C\",\"timestamp\":1700000000}");
+        assert_eq!(payload("dht11", f64::NAN, "°C", 1700000000), "{\"sensor\":\"dht11\",\"value\":null,\"unit\":\"°C\",\"timestamp\":1700000000}");
+        assert!(payload("a \"quoted\" name", f64::INFINITY, "°C", 0).contains("\"sensor\":\"a \\\"quoted\\\" name\",\"value\":null"));
+    }
+
+    #[test]
+    fn published_values_are_converted_and_rounded() {
+        let data = Measurement::new().with(Quantity::Temperature, 21.53).with(Quantity::RelativeHumidity, 40.04).with(Quantity::Pressure, 1013.26);
+        let values = state_values(&data, TemperatureUnit::Fahrenheit);
+        assert_eq!(&values[..3], [("temperature", 70.8, "°F"), ("humidity", 40.0, "%"), ("pressure", 1013.3, "hPa")]);
+        let dew_point = values.iter().find(|(name, _, _)| *name == "dew_point").unwrap().1;
+        assert_eq!(dew_point, precision::TEMPERATURE_PRECISION.round(dew_point));
+        assert_eq!(values.len(), 6);
+    }
+
+    #[test]
+    fn report_by_exception_suppresses_small_changes() {
+        let policy = ReportByException { deltas: HashMap::from([("temperature".to_string(), 0.5)]), max_silence: Duration::from_secs(600) };
+        let mut filter = ExceptionFilter::default();
+        let start = Instant::now();
+
+        assert!(filter.should_publish(&policy, "weather/temperature", "temperature", 20.0, start));
+        assert!(!filter.should_publish(&policy, "weather/temperature", "temperature", 20.4, start + Duration::from_secs(60)));
+        assert!(filter.should_publish(&policy, "weather/temperature", "temperature", 20.6, start + Duration::from_secs(120)));
+        assert!(filter.should_publish(&policy, "weather/temperature", "temperature", 20.6, start + Duration::from_secs(720)));
+
+        assert!(filter.should_publish(&policy, "weather/humidity", "humidity", 40.0, start));
+        assert!(!filter.should_publish(&policy, "weather/humidity", "humidity", 40.0, start + Duration::from_secs(60)));
+        assert!(filter.should_publish(&policy, "weather/humidity", "humidity", 41.0, start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn topic_tree_with_unit() {
+        let tree = TopicTree::parse("legacy/weather=fahrenheit").unwrap();
+        assert_eq!(tree.prefix, "legacy/weather");
+        assert_eq!(tree.temperature_unit, TemperatureUnit::Fahrenheit);
+        assert!(TopicTree::parse("legacy/weather").is_none());
+    }
+
+    #[test]
+    fn home_assistant_discovery() {
+        let tree = TopicTree { prefix: "weather".to_string(), temperature_unit: TemperatureUnit::Celsius };
+        let messages = discovery_messages("homeassistant", &tree, &[SensorConfig::single(23)]);
+        assert_eq!(messages.len(), 5);
+        let (topic, payload) = &messages[0];
+        assert_eq!(topic, "homeassistant/sensor/weather_station_temperature/config");
+        let config: serde_json::Value = serde_json::from_str(payload).unwrap();
+        assert_eq!(config["state_topic"], "weather/temperature");
+        assert_eq!(config["value_template"], "{{ value_json.value }}");
+        assert_eq!(config["availability_topic"], "weather/status");
+        assert_eq!(config["device"], json!({ "identifiers": ["weather_station"], "name": "Weather station", "model": "DHT11" }));
+    }
+
+    #[test]
+    fn several_sensors_get_their_own_topics_and_devices() {
+        let tree = TopicTree { prefix: "weather".to_string(), temperature_unit: TemperatureUnit::Celsius };
+        let sensors = [
+            SensorConfig { name: "indoor".to_string(), kind: SensorKind::Dht11 { pin: 23 }, location: Some("Living room".to_string()) },
+            SensorConfig { name: "outdoor".to_string(), kind: SensorKind::Bme280 { address: 0x76 }, location: Some("C:\\attic \"north\"\n".to_string()) },
+            SensorConfig { name: "soil".to_string(), kind: SensorKind::Ds18b20 { rom_id: "28-0316a2795aff".to_string(), resolution: None }, location: None },
+        ];
+        let messages = discovery_messages("homeassistant", &tree, &sensors);
+        assert_eq!(messages.len(), 12);
+        assert_eq!(messages[11].0, "homeassistant/sensor/weather_station_soil_temperature/config");
+        let config = |index: usize| serde_json::from_str::<serde_json::Value>(&messages[index].1).unwrap();
+        assert_eq!(messages[5].0, "homeassistant/sensor/weather_station_outdoor_temperature/config");
+        assert_eq!(config(5)["state_topic"], "weather/outdoor/temperature");
+        // Free-form locations are escaped, whatever they contain.
+        assert_eq!(config(5)["device"]["suggested_area"], "C:\\attic \"north\"\n");
+        assert_eq!(config(0)["device"], json!({ "identifiers": ["weather_station_indoor"], "name": "Weather station indoor", "model": "DHT11", "suggested_area": "Living room" }));
+        assert_eq!(messages[10].0, "homeassistant/sensor/weather_station_outdoor_pressure/config");
+        assert_eq!(config(10)["unit_of_measurement"], "hPa");
+        assert_eq!(config(10)["device"]["model"], "BME280");
+    }
+}
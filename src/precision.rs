@@ -0,0 +1,61 @@
+use sensor::Quantity;
+
+/// Number of decimals a metric is published with. Values are kept at full
+/// precision internally and only rounded when they leave the station.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Precision {
+    pub decimals: usize,
+}
+
+pub const HUMIDITY_PRECISION: Precision = Precision { decimals: 0 };
+pub const TEMPERATURE_PRECISION: Precision = Precision { decimals: 1 };
+pub const ABSOLUTE_HUMIDITY_PRECISION: Precision = Precision { decimals: 1 };
+pub const PRESSURE_PRECISION: Precision = Precision { decimals: 1 };
+pub const IRRADIANCE_PRECISION: Precision = Precision { decimals: 0 };
+/// Centimeters, about what echo timing resolves.
+pub const SNOW_DEPTH_PRECISION: Precision = Precision { decimals: 0 };
+pub const LEAF_WETNESS_PRECISION: Precision = Precision { decimals: 0 };
+/// Hours the leaves were wet.
+pub const WET_HOURS_PRECISION: Precision = Precision { decimals: 1 };
+/// Millimeters of reference evapotranspiration.
+pub const EVAPOTRANSPIRATION_PRECISION: Precision = Precision { decimals: 1 };
+/// MJ/m² of daily solar radiation.
+pub const RADIATION_PRECISION: Precision = Precision { decimals: 1 };
+/// Hours of sunshine.
+pub const SUNSHINE_PRECISION: Precision = Precision { decimals: 1 };
+pub const CPU_TEMPERATURE_PRECISION: Precision = Precision { decimals: 1 };
+
+impl Precision {
+    /// Precision values of a measured quantity are published with.
+    pub fn of(quantity: Quantity) -> Precision {
+        match quantity {
+            Quantity::Temperature => TEMPERATURE_PRECISION,
+            Quantity::RelativeHumidity => HUMIDITY_PRECISION,
+            Quantity::Pressure => PRESSURE_PRECISION,
+            Quantity::Irradiance => IRRADIANCE_PRECISION,
+            Quantity::SnowDepth => SNOW_DEPTH_PRECISION,
+            Quantity::LeafWetness => LEAF_WETNESS_PRECISION,
+        }
+    }
+
+    pub fn round(&self, value: f64) -> f64 {
+        let factor = 10f64.powi(self.decimals as i32);
+        (value * factor).round() / factor
+    }
+
+    pub fn format(&self, value: f64) -> String {
+        format!("{:.*}", self.decimals, self.round(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounding_to_configured_decimals() {
+        assert_eq!(HUMIDITY_PRECISION.format(47.00000000001), "47");
+        assert_eq!(TEMPERATURE_PRECISION.format(23.849999), "23.8");
+        assert_eq!(Precision { decimals: 2 }.round(-1.005001), -1.01);
+    }
+}
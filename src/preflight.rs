@@ -0,0 +1,137 @@
+use std::fs::{self, OpenOptions};
+use std::io::ErrorKind;
+use std::path::Path;
+use tracing::{error, warn};
+
+const GPIO_MEMORY_DEVICES: [&str; 2] = ["/dev/gpiomem", "/dev/gpiomem0"];
+const I2C_DEVICE: &str = "/dev/i2c-1";
+const SPI_DEVICE: &str = "/dev/spidev0.0";
+const CONFIG_TXT_PATHS: [&str; 2] = ["/boot/firmware/config.txt", "/boot/config.txt"];
+
+/// A setup problem detected before touching the hardware, with distro specific advice.
+pub struct PreflightProblem {
+    pub problem: String,
+    pub remediation: String,
+
+    /// Fatal problems make the station unable to read any sensor.
+    pub fatal: bool,
+}
+
+fn distro_id() -> String {
+    fs::read_to_string("/etc/os-release")
+        .ok()
+        .and_then(|content| {
+            content
+                .lines()
+                .find_map(|line| line.strip_prefix("ID=").map(|id| id.trim_matches('"').to_string()))
+        })
+        .unwrap_or_default()
+}
+
+fn config_txt_path() -> &'static str {
+    CONFIG_TXT_PATHS.iter().find(|path| Path::new(path).exists()).unwrap_or(&CONFIG_TXT_PATHS[0])
+}
+
+/// # Returns
+/// Group id of `name` in an `/etc/group` formatted file.
+fn group_id(etc_group: &str, name: &str) -> Option<u32> {
+    etc_group.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        if fields.next()? != name {
+            return None;
+        }
+        fields.nth(1)?.parse().ok()
+    })
+}
+
+/// # Returns
+/// Supplementary group ids from the `Groups:` line of `/proc/self/status`.
+fn process_groups(status: &str) -> Vec<u32> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Groups:"))
+        .map(|groups| groups.split_whitespace().filter_map(|gid| gid.parse().ok()).collect())
+        .unwrap_or_default()
+}
+
+pub fn is_in_group(name: &str) -> Option<bool> {
+    let gid = group_id(&fs::read_to_string("/etc/group").ok()?, name)?;
+    Some(process_groups(&fs::read_to_string("/proc/self/status").ok()?).contains(&gid))
+}
+
+fn gpio_permission_remediation() -> String {
+    match (distro_id().as_str(), is_in_group("gpio")) {
+        (_, Some(false)) => "add the user to the gpio group with `sudo usermod -aG gpio $USER` and log in again".to_string(),
+        ("ubuntu", None) | ("debian", None) => {
+            "create a gpio group (`sudo groupadd gpio`), add the user to it and add a udev rule giving it /dev/gpiomem: \
+             SUBSYSTEM==\"bcm2835-gpiomem\", GROUP=\"gpio\", MODE=\"0660\""
+                .to_string()
+        }
+        _ => "run the station as a user with read/write access to /dev/gpiomem".to_string(),
+    }
+}
+
+fn check_gpio(problems: &mut Vec<PreflightProblem>) {
+    let Some(device) = GPIO_MEMORY_DEVICES.iter().find(|device| Path::new(device).exists()) else {
+        problems.push(PreflightProblem {
+            problem: "/dev/gpiomem is absent".to_string(),
+            remediation: "make sure this is a Raspberry Pi running a kernel with the gpiomem driver (Raspberry Pi OS or a raspi kernel package)".to_string(),
+            fatal: true,
+        });
+        return;
+    };
+
+    if let Err(error) = OpenOptions::new().read(true).write(true).open(device) {
+        if error.kind() == ErrorKind::PermissionDenied {
+            problems.push(PreflightProblem {
+                problem: format!("permission denied opening {}", device),
+                remediation: gpio_permission_remediation(),
+                fatal: true,
+            });
+        }
+    }
+}
+
+fn check_interface(problems: &mut Vec<PreflightProblem>, device: &str, name: &str, dtparam: &str) {
+    if !Path::new(device).exists() {
+        problems.push(PreflightProblem {
+            problem: format!("{} interface is disabled ({} is absent)", name, device),
+            remediation: format!("add `dtparam={}=on` to {} (or use raspi-config) and reboot", dtparam, config_txt_path()),
+            fatal: false,
+        });
+    }
+}
+
+/// Detects the common reasons GPIO, I2C or SPI access fails on a fresh install.
+pub fn run_preflight() -> Vec<PreflightProblem> {
+    let mut problems = Vec::new();
+    check_gpio(&mut problems);
+    check_interface(&mut problems, I2C_DEVICE, "I2C", "i2c_arm");
+    check_interface(&mut problems, SPI_DEVICE, "SPI", "spi");
+    problems
+}
+
+pub fn log_preflight_problems(problems: &[PreflightProblem]) {
+    for problem in problems {
+        if problem.fatal {
+            error!(problem = %problem.problem, remediation = %problem.remediation, "preflight check failed");
+        } else {
+            warn!(problem = %problem.problem, remediation = %problem.remediation, "preflight check found a problem");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gpio_group_membership() {
+        let etc_group = "root:x:0:\ngpio:x:997:pi\ni2c:x:998:pi\n";
+        let status = "Name:\tweather_station\nGroups:\t4 20 997 \n";
+
+        assert_eq!(group_id(etc_group, "gpio"), Some(997));
+        assert_eq!(group_id(etc_group, "spi"), None);
+        assert!(process_groups(status).contains(&997));
+    }
+}
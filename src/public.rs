@@ -0,0 +1,79 @@
+use sensor::Quantity;
+use crate::precision::Precision;
+use crate::storage::Reading;
+
+/// How readings are reduced before they are shown to the public, local storage
+/// keeps them at full fidelity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PublicProfile {
+    /// Sensors never shown, e.g. indoor ones that reveal presence.
+    pub exclude_sensors: Vec<String>,
+    /// Quantities shown, the others are left out of every reading.
+    pub metrics: Vec<Quantity>,
+    /// Decimals of every published value.
+    pub precision: Precision,
+    /// Timestamps are rounded down to a multiple of this, so activity can't be
+    /// told from exact sample times.
+    ///
+    /// # Unit
+    /// Seconds.
+    pub time_resolution_s: i64,
+}
+
+impl Default for PublicProfile {
+    fn default() -> Self {
+        PublicProfile { exclude_sensors: Vec::new(), metrics: Quantity::ALL.to_vec(), precision: Precision { decimals: 0 }, time_resolution_s: 10 * 60 }
+    }
+}
+
+impl PublicProfile {
+    ///
+    /// # Returns
+    /// `reading` as the public may see it, `None` for excluded sensors.
+    pub fn apply(&self, reading: &Reading) -> Option<Reading> {
+        if self.exclude_sensors.contains(&reading.sensor_id) {
+            return None;
+        }
+        let round = |quantity, value: Option<f64>| value.filter(|_| self.metrics.contains(&quantity)).map(|value| self.precision.round(value));
+        Some(Reading {
+            timestamp: reading.timestamp - reading.timestamp.rem_euclid(self.time_resolution_s.max(1)),
+            sensor_id: reading.sensor_id.clone(),
+            temperature: round(Quantity::Temperature, reading.temperature),
+            humidity: round(Quantity::RelativeHumidity, reading.humidity),
+            status: reading.status.clone(),
+            pressure: round(Quantity::Pressure, reading.pressure),
+            irradiance: round(Quantity::Irradiance, reading.irradiance),
+            snow_depth: round(Quantity::SnowDepth, reading.snow_depth),
+            leaf_wetness: round(Quantity::LeafWetness, reading.leaf_wetness),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readings_are_coarsened_and_filtered() {
+        let profile = PublicProfile { exclude_sensors: vec!["indoor".to_string()], ..Default::default() };
+        let reading = |sensor_id: &str| Reading {
+            timestamp: 1_700_000_123,
+            sensor_id: sensor_id.to_string(),
+            temperature: Some(21.46),
+            humidity: None,
+            status: "ok".to_string(),
+            pressure: Some(1013.26),
+            irradiance: None,
+            snow_depth: None,
+            leaf_wetness: None,
+        };
+
+        assert!(profile.apply(&reading("indoor")).is_none());
+        let public = profile.apply(&reading("outdoor")).unwrap();
+        assert_eq!(public.timestamp, 1_699_999_800);
+        assert_eq!((public.temperature, public.humidity, public.pressure), (Some(21.0), None, Some(1013.0)));
+
+        let profile = PublicProfile { metrics: vec![Quantity::Temperature], ..Default::default() };
+        assert_eq!(profile.apply(&reading("outdoor")).unwrap().pressure, None);
+    }
+}
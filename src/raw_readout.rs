@@ -0,0 +1,67 @@
+use std::thread;
+use std::time::Duration;
+use dht11::{Dht11, Dht11Bus, Dht11Config, Dht11RawReadout, Dht11Readout, DHT11_MIN_SAMPLING_PERIOD_US};
+use crate::cli::{OutputFormat, RawReadoutArgs};
+use crate::error::StationError;
+use crate::{GpioEdgeCapture, IoPinDht, Timing};
+
+enum PulseSource {
+    Polling(IoPinDht),
+    Edges(GpioEdgeCapture),
+}
+
+fn bits_text(raw: &Dht11RawReadout) -> String {
+    raw.bits.chunks(8).map(|byte| byte.iter().map(|bit| if *bit { '1' } else { '0' }).collect::<String>()).collect::<Vec<_>>().join(" ")
+}
+
+fn raw_json(raw: &Dht11RawReadout) -> serde_json::Value {
+    let mut json = serde_json::json!({ "pulse_durations_us": raw.pulse_durations_us.as_slice(), "bits": bits_text(raw) });
+    match &raw.readout {
+        Ok(readout) => {
+            let readout = Dht11Readout::from(*readout);
+            json["status"] = "ok".into();
+            json["humidity"] = readout.humidity.into();
+            json["temperature"] = readout.temperature.into();
+        }
+        Err(error) => json["status"] = format!("{:?}", error).into(),
+    }
+    json
+}
+
+fn print_raw(attempt: u32, raw: &Dht11RawReadout) {
+    println!("Readout {}:", attempt);
+    println!("  pulses (us): {}", raw.pulse_durations_us.map(|duration| duration.to_string()).join(" "));
+    println!("  bits: {}", bits_text(raw));
+    match &raw.readout {
+        Ok(readout) => {
+            let readout = Dht11Readout::from(*readout);
+            println!("  result: {}% {}*C", readout.humidity, readout.temperature);
+        }
+        Err(error) => println!("  result: {:?}", error),
+    }
+}
+
+/// Logs the high pulse duration of every bit of `--count` readouts, 0/1 bits
+/// are told apart at `--bit-threshold-us`. Timeouts are reported without timings.
+pub fn raw_readout(args: &RawReadoutArgs) -> Result<(), StationError> {
+    let timing = Timing::new();
+    let mut dht = Dht11::new(Dht11Config { bit_threshold_us: args.bit_threshold_us, ..Dht11Config::default() });
+    let mut source = if args.edge_capture { PulseSource::Edges(GpioEdgeCapture::new(args.pin)?) } else { PulseSource::Polling(IoPinDht::new(args.pin)?) };
+
+    for attempt in 1..=args.count {
+        if attempt > 1 {
+            thread::sleep(Duration::from_micros(DHT11_MIN_SAMPLING_PERIOD_US.into()));
+        }
+        let result = match &mut source {
+            PulseSource::Polling(pin) => dht.read_raw(Dht11Bus::Polling(pin), &timing),
+            PulseSource::Edges(capture) => dht.read_raw(Dht11Bus::Edges(capture), &timing),
+        };
+        match (args.format, result) {
+            (OutputFormat::Text, Ok(raw)) => print_raw(attempt, &raw),
+            (OutputFormat::Json, Ok(raw)) => println!("{}", raw_json(&raw)),
+            (OutputFormat::Text, Err(error)) => println!("Readout {}: {:?}, no pulse timings", attempt, error),
+            (OutputFormat::Json, Err(error)) => println!("{}", serde_json::json!({ "status": format!("{:?}", error) })),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use sensor::Quantity;
+use serde::Serialize;
+use crate::cli::OutputFormat;
+use crate::config::{SensorKind, StationConfig};
+use crate::preflight;
+use crate::sensors::SensorChannel;
+
+#[derive(Serialize)]
+struct Check {
+    name: &'static str,
+    passed: bool,
+    detail: String,
+}
+
+#[derive(Serialize)]
+struct SelftestReport {
+    passed: bool,
+    checks: Vec<Check>,
+}
+
+fn run_checks(config: &StationConfig) -> Vec<Check> {
+    let problems = preflight::run_preflight();
+    let mut checks = vec![Check {
+        name: "preflight",
+        passed: !problems.iter().any(|problem| problem.fatal),
+        detail: problems.iter().map(|problem| problem.problem.as_str()).collect::<Vec<_>>().join("; "),
+    }];
+
+    for sensor in &config.sensors {
+        let bus = match sensor.kind {
+            SensorKind::Dht11 { .. } => "gpio",
+            SensorKind::Bme280 { .. } => "i2c",
+            SensorKind::Ds18b20 { .. } => "1-wire",
+            SensorKind::Pyranometer { .. } | SensorKind::LeafWetness { .. } => "adc",
+            SensorKind::Ultrasonic { .. } => "gpio",
+        };
+        let mut channel = match SensorChannel::open(sensor, config) {
+            Ok(channel) => {
+                checks.push(Check { name: bus, passed: true, detail: format!("{}: {:?} claimed", sensor.name, sensor.kind) });
+                channel
+            }
+            Err(error) => {
+                checks.push(Check { name: bus, passed: false, detail: format!("{}: {}", sensor.name, error) });
+                continue;
+            }
+        };
+
+        let (passed, detail) = match channel.read() {
+            Ok(data) => {
+                let values: Vec<String> = data.iter().map(|(quantity, value)| format!("{}={}{}", quantity.name(), value, quantity.unit())).collect();
+                (data.get(Quantity::Temperature).is_some(), format!("{}: {}", sensor.name, values.join(" ")))
+            }
+            Err(error) => (false, format!("{}: {}", sensor.name, error)),
+        };
+        checks.push(Check { name: "sensor", passed, detail });
+    }
+    checks
+}
+
+/// Verifies that GPIO is usable and every sensor answers.
+///
+/// # Returns
+/// Whether every check passed.
+pub fn selftest(config: &StationConfig, format: OutputFormat) -> bool {
+    let checks = run_checks(config);
+    let report = SelftestReport { passed: checks.iter().all(|check| check.passed), checks };
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string(&report).unwrap_or_default()),
+        OutputFormat::Text => {
+            for check in &report.checks {
+                println!("{}: {} {}", check.name, if check.passed { "ok" } else { "FAILED" }, check.detail);
+            }
+        }
+    }
+    report.passed
+}
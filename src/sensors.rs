@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use bme280::{bme280_perform_readout, bme280_read_calibration, Bme280Calibration, Bme280Error};
+use dht11::{Dht11, Dht11Bus, Dht11Error, Dht11Readout, Dht11Statistics};
+use ds18b20::{Ds18b20Error, Ds18b20Probe};
+use leaf_wetness::{leaf_wetness_perform_readout, LeafWetnessCalibration, LeafWetnessError};
+use pyranometer::{pyranometer_perform_readout, PyranometerCalibration, PyranometerError};
+use sensor::{Measurement, Quantity, Sensor, SensorCounter, SensorError, SensorErrorKind};
+use tracing::warn;
+use ultrasonic::{ultrasonic_perform_readout, SnowDepthGauge, UltrasonicError};
+use weather_math::MeteoDerived;
+use crate::config::{SensorConfig, SensorKind, StationConfig};
+use crate::error::StationError;
+use crate::{GpioEdgeCapture, I2cBme280, IioAdc, IoPinDht, Timing, UltrasonicGpio};
+
+/// Dew point, heat index and absolute humidity, for measurements with humidity.
+pub fn meteo_derived(measurement: &Measurement) -> Option<MeteoDerived> {
+    Some(MeteoDerived::compute(measurement.get(Quantity::Temperature)?, measurement.get(Quantity::RelativeHumidity)?))
+}
+
+fn dht11_measurement(result: Result<Dht11Readout, Dht11Error>) -> Result<Measurement, SensorError> {
+    match result {
+        Ok(data) => Ok(Measurement::new().with(Quantity::Temperature, data.temperature).with(Quantity::RelativeHumidity, data.humidity)),
+        Err(error @ Dht11Error::Timeout) => Err(SensorError::new(SensorErrorKind::Timeout, error)),
+        Err(error @ Dht11Error::ChecksumError) => Err(SensorError::new(SensorErrorKind::Checksum, error)),
+    }
+}
+
+fn dht11_counters(statistics: &Dht11Statistics) -> Vec<SensorCounter> {
+    vec![
+        SensorCounter { name: "readout_attempts", help: "Sensor readout attempts, retries included.", value: statistics.attempts },
+        SensorCounter { name: "checksum_errors", help: "Readout attempts with a checksum mismatch.", value: statistics.checksum_errors },
+        SensorCounter { name: "timeouts", help: "Readout attempts the sensor did not answer in time.", value: statistics.timeouts },
+    ]
+}
+
+/// Times the pulses by busy polling the pin.
+struct Dht11Sensor {
+    pin: IoPinDht,
+    timing: Timing,
+    dht: Dht11,
+}
+
+impl Sensor for Dht11Sensor {
+    fn driver(&self) -> &'static str {
+        "dht11"
+    }
+
+    fn read(&mut self) -> Result<Measurement, SensorError> {
+        dht11_measurement(self.dht.read(Dht11Bus::Polling(&mut self.pin), &self.timing))
+    }
+
+    fn counters(&self) -> Vec<SensorCounter> {
+        dht11_counters(&self.dht.statistics())
+    }
+}
+
+/// Times the pulses with kernel timestamped GPIO interrupts, so scheduler
+/// preemption no longer corrupts readouts.
+struct Dht11EdgeSensor {
+    capture: GpioEdgeCapture,
+    timing: Timing,
+    dht: Dht11,
+}
+
+impl Sensor for Dht11EdgeSensor {
+    fn driver(&self) -> &'static str {
+        "dht11"
+    }
+
+    fn read(&mut self) -> Result<Measurement, SensorError> {
+        dht11_measurement(self.dht.read(Dht11Bus::Edges(&mut self.capture), &self.timing))
+    }
+
+    fn counters(&self) -> Vec<SensorCounter> {
+        dht11_counters(&self.dht.statistics())
+    }
+}
+
+/// The calibration is read on the first readout, and again after a failed
+/// one, so a sensor connected after startup is picked up.
+struct Bme280Sensor {
+    bus: I2cBme280,
+    timing: Timing,
+    calibration: Option<Bme280Calibration>,
+}
+
+impl Sensor for Bme280Sensor {
+    fn driver(&self) -> &'static str {
+        "bme280"
+    }
+
+    fn read(&mut self) -> Result<Measurement, SensorError> {
+        let result = match &self.calibration {
+            Some(calibration) => bme280_perform_readout(&mut self.bus, &self.timing, calibration),
+            None => bme280_read_calibration(&mut self.bus)
+                .and_then(|read| bme280_perform_readout(&mut self.bus, &self.timing, self.calibration.insert(read))),
+        };
+        if result.is_err() {
+            self.calibration = None;
+        }
+        match result {
+            Ok(data) => Ok(Measurement::new()
+                .with(Quantity::Temperature, data.temperature)
+                .with(Quantity::RelativeHumidity, data.humidity)
+                .with(Quantity::Pressure, data.pressure)),
+            Err(error @ Bme280Error::Bus) => Err(SensorError::new(SensorErrorKind::Bus, error)),
+            Err(error @ Bme280Error::UnexpectedChipId(_)) => Err(SensorError::new(SensorErrorKind::UnexpectedDevice, error)),
+            Err(error @ Bme280Error::Timeout) => Err(SensorError::new(SensorErrorKind::Timeout, error)),
+        }
+    }
+}
+
+struct Ds18b20Sensor(Ds18b20Probe);
+
+impl Sensor for Ds18b20Sensor {
+    fn driver(&self) -> &'static str {
+        "ds18b20"
+    }
+
+    fn read(&mut self) -> Result<Measurement, SensorError> {
+        match self.0.perform_readout() {
+            Ok(data) => Ok(Measurement::new().with(Quantity::Temperature, data.temperature)),
+            Err(error @ Ds18b20Error::Io(_)) => Err(SensorError::new(SensorErrorKind::Bus, error)),
+            Err(error @ Ds18b20Error::ChecksumError) => Err(SensorError::new(SensorErrorKind::Checksum, error)),
+            Err(error @ (Ds18b20Error::PowerOnReset | Ds18b20Error::Malformed)) => Err(SensorError::new(SensorErrorKind::InvalidData, error)),
+        }
+    }
+}
+
+struct PyranometerSensor {
+    adc: IioAdc,
+    calibration: PyranometerCalibration,
+}
+
+impl Sensor for PyranometerSensor {
+    fn driver(&self) -> &'static str {
+        "pyranometer"
+    }
+
+    fn read(&mut self) -> Result<Measurement, SensorError> {
+        match pyranometer_perform_readout(&mut self.adc, &self.calibration) {
+            Ok(data) => Ok(Measurement::new().with(Quantity::Irradiance, data.irradiance)),
+            Err(error @ PyranometerError::Adc) => Err(SensorError::new(SensorErrorKind::Bus, error)),
+        }
+    }
+}
+
+struct LeafWetnessSensor {
+    adc: IioAdc,
+    calibration: LeafWetnessCalibration,
+}
+
+impl Sensor for LeafWetnessSensor {
+    fn driver(&self) -> &'static str {
+        "leaf_wetness"
+    }
+
+    fn read(&mut self) -> Result<Measurement, SensorError> {
+        match leaf_wetness_perform_readout(&mut self.adc, &self.calibration) {
+            Ok(data) => Ok(Measurement::new().with(Quantity::LeafWetness, data.wetness)),
+            Err(error @ LeafWetnessError::Adc) => Err(SensorError::new(SensorErrorKind::Bus, error)),
+        }
+    }
+}
+
+/// Speed of sound compensation until the station measured the air temperature.
+const REFERENCE_AIR_TEMPERATURE: f64 = 20.0;
+const CENTIMETERS_PER_METER: f64 = 100.0;
+
+/// Snow depth from the median of several echoes.
+struct UltrasonicSensor {
+    trigger: UltrasonicGpio,
+    echo: UltrasonicGpio,
+    timing: Timing,
+    gauge: SnowDepthGauge,
+    air_temperature: f64,
+}
+
+impl Sensor for UltrasonicSensor {
+    fn driver(&self) -> &'static str {
+        "ultrasonic"
+    }
+
+    fn read(&mut self) -> Result<Measurement, SensorError> {
+        match ultrasonic_perform_readout(&mut self.trigger, &mut self.echo, &self.timing, self.air_temperature) {
+            Ok(data) => Ok(Measurement::new().with(Quantity::SnowDepth, self.gauge.snow_depth(&data) * CENTIMETERS_PER_METER)),
+            Err(error @ (UltrasonicError::Timeout | UltrasonicError::NoEcho)) => Err(SensorError::new(SensorErrorKind::Timeout, error)),
+        }
+    }
+
+    fn set_air_temperature(&mut self, temperature: f64) {
+        self.air_temperature = temperature;
+    }
+}
+
+/// A configured sensor with its claimed GPIO pins, I2C address, 1-Wire probe or
+/// ADC channel.
+pub struct SensorChannel {
+    pub config: SensorConfig,
+    sensor: Box<dyn Sensor>,
+}
+
+impl SensorChannel {
+    /// DHT11 readouts are retried according to the station's retry policy,
+    /// BME280, DS18B20, pyranometer and leaf wetness ones are reliable enough to be taken once,
+    /// ultrasonic ones filter several echoes themselves.
+    pub fn open(config: &SensorConfig, options: &StationConfig) -> Result<Self, StationError> {
+        let dht = Dht11::new(options.dht11).with_retries(options.retry_policy);
+        let sensor: Box<dyn Sensor> = match &config.kind {
+            SensorKind::Dht11 { pin } if options.edge_capture => Box::new(Dht11EdgeSensor {
+                capture: GpioEdgeCapture::new(*pin)?,
+                timing: Timing::new(),
+                dht,
+            }),
+            SensorKind::Dht11 { pin } => Box::new(Dht11Sensor {
+                pin: IoPinDht::new(*pin)?,
+                timing: Timing::new(),
+                dht,
+            }),
+            SensorKind::Bme280 { address } => Box::new(Bme280Sensor { bus: I2cBme280::new(*address)?, timing: Timing::new(), calibration: None }),
+            SensorKind::Ds18b20 { rom_id, resolution } => {
+                let probe = Ds18b20Probe::new(rom_id);
+                // Not fatal, the probe keeps converting at its current resolution.
+                if let Some(Err(error)) = resolution.map(|bits| probe.set_resolution(bits)) {
+                    warn!(sensor = %config.name, %rom_id, ?error, "setting the probe resolution failed, is the station running as root?");
+                }
+                Box::new(Ds18b20Sensor(probe))
+            }
+            SensorKind::Pyranometer { adc_device, adc_channel, calibration } => {
+                Box::new(PyranometerSensor { adc: IioAdc::new(*adc_device, *adc_channel), calibration: *calibration })
+            }
+            SensorKind::LeafWetness { adc_device, adc_channel, calibration } => {
+                Box::new(LeafWetnessSensor { adc: IioAdc::new(*adc_device, *adc_channel), calibration: *calibration })
+            }
+            SensorKind::Ultrasonic { trigger_pin, echo_pin, baseline_cm, .. } => Box::new(UltrasonicSensor {
+                trigger: UltrasonicGpio::trigger(*trigger_pin)?,
+                echo: UltrasonicGpio::echo(*echo_pin)?,
+                timing: Timing::new(),
+                gauge: SnowDepthGauge::new(baseline_cm / CENTIMETERS_PER_METER),
+                air_temperature: REFERENCE_AIR_TEMPERATURE,
+            }),
+        };
+        Ok(SensorChannel { config: config.clone(), sensor })
+    }
+
+    pub fn read(&mut self) -> Result<Measurement, SensorError> {
+        self.sensor.read()
+    }
+
+    pub fn driver(&self) -> &'static str {
+        self.sensor.driver()
+    }
+
+    pub fn counters(&self) -> Vec<SensorCounter> {
+        self.sensor.counters()
+    }
+}
+
+/// Last temperature every sensor measured, for the sensors compensating for it.
+#[derive(Default)]
+pub struct AirTemperatures(HashMap<String, f64>);
+
+impl AirTemperatures {
+    /// Reads `channel`, compensated with the last temperature of its
+    /// `temperature_sensor`, and keeps the temperature it measured.
+    pub fn read(&mut self, channel: &mut SensorChannel) -> Result<Measurement, SensorError> {
+        if let SensorKind::Ultrasonic { temperature_sensor: Some(name), .. } = &channel.config.kind {
+            if let Some(temperature) = self.0.get(name) {
+                channel.sensor.set_air_temperature(*temperature);
+            }
+        }
+        let result = channel.read();
+        if let Some(temperature) = result.as_ref().ok().and_then(|data| data.get(Quantity::Temperature)) {
+            self.0.insert(channel.config.name.clone(), temperature);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use super::*;
+
+    struct Thermometer(f64);
+
+    impl Sensor for Thermometer {
+        fn driver(&self) -> &'static str {
+            "thermometer"
+        }
+
+        fn read(&mut self) -> Result<Measurement, SensorError> {
+            Ok(Measurement::new().with(Quantity::Temperature, self.0))
+        }
+    }
+
+    struct Compensated(Rc<Cell<Option<f64>>>);
+
+    impl Sensor for Compensated {
+        fn driver(&self) -> &'static str {
+            "compensated"
+        }
+
+        fn read(&mut self) -> Result<Measurement, SensorError> {
+            Ok(Measurement::new().with(Quantity::SnowDepth, 0.0))
+        }
+
+        fn set_air_temperature(&mut self, temperature: f64) {
+            self.0.set(Some(temperature));
+        }
+    }
+
+    #[test]
+    fn compensated_sensors_get_the_last_air_temperature() {
+        let config = |name: &str, kind| SensorConfig { name: name.to_string(), kind, location: None };
+        let mut outdoor = SensorChannel { config: config("outdoor", SensorKind::Dht11 { pin: 23 }), sensor: Box::new(Thermometer(-5.0)) };
+        let received = Rc::new(Cell::new(None));
+        let kind = SensorKind::Ultrasonic { trigger_pin: 5, echo_pin: 6, baseline_cm: 150.0, temperature_sensor: Some("outdoor".to_string()) };
+        let mut snow = SensorChannel { config: config("snow", kind), sensor: Box::new(Compensated(Rc::clone(&received))) };
+
+        let mut temperatures = AirTemperatures::default();
+        temperatures.read(&mut snow).unwrap();
+        assert_eq!(received.get(), None);
+        temperatures.read(&mut outdoor).unwrap();
+        temperatures.read(&mut snow).unwrap();
+        assert_eq!(received.get(), Some(-5.0));
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::flag;
+use crate::error::StationError;
+
+/// How often sleeps check for a requested shutdown.
+const POLL_PERIOD: Duration = Duration::from_millis(100);
+
+/// Set once SIGINT or SIGTERM arrived. The station checks it between readouts,
+/// so a readout in flight always completes and the pin is left idle. A second
+/// signal ends the process right away, for a readout that hangs.
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+}
+
+impl Shutdown {
+    pub fn register() -> Result<Self, StationError> {
+        let requested = Arc::new(AtomicBool::new(false));
+        for signal in [SIGINT, SIGTERM] {
+            // Registered first, so it only fires when the flag was already set.
+            flag::register_conditional_shutdown(signal, 1, Arc::clone(&requested))?;
+            flag::register(signal, Arc::clone(&requested))?;
+        }
+        Ok(Shutdown { requested })
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    /// Sleeps for `duration` unless a shutdown is requested earlier.
+    pub fn sleep(&self, duration: Duration) {
+        let deadline = Instant::now() + duration;
+        while !self.is_requested() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            thread::sleep(remaining.min(POLL_PERIOD));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signal_interrupts_sleep() {
+        let shutdown = Shutdown::register().unwrap();
+        assert!(!shutdown.is_requested());
+        signal_hook::low_level::raise(SIGTERM).unwrap();
+        assert!(shutdown.is_requested());
+
+        let start = Instant::now();
+        shutdown.sleep(Duration::from_secs(60));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
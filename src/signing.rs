@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use crate::cli::VerifyArgs;
+use crate::config::StationConfig;
+use crate::error::StationError;
+
+const ALGORITHM: &str = "ed25519";
+
+/// Detached signature stored next to the signed file as `<file>.sig`.
+#[derive(Serialize, Deserialize)]
+struct SignatureFile {
+    algorithm: String,
+    public_key: String,
+    signature: String,
+}
+
+fn parse_public_key(text: &str) -> Option<VerifyingKey> {
+    let mut bytes = [0; 32];
+    hex::decode_to_slice(text.trim(), &mut bytes).ok()?;
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".sig");
+    PathBuf::from(name)
+}
+
+/// Signs exports and finished CSV log files with the station's ed25519 key.
+pub struct ArchiveSigner {
+    key: SigningKey,
+}
+
+impl ArchiveSigner {
+    /// `key_file` holds the 32 byte secret key as 64 hex digits.
+    pub fn load(key_file: &str) -> Result<Self, StationError> {
+        let text = fs::read_to_string(key_file)?;
+        let mut seed = [0; 32];
+        hex::decode_to_slice(text.trim(), &mut seed).map_err(|_| StationError::Config(format!("signing.key_file {} must hold 64 hex digits", key_file)))?;
+        Ok(ArchiveSigner { key: SigningKey::from_bytes(&seed) })
+    }
+
+    pub fn public_key(&self) -> String {
+        hex::encode(self.key.verifying_key().as_bytes())
+    }
+
+    /// Writes the signature of `path` to `<path>.sig`.
+    pub fn sign_file(&self, path: &Path) -> Result<(), StationError> {
+        let signature = self.key.sign(&fs::read(path)?);
+        let file = SignatureFile { algorithm: ALGORITHM.to_string(), public_key: self.public_key(), signature: hex::encode(signature.to_bytes()) };
+        fs::write(signature_path(path), serde_json::to_string_pretty(&file).unwrap_or_default() + "\n")?;
+        Ok(())
+    }
+}
+
+/// Checks `path` against its `<path>.sig` and the trusted `public_key`.
+///
+/// # Returns
+/// Why the file is not authentic, `None` when it is.
+fn verify_file(path: &Path, public_key: &VerifyingKey) -> Option<String> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(error) => return Some(error.to_string()),
+    };
+    let file: SignatureFile = match fs::read_to_string(signature_path(path)).map(|text| serde_json::from_str(&text)) {
+        Ok(Ok(file)) => file,
+        Ok(Err(error)) => return Some(format!("malformed signature file: {}", error)),
+        Err(error) => return Some(format!("no signature file: {}", error)),
+    };
+    if file.algorithm != ALGORITHM {
+        return Some(format!("unsupported algorithm {}", file.algorithm));
+    }
+    if parse_public_key(&file.public_key).as_ref() != Some(public_key) {
+        return Some(format!("signed by another key ({})", file.public_key));
+    }
+    let mut signature = [0; 64];
+    if hex::decode_to_slice(file.signature.trim(), &mut signature).is_err() {
+        return Some("malformed signature".to_string());
+    }
+    let signature = Signature::from_bytes(&signature);
+    public_key.verify(&content, &signature).err().map(|_| "content does not match the signature".to_string())
+}
+
+/// Verifies every file of `args` against `--public-key`, or the public half of
+/// the configured signing key.
+///
+/// # Returns
+/// Whether every file is authentic.
+pub fn verify(config: &StationConfig, args: &VerifyArgs) -> Result<bool, StationError> {
+    let public_key = match (&args.public_key, &config.signing_key_file) {
+        (Some(text), _) => parse_public_key(text).ok_or_else(|| StationError::Config(format!("--public-key {} is not an ed25519 public key", text)))?,
+        (None, Some(key_file)) => ArchiveSigner::load(key_file)?.key.verifying_key(),
+        (None, None) => return Err(StationError::Config("pass --public-key or set signing.key_file".to_string())),
+    };
+    let mut authentic = true;
+    for path in &args.files {
+        match verify_file(path, &public_key) {
+            None => println!("{}: ok", path.display()),
+            Some(problem) => {
+                println!("{}: FAILED {}", path.display(), problem);
+                authentic = false;
+            }
+        }
+    }
+    Ok(authentic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn signed_files_verify_until_changed() {
+        let directory = env::temp_dir().join(format!("weather_station_signing_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let key_file = directory.join("signing.key");
+        fs::write(&key_file, format!("{}\n", "4f".repeat(32))).unwrap();
+        let signer = ArchiveSigner::load(key_file.to_str().unwrap()).unwrap();
+        let public_key = parse_public_key(&signer.public_key()).unwrap();
+        let archive = directory.join("readings-2024-05-01.csv");
+        fs::write(&archive, "timestamp,sensor_id\n1714521600,dht11\n").unwrap();
+
+        assert!(verify_file(&archive, &public_key).unwrap().starts_with("no signature file"));
+        signer.sign_file(&archive).unwrap();
+        assert_eq!(verify_file(&archive, &public_key), None);
+        let other_key = SigningKey::from_bytes(&[7; 32]).verifying_key();
+        assert!(verify_file(&archive, &other_key).unwrap().starts_with("signed by another key"));
+        fs::write(&archive, "timestamp,sensor_id\n1714521600,dht22\n").unwrap();
+        assert_eq!(verify_file(&archive, &public_key).unwrap(), "content does not match the signature");
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn malformed_keys_are_rejected() {
+        let public_key = hex::encode(SigningKey::from_bytes(&[7; 32]).verifying_key().as_bytes());
+        assert!(parse_public_key(&format!(" {}\n", public_key)).is_some());
+        assert!(parse_public_key(&public_key[1..]).is_none());
+        assert!(parse_public_key(&format!("{}g", &public_key[1..])).is_none());
+        assert!(parse_public_key("00").is_none());
+    }
+}
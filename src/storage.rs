@@ -0,0 +1,334 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use rusqlite::{params, Connection, Row};
+use sensor::{Measurement, Quantity, SensorError};
+use serde::{Deserialize, Serialize};
+use crate::error::StationError;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Applied in order on startup, the database `user_version` records how many ran.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE readings (
+        id INTEGER PRIMARY KEY,
+        timestamp INTEGER NOT NULL,
+        sensor_id TEXT NOT NULL,
+        temperature REAL,
+        humidity REAL,
+        status TEXT NOT NULL
+    );
+    CREATE INDEX readings_timestamp ON readings (timestamp);",
+    "ALTER TABLE readings ADD COLUMN pressure REAL;",
+    "CREATE TABLE annotations (
+        id INTEGER PRIMARY KEY,
+        start INTEGER NOT NULL,
+        end INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        text TEXT NOT NULL
+    );",
+    "ALTER TABLE readings ADD COLUMN irradiance REAL;",
+    "ALTER TABLE readings ADD COLUMN snow_depth REAL;",
+    "ALTER TABLE readings ADD COLUMN leaf_wetness REAL;",
+];
+
+#[derive(Clone, Serialize)]
+pub struct Reading {
+    ///
+    /// # Unit
+    /// Seconds since the unix epoch.
+    pub timestamp: i64,
+    pub sensor_id: String,
+    pub temperature: Option<f64>,
+    pub humidity: Option<f64>,
+    pub status: String,
+    ///
+    /// # Unit
+    /// Hectopascals.
+    pub pressure: Option<f64>,
+    ///
+    /// # Unit
+    /// Watts per square meter.
+    pub irradiance: Option<f64>,
+    ///
+    /// # Unit
+    /// Centimeters.
+    pub snow_depth: Option<f64>,
+    ///
+    /// # Unit
+    /// Percents.
+    pub leaf_wetness: Option<f64>,
+}
+
+pub fn unix_time_now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO).as_secs() as i64
+}
+
+/// Proleptic Gregorian (year, month, day) of a day counted from the unix epoch.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = if days >= 0 { days } else { days - 146096 } / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+impl Reading {
+    pub fn new(sensor_id: &str, result: &Result<Measurement, SensorError>) -> Self {
+        let status = match result {
+            Ok(_) => "ok",
+            Err(error) => error.kind.status(),
+        };
+        let value = |quantity| result.as_ref().ok().and_then(|data| data.get(quantity));
+
+        Reading {
+            timestamp: unix_time_now(),
+            sensor_id: sensor_id.to_string(),
+            temperature: value(Quantity::Temperature),
+            humidity: value(Quantity::RelativeHumidity),
+            status: status.to_string(),
+            pressure: value(Quantity::Pressure),
+            irradiance: value(Quantity::Irradiance),
+            snow_depth: value(Quantity::SnowDepth),
+            leaf_wetness: value(Quantity::LeafWetness),
+        }
+    }
+
+    pub fn value(&self, quantity: Quantity) -> Option<f64> {
+        match quantity {
+            Quantity::Temperature => self.temperature,
+            Quantity::RelativeHumidity => self.humidity,
+            Quantity::Pressure => self.pressure,
+            Quantity::Irradiance => self.irradiance,
+            Quantity::SnowDepth => self.snow_depth,
+            Quantity::LeafWetness => self.leaf_wetness,
+        }
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Reading {
+            timestamp: row.get(0)?,
+            sensor_id: row.get(1)?,
+            temperature: row.get(2)?,
+            humidity: row.get(3)?,
+            status: row.get(4)?,
+            pressure: row.get(5)?,
+            irradiance: row.get(6)?,
+            snow_depth: row.get(7)?,
+            leaf_wetness: row.get(8)?,
+        })
+    }
+}
+
+/// A time range charts explain, e.g. a maintenance window during which a sensor
+/// was unplugged.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Annotation {
+    ///
+    /// # Unit
+    /// Seconds since the unix epoch.
+    pub start: i64,
+    ///
+    /// # Unit
+    /// Seconds since the unix epoch, `start` for a single instant.
+    pub end: i64,
+    /// Free-form category, e.g. `maintenance`.
+    pub kind: String,
+    pub text: String,
+}
+
+/// Each migration commits together with its `user_version`, so a crash or power
+/// loss never leaves one applied but unrecorded, to fail when it is run again.
+fn migrate(connection: &mut Connection, migrations: &[&str]) -> Result<(), StationError> {
+    let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (index, migration) in migrations.iter().enumerate().skip(version) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.execute_batch(&format!("PRAGMA user_version = {}", index + 1))?;
+        transaction.commit()?;
+    }
+    Ok(())
+}
+
+const SELECT_READINGS: &str = "SELECT timestamp, sensor_id, temperature, humidity, status, pressure, irradiance, snow_depth, leaf_wetness FROM readings";
+
+pub struct Storage {
+    connection: Connection,
+}
+
+impl Storage {
+    /// Opens or creates the database at `path` and migrates it to the current schema.
+    pub fn open(path: &str) -> Result<Self, StationError> {
+        let mut connection = Connection::open(path)?;
+        migrate(&mut connection, MIGRATIONS)?;
+        Ok(Storage { connection })
+    }
+
+    pub fn insert_reading(&self, reading: &Reading) -> Result<(), StationError> {
+        self.connection.execute(
+            "INSERT INTO readings (timestamp, sensor_id, temperature, humidity, status, pressure, irradiance, snow_depth, leaf_wetness) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                reading.timestamp,
+                reading.sensor_id,
+                reading.temperature,
+                reading.humidity,
+                reading.status,
+                reading.pressure,
+                reading.irradiance,
+                reading.snow_depth,
+                reading.leaf_wetness
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn latest_reading(&self) -> Result<Option<Reading>, StationError> {
+        let mut statement = self.connection.prepare(&format!("{} ORDER BY timestamp DESC, id DESC LIMIT 1", SELECT_READINGS))?;
+        let mut rows = statement.query_map([], Reading::from_row)?;
+        Ok(rows.next().transpose()?)
+    }
+
+    /// Latest reading of every sensor, by sensor id.
+    pub fn latest_reading_per_sensor(&self) -> Result<Vec<Reading>, StationError> {
+        let mut statement = self.connection.prepare(&format!(
+            "{} WHERE id IN (SELECT MAX(id) FROM readings GROUP BY sensor_id) ORDER BY sensor_id",
+            SELECT_READINGS
+        ))?;
+        let readings = statement.query_map([], Reading::from_row)?.collect::<Result<_, _>>()?;
+        Ok(readings)
+    }
+
+    /// Readings with `from <= timestamp <= to`, oldest first.
+    pub fn readings_between(&self, from: i64, to: i64) -> Result<Vec<Reading>, StationError> {
+        let mut statement =
+            self.connection.prepare(&format!("{} WHERE timestamp BETWEEN ?1 AND ?2 ORDER BY timestamp, id", SELECT_READINGS))?;
+        let readings = statement.query_map(params![from, to], Reading::from_row)?.collect::<Result<_, _>>()?;
+        Ok(readings)
+    }
+
+    /// Closes the database, reporting what dropping the connection would swallow.
+    pub fn close(self) -> Result<(), StationError> {
+        self.connection.close().map_err(|(_, error)| StationError::Storage(error))
+    }
+
+    pub fn insert_annotation(&self, annotation: &Annotation) -> Result<(), StationError> {
+        self.connection.execute(
+            "INSERT INTO annotations (start, end, kind, text) VALUES (?1, ?2, ?3, ?4)",
+            params![annotation.start, annotation.end, annotation.kind, annotation.text],
+        )?;
+        Ok(())
+    }
+
+    /// Annotations overlapping `from..=to`, oldest first.
+    pub fn annotations_between(&self, from: i64, to: i64) -> Result<Vec<Annotation>, StationError> {
+        let mut statement = self.connection.prepare("SELECT start, end, kind, text FROM annotations WHERE end >= ?1 AND start <= ?2 ORDER BY start, id")?;
+        let annotations = statement
+            .query_map(params![from, to], |row| Ok(Annotation { start: row.get(0)?, end: row.get(1)?, kind: row.get(2)?, text: row.get(3)? }))?
+            .collect::<Result<_, _>>()?;
+        Ok(annotations)
+    }
+
+    /// Annotations that ended before the cutoff go with the readings they explain.
+    ///
+    /// # Returns
+    /// Number of deleted readings.
+    pub fn prune(&self, retention_days: u32, now: i64) -> Result<usize, StationError> {
+        let cutoff = now - retention_days as i64 * SECONDS_PER_DAY;
+        self.connection.execute("DELETE FROM annotations WHERE end < ?1", params![cutoff])?;
+        Ok(self.connection.execute("DELETE FROM readings WHERE timestamp < ?1", params![cutoff])?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sensor::SensorErrorKind;
+    use super::*;
+
+    fn reading(timestamp: i64) -> Reading {
+        Reading { timestamp, sensor_id: "dht11".to_string(), temperature: Some(21.5), humidity: Some(40.0), status: "ok".to_string(), pressure: None, irradiance: None, snow_depth: None, leaf_wetness: None }
+    }
+
+    fn count(storage: &Storage) -> i64 {
+        storage.connection.query_row("SELECT COUNT(*) FROM readings", [], |row| row.get(0)).unwrap()
+    }
+
+    fn user_version(connection: &Connection) -> usize {
+        connection.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19844), (2024, 5, 1));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+    }
+
+    #[test]
+    fn migrations_are_applied_once() {
+        let mut storage = Storage::open(":memory:").unwrap();
+        migrate(&mut storage.connection, MIGRATIONS).unwrap();
+        assert_eq!(user_version(&storage.connection), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn interrupted_migration_is_rolled_back_and_rerun() {
+        let path = std::env::temp_dir().join(format!("weather_station_migrate_{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut connection = Connection::open(&path).unwrap();
+        // The second migration dies after its first statement, like a crash would.
+        let broken = [MIGRATIONS[0], "ALTER TABLE readings ADD COLUMN pressure REAL; INSERT INTO missing VALUES (1);"];
+        assert!(migrate(&mut connection, &broken).is_err());
+        assert_eq!(user_version(&connection), 1);
+        drop(connection);
+
+        let storage = Storage::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(user_version(&storage.connection), MIGRATIONS.len());
+        storage.insert_reading(&Reading { timestamp: 1, sensor_id: "outdoor".to_string(), temperature: None, humidity: None, status: "ok".to_string(), pressure: Some(1013.0), irradiance: None, snow_depth: None, leaf_wetness: None }).unwrap();
+        storage.close().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn prune_removes_only_expired_readings() {
+        let storage = Storage::open(":memory:").unwrap();
+        let now = 100 * SECONDS_PER_DAY;
+        storage.insert_reading(&reading(now - 31 * SECONDS_PER_DAY)).unwrap();
+        storage.insert_reading(&reading(now - SECONDS_PER_DAY)).unwrap();
+        storage.insert_reading(&Reading::new("dht11", &Err(SensorError::new(SensorErrorKind::Timeout, "no answer")))).unwrap();
+
+        assert_eq!(storage.prune(30, now).unwrap(), 1);
+        assert_eq!(count(&storage), 2);
+    }
+
+    #[test]
+    fn queries_history() {
+        let storage = Storage::open(":memory:").unwrap();
+        for timestamp in [30, 10, 20] {
+            storage.insert_reading(&reading(timestamp)).unwrap();
+        }
+
+        assert_eq!(storage.latest_reading().unwrap().unwrap().timestamp, 30);
+        let timestamps: Vec<i64> = storage.readings_between(10, 20).unwrap().iter().map(|reading| reading.timestamp).collect();
+        assert_eq!(timestamps, [10, 20]);
+
+        storage.insert_reading(&Reading { sensor_id: "attic".to_string(), ..reading(5) }).unwrap();
+        let latest: Vec<(String, i64)> = storage.latest_reading_per_sensor().unwrap().into_iter().map(|reading| (reading.sensor_id, reading.timestamp)).collect();
+        assert_eq!(latest, [("attic".to_string(), 5), ("dht11".to_string(), 20)]);
+    }
+
+    #[test]
+    fn annotations_overlapping_the_range_are_returned() {
+        let storage = Storage::open(":memory:").unwrap();
+        let annotation = |start, end| Annotation { start, end, kind: "maintenance".to_string(), text: "sensor cleaned".to_string() };
+        for (start, end) in [(0, 5), (8, 12), (30, 30)] {
+            storage.insert_annotation(&annotation(start, end)).unwrap();
+        }
+
+        assert_eq!(storage.annotations_between(10, 30).unwrap(), [annotation(8, 12), annotation(30, 30)]);
+        storage.prune(0, 10).unwrap();
+        assert_eq!(storage.annotations_between(0, 100).unwrap().len(), 2);
+    }
+}
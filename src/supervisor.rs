@@ -0,0 +1,68 @@
+//! Restarts background subsystems that fail, so a crashed HTTP API or MQTT
+//! connection never takes sampling down with it.
+
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+use crate::error::StationError;
+
+const FIRST_RESTART_DELAY: Duration = Duration::from_secs(1);
+/// Restarts back off up to this, a subsystem that ran this long is considered
+/// healthy again and the next restart comes quickly.
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+
+/// Runs `task` again whenever it fails or panics. Returns once `task` returns
+/// `Ok`, which is how a subsystem stops for good.
+fn supervise<F>(name: &str, first_delay: Duration, mut task: F) -> u32
+where
+    F: FnMut() -> Result<(), StationError>,
+{
+    let mut restarts = 0;
+    let mut delay = first_delay;
+    loop {
+        let started = Instant::now();
+        match panic::catch_unwind(AssertUnwindSafe(&mut task)) {
+            Ok(Ok(())) => return restarts,
+            Ok(Err(error)) => error!(subsystem = name, %error, "subsystem failed"),
+            Err(_) => error!(subsystem = name, "subsystem panicked"),
+        }
+        if started.elapsed() >= MAX_RESTART_DELAY {
+            delay = first_delay;
+        }
+        thread::sleep(delay);
+        delay = (delay * 2).min(MAX_RESTART_DELAY);
+        restarts += 1;
+        info!(subsystem = name, restarts, "restarting subsystem");
+    }
+}
+
+/// Runs `task` on a thread named `name` under supervision.
+pub fn spawn<F>(name: &'static str, task: F) -> io::Result<JoinHandle<()>>
+where
+    F: FnMut() -> Result<(), StationError> + Send + 'static,
+{
+    thread::Builder::new().name(name.to_string()).spawn(move || {
+        supervise(name, FIRST_RESTART_DELAY, task);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_and_panicked_tasks_are_restarted() {
+        let mut runs = 0;
+        let restarts = supervise("test", Duration::ZERO, || {
+            runs += 1;
+            match runs {
+                1 => Err(StationError::Config("broken".to_string())),
+                2 => panic!("crashed"),
+                _ => Ok(()),
+            }
+        });
+        assert_eq!((runs, restarts), (3, 2));
+    }
+}
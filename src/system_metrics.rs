@@ -0,0 +1,44 @@
+use std::fs;
+use crate::diagnostics;
+use crate::precision::CPU_TEMPERATURE_PRECISION;
+
+const CPU_TEMPERATURE_PATH: &str = "/sys/class/thermal/thermal_zone0/temp";
+
+pub struct SystemMetrics {
+    ///
+    /// # Unit
+    /// Celcius degrees.
+    pub cpu_temperature: Option<f64>,
+
+    /// Raw firmware throttling flags, see `diagnostics::throttled_problems`.
+    pub throttled: Option<u32>,
+}
+
+fn read_cpu_temperature() -> Option<f64> {
+    let millidegrees: f64 = fs::read_to_string(CPU_TEMPERATURE_PATH).ok()?.trim().parse().ok()?;
+    Some(millidegrees / 1000.0)
+}
+
+impl SystemMetrics {
+    pub fn read() -> Self {
+        SystemMetrics {
+            cpu_temperature: read_cpu_temperature(),
+            throttled: diagnostics::read_throttled_flags(),
+        }
+    }
+
+    pub fn print(&self) {
+        if let Some(temperature) = self.cpu_temperature {
+            println!("CPU temperature: {}*C", CPU_TEMPERATURE_PRECISION.format(temperature));
+        }
+
+        if let Some(flags) = self.throttled {
+            let problems = diagnostics::throttled_problems(flags);
+            if problems.is_empty() {
+                println!("Throttled: 0x{:x}", flags);
+            } else {
+                println!("Throttled: 0x{:x} ({})", flags, problems.join(", "));
+            }
+        }
+    }
+}
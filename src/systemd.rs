@@ -0,0 +1,119 @@
+//! The sd_notify protocol, spoken directly over the socket systemd passes in
+//! `NOTIFY_SOCKET`, so no libsystemd is needed.
+
+use std::env;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::time::Duration;
+use sensor::Quantity;
+use tracing::warn;
+use crate::precision::Precision;
+use crate::storage::Reading;
+
+/// Cycles without a successful readout before systemd restarts the station.
+const WATCHDOG_CYCLES: u32 = 3;
+/// Headroom for the readouts themselves, DHT11 retries take seconds.
+const WATCHDOG_SLACK: Duration = Duration::from_secs(30);
+
+/// Reports the station's state to systemd when it runs as a `Type=notify`
+/// service, does nothing otherwise.
+pub struct Notifier {
+    target: Option<(UnixDatagram, SocketAddr)>,
+}
+
+impl Notifier {
+    pub fn from_env() -> Self {
+        let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+            return Notifier { target: None };
+        };
+        match Notifier::connect(&path.to_string_lossy()) {
+            Ok(notifier) => notifier,
+            Err(error) => {
+                warn!(%error, "NOTIFY_SOCKET is unusable, not notifying systemd");
+                Notifier { target: None }
+            }
+        }
+    }
+
+    /// `path` starting with `@` names a socket in the abstract namespace.
+    fn connect(path: &str) -> std::io::Result<Self> {
+        let address = match path.strip_prefix('@') {
+            Some(name) => SocketAddr::from_abstract_name(name)?,
+            None => SocketAddr::from_pathname(path)?,
+        };
+        Ok(Notifier { target: Some((UnixDatagram::unbound()?, address)) })
+    }
+
+    fn send(&self, state: &str) {
+        if let Some((socket, address)) = &self.target {
+            if let Err(error) = socket.send_to_addr(state.as_bytes(), address) {
+                warn!(%error, "notifying systemd failed");
+            }
+        }
+    }
+
+    /// Initialization finished. The watchdog timeout is set from `interval`,
+    /// overriding the unit's `WatchdogSec`.
+    pub fn ready(&self, interval: Duration) {
+        let timeout = interval * WATCHDOG_CYCLES + WATCHDOG_SLACK;
+        self.send(&format!("READY=1\nWATCHDOG_USEC={}\nSTATUS=Waiting for the first readout", timeout.as_micros()));
+    }
+
+    /// Heartbeat after a cycle with at least one successful readout, `status`
+    /// shows up in `systemctl status`.
+    pub fn cycle_completed(&self, status: &str) {
+        self.send(&format!("WATCHDOG=1\nSTATUS={}", status));
+    }
+
+    pub fn status(&self, status: &str) {
+        self.send(&format!("STATUS={}", status));
+    }
+
+    pub fn stopping(&self) {
+        self.send("STOPPING=1");
+    }
+}
+
+/// One line summary of a cycle, e.g. `outdoor 21.5 °C 40 %, soil bus_error`.
+pub fn cycle_status(readings: &[Reading]) -> String {
+    let describe = |reading: &Reading| {
+        if reading.status != "ok" {
+            return format!("{} {}", reading.sensor_id, reading.status);
+        }
+        let mut text = reading.sensor_id.clone();
+        for quantity in Quantity::ALL {
+            if let Some(value) = reading.value(quantity) {
+                text.push_str(&format!(" {} {}", Precision::of(quantity).format(value), quantity.unit()));
+            }
+        }
+        text
+    };
+    readings.iter().map(describe).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notifications_reach_the_socket() {
+        let path = env::temp_dir().join(format!("weather_station_notify_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let systemd = UnixDatagram::bind(&path).unwrap();
+        let notifier = Notifier::connect(path.to_str().unwrap()).unwrap();
+
+        notifier.ready(Duration::from_secs(60));
+        let mut buffer = [0; 256];
+        let length = systemd.recv(&mut buffer).unwrap();
+        assert_eq!(std::str::from_utf8(&buffer[..length]).unwrap(), "READY=1\nWATCHDOG_USEC=210000000\nSTATUS=Waiting for the first readout");
+
+        let readings = [
+            Reading { timestamp: 0, sensor_id: "outdoor".to_string(), temperature: Some(21.53), humidity: Some(40.0), status: "ok".to_string(), pressure: None, irradiance: None, snow_depth: None, leaf_wetness: None },
+            Reading { timestamp: 0, sensor_id: "soil".to_string(), temperature: None, humidity: None, status: "bus_error".to_string(), pressure: None, irradiance: None, snow_depth: None, leaf_wetness: None },
+        ];
+        notifier.cycle_completed(&cycle_status(&readings));
+        let length = systemd.recv(&mut buffer).unwrap();
+        assert_eq!(std::str::from_utf8(&buffer[..length]).unwrap(), "WATCHDOG=1\nSTATUS=outdoor 21.5 °C 40 %, soil bus_error");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,45 @@
+/// Temperature unit a sink publishes with. Readings stay in Celsius inside the
+/// station and are only converted at the sink boundary.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "celsius" | "c" => Some(TemperatureUnit::Celsius),
+            "fahrenheit" | "f" => Some(TemperatureUnit::Fahrenheit),
+            _ => None,
+        }
+    }
+
+    /// Converts a Celsius temperature into this unit.
+    pub fn convert(&self, celsius: f64) -> f64 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fahrenheit_conversion() {
+        assert_eq!(TemperatureUnit::parse("fahrenheit"), Some(TemperatureUnit::Fahrenheit));
+        assert_eq!(TemperatureUnit::Fahrenheit.convert(-40.0), -40.0);
+        assert_eq!(TemperatureUnit::Fahrenheit.convert(21.5), 70.7);
+        assert_eq!(TemperatureUnit::Celsius.convert(21.5), 21.5);
+    }
+}
@@ -0,0 +1,194 @@
+const ULTRASONIC_TRIGGER_PULSE_US: u32 = 10;
+const ULTRASONIC_SETTLE_US: u32 = 2;
+const ULTRASONIC_ECHO_TIMEOUT_US: u32 = 40 * 1000;
+const ULTRASONIC_SAMPLE_INTERVAL_US: u32 = 60 * 1000;
+
+/// Number of echoes taken for a single filtered readout.
+pub const ULTRASONIC_SAMPLES: usize = 5;
+
+pub trait UltrasonicPin {
+    fn is_low(&mut self) -> bool;
+    fn is_high(&mut self) -> bool;
+    fn set_low(&mut self);
+    fn set_high(&mut self);
+}
+
+pub trait UltrasonicTiming {
+    fn wait(&self, microseconds: u32);
+
+    /// # Returns
+    /// Current time in microseconds
+    fn get_time_us(&self) -> u128;
+}
+
+#[derive(Debug)]
+pub enum UltrasonicError {
+    Timeout,
+    NoEcho,
+}
+
+pub struct UltrasonicReadout {
+    ///
+    /// # Unit
+    /// Meters.
+    pub distance: f64,
+}
+
+pub struct SnowDepthGauge {
+    ///
+    /// # Unit
+    /// Meters. Distance from the sensor to bare ground.
+    pub baseline: f64,
+}
+
+impl SnowDepthGauge {
+    pub fn new(baseline: f64) -> Self {
+        SnowDepthGauge { baseline }
+    }
+
+    ///
+    /// # Returns
+    /// Snow depth in meters, never negative.
+    pub fn snow_depth(&self, readout: &UltrasonicReadout) -> f64 {
+        (self.baseline - readout.distance).max(0.0)
+    }
+}
+
+///
+/// # Parameters
+/// temperature = Celcius degrees
+///
+/// # Returns
+/// Speed of sound in dry air in meters per second.
+pub fn speed_of_sound(temperature: f64) -> f64 {
+    331.3 * (1.0 + temperature / 273.15).sqrt()
+}
+
+///
+/// # Parameters
+/// time = microseconds, round trip
+///
+/// # Returns
+/// Distance in meters.
+fn convert_time_to_distance(time: u128, temperature: f64) -> f64 {
+    time as f64 / 1_000_000.0 * speed_of_sound(temperature) / 2.0
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    assert!(!values.is_empty());
+
+    values.sort_by(|a, b| a.total_cmp(b));
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+fn wait_for_level(
+    level: bool,
+    pin: &mut dyn UltrasonicPin,
+    timing: &dyn UltrasonicTiming,
+) -> Result<(), UltrasonicError> {
+    let timeout = timing.get_time_us() + ULTRASONIC_ECHO_TIMEOUT_US as u128;
+    loop {
+        if level && pin.is_high() || !level && pin.is_low() {
+            return Ok(());
+        }
+
+        if timing.get_time_us() > timeout {
+            return Err(if level { UltrasonicError::NoEcho } else { UltrasonicError::Timeout });
+        }
+    }
+}
+
+/// Sends a single trigger pulse and measures the echo.
+///
+/// # Returns
+/// Round trip time of the echo in microseconds.
+pub fn ultrasonic_measure_echo(
+    trigger: &mut dyn UltrasonicPin,
+    echo: &mut dyn UltrasonicPin,
+    timing: &dyn UltrasonicTiming,
+) -> Result<u128, UltrasonicError> {
+    trigger.set_low();
+    timing.wait(ULTRASONIC_SETTLE_US);
+    trigger.set_high();
+    timing.wait(ULTRASONIC_TRIGGER_PULSE_US);
+    trigger.set_low();
+
+    wait_for_level(true, echo, timing)?;
+    let start_time = timing.get_time_us();
+    wait_for_level(false, echo, timing)?;
+    Ok(timing.get_time_us() - start_time)
+}
+
+/// Takes `ULTRASONIC_SAMPLES` echoes and returns the median distance, which rejects
+/// the spurious short and long echoes these sensors are known for.
+///
+/// # Parameters
+/// temperature = Celcius degrees, used to compensate the speed of sound
+pub fn ultrasonic_perform_readout(
+    trigger: &mut dyn UltrasonicPin,
+    echo: &mut dyn UltrasonicPin,
+    timing: &dyn UltrasonicTiming,
+    temperature: f64,
+) -> Result<UltrasonicReadout, UltrasonicError> {
+    let mut distances: [f64; ULTRASONIC_SAMPLES] = [0.0; ULTRASONIC_SAMPLES];
+    let mut valid: usize = 0;
+    let mut last_error = UltrasonicError::NoEcho;
+
+    for _ in 0..ULTRASONIC_SAMPLES {
+        match ultrasonic_measure_echo(trigger, echo, timing) {
+            Ok(time) => {
+                distances[valid] = convert_time_to_distance(time, temperature);
+                valid += 1;
+            }
+            Err(error) => last_error = error,
+        }
+        timing.wait(ULTRASONIC_SAMPLE_INTERVAL_US);
+    }
+
+    if valid == 0 {
+        return Err(last_error);
+    }
+
+    Ok(UltrasonicReadout {
+        distance: median(&mut distances[..valid]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speed_of_sound_is_temperature_compensated() {
+        assert!((speed_of_sound(0.0) - 331.3).abs() < 1e-9);
+        assert!((speed_of_sound(20.0) - 343.2).abs() < 0.1);
+        assert!(speed_of_sound(-20.0) < speed_of_sound(0.0));
+    }
+
+    #[test]
+    fn echo_time_to_distance() {
+        let distance = convert_time_to_distance(5831, 20.0);
+        assert!((distance - 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn median_rejects_outliers() {
+        let mut odd = [1.02, 0.2, 1.01, 4.0, 1.0];
+        assert_eq!(median(&mut odd), 1.01);
+
+        let mut even = [1.0, 3.0, 2.0, 100.0];
+        assert_eq!(median(&mut even), 2.5);
+    }
+
+    #[test]
+    fn snow_depth_against_baseline() {
+        let gauge = SnowDepthGauge::new(1.5);
+        assert!((gauge.snow_depth(&UltrasonicReadout { distance: 1.2 }) - 0.3).abs() < 1e-9);
+        assert_eq!(gauge.snow_depth(&UltrasonicReadout { distance: 1.6 }), 0.0);
+    }
+}
@@ -0,0 +1,344 @@
+const MAGNUS_A: f64 = 17.62;
+const MAGNUS_B: f64 = 243.12;
+const METERS_PER_SECOND_TO_MPH: f64 = 2.236_936;
+const SOLAR_CONSTANT: f64 = 0.0820;
+const STEFAN_BOLTZMANN_DAILY: f64 = 4.903e-9;
+/// Converts MJ m-2 day-1 of radiation into mm day-1 of evaporated water.
+const RADIATION_TO_EVAPORATION: f64 = 0.408;
+
+fn celcius_to_fahrenheit(temperature: f64) -> f64 {
+    temperature * 9.0 / 5.0 + 32.0
+}
+
+fn fahrenheit_to_celcius(temperature: f64) -> f64 {
+    (temperature - 32.0) * 5.0 / 9.0
+}
+
+///
+/// # Parameters
+/// temperature = Celcius degrees, humidity = percents
+///
+/// # Returns
+/// Dew point in Celcius degrees (Magnus formula).
+pub fn dew_point(temperature: f64, humidity: f64) -> f64 {
+    let gamma = (humidity / 100.0).ln() + MAGNUS_A * temperature / (MAGNUS_B + temperature);
+    MAGNUS_B * gamma / (MAGNUS_A - gamma)
+}
+
+///
+/// # Parameters
+/// temperature = Celcius degrees, humidity = percents
+///
+/// # Returns
+/// Heat index in Celcius degrees (NWS Rothfusz regression with adjustments).
+pub fn heat_index(temperature: f64, humidity: f64) -> f64 {
+    fahrenheit_to_celcius(heat_index_fahrenheit(celcius_to_fahrenheit(temperature), humidity))
+}
+
+fn heat_index_fahrenheit(t: f64, rh: f64) -> f64 {
+    let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+    if (simple + t) / 2.0 < 80.0 {
+        return simple;
+    }
+
+    let mut index = -42.379 + 2.049_015_23 * t + 10.143_331_27 * rh
+        - 0.224_755_41 * t * rh
+        - 0.006_837_83 * t * t
+        - 0.054_817_17 * rh * rh
+        + 0.001_228_74 * t * t * rh
+        + 0.000_852_82 * t * rh * rh
+        - 0.000_001_99 * t * t * rh * rh;
+
+    if rh < 13.0 && (80.0..=112.0).contains(&t) {
+        index -= (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+    } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+        index += (rh - 85.0) / 10.0 * ((87.0 - t) / 5.0);
+    }
+    index
+}
+
+///
+/// # Parameters
+/// temperature = Celcius degrees, humidity = percents
+///
+/// # Returns
+/// Canadian humidex, dimensionless but read like Celcius degrees.
+pub fn humidex(temperature: f64, humidity: f64) -> f64 {
+    let dew_point_kelvin = dew_point(temperature, humidity) + 273.15;
+    let vapour_pressure = 6.11 * (5417.7530 * (1.0 / 273.16 - 1.0 / dew_point_kelvin)).exp();
+    temperature + 0.5555 * (vapour_pressure - 10.0)
+}
+
+///
+/// # Parameters
+/// temperature = Celcius degrees, humidity = percents, wind_speed = meters per second
+///
+/// # Returns
+/// Temperature-Humidity-Wind index in Celcius degrees: the heat index lowered
+/// by 1.072 °F per mph of wind.
+pub fn thw_index(temperature: f64, humidity: f64, wind_speed: f64) -> f64 {
+    let heat_index = heat_index_fahrenheit(celcius_to_fahrenheit(temperature), humidity);
+    fahrenheit_to_celcius(heat_index - 1.072 * wind_speed * METERS_PER_SECOND_TO_MPH)
+}
+
+///
+/// # Parameters
+/// temperature = Celcius degrees, humidity = percents
+///
+/// # Returns
+/// Absolute humidity in grams of water vapour per cubic meter.
+pub fn absolute_humidity(temperature: f64, humidity: f64) -> f64 {
+    let saturation_pressure = 6.112 * (17.67 * temperature / (temperature + 243.5)).exp();
+    saturation_pressure * humidity * 2.1674 / (273.15 + temperature)
+}
+
+/// Metrics derived from a temperature and relative humidity readout.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeteoDerived {
+    ///
+    /// # Unit
+    /// Celcius degrees.
+    pub dew_point: f64,
+    pub heat_index: f64,
+
+    ///
+    /// # Unit
+    /// g/m³.
+    pub absolute_humidity: f64,
+}
+
+impl MeteoDerived {
+    ///
+    /// # Parameters
+    /// temperature = Celcius degrees, humidity = percents
+    pub fn compute(temperature: f64, humidity: f64) -> Self {
+        MeteoDerived {
+            dew_point: dew_point(temperature, humidity),
+            heat_index: heat_index(temperature, humidity),
+            absolute_humidity: absolute_humidity(temperature, humidity),
+        }
+    }
+}
+
+/// Comfort index reported by a deployment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComfortIndex {
+    Humidex,
+    Thw,
+}
+
+impl ComfortIndex {
+    ///
+    /// # Parameters
+    /// temperature = Celcius degrees, humidity = percents, wind_speed = meters per second
+    pub fn compute(&self, temperature: f64, humidity: f64, wind_speed: f64) -> f64 {
+        match self {
+            ComfortIndex::Humidex => humidex(temperature, humidity),
+            ComfortIndex::Thw => thw_index(temperature, humidity, wind_speed),
+        }
+    }
+}
+
+/// Pressure change over three hours below which the barometer counts as steady,
+/// the Met Office's "rising/falling slowly" included.
+///
+/// # Unit
+/// hPa.
+const STEADY_PRESSURE_CHANGE: f64 = 1.6;
+
+/// Barometric tendency, what home weather stations base their forecast icon on:
+/// rising pressure brings fair weather, falling pressure unsettled weather.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PressureTendency {
+    Rising,
+    Steady,
+    Falling,
+}
+
+impl PressureTendency {
+    ///
+    /// # Parameters
+    /// change = hPa over three hours
+    pub fn from_change(change: f64) -> Self {
+        if change >= STEADY_PRESSURE_CHANGE {
+            PressureTendency::Rising
+        } else if change <= -STEADY_PRESSURE_CHANGE {
+            PressureTendency::Falling
+        } else {
+            PressureTendency::Steady
+        }
+    }
+}
+
+/// Daily aggregates needed for reference evapotranspiration. Only temperatures are
+/// mandatory, the other inputs enable the more accurate Penman-Monteith method.
+pub struct DailyWeather {
+    ///
+    /// # Unit
+    /// Celcius degrees.
+    pub temperature_min: f64,
+    pub temperature_max: f64,
+
+    ///
+    /// # Unit
+    /// Percents.
+    pub humidity_min: Option<f64>,
+    pub humidity_max: Option<f64>,
+
+    ///
+    /// # Unit
+    /// Meters per second, mean at 2 m height.
+    pub wind_speed: Option<f64>,
+
+    ///
+    /// # Unit
+    /// MJ m-2 day-1.
+    pub solar_radiation: Option<f64>,
+}
+
+/// FAO-56's substitute for an unmeasured wind speed, the global average at 2 m.
+///
+/// # Unit
+/// Meters per second.
+pub const DEFAULT_WIND_SPEED: f64 = 2.0;
+
+///
+/// # Unit
+/// kPa.
+fn saturation_vapour_pressure(temperature: f64) -> f64 {
+    0.6108 * (17.27 * temperature / (temperature + 237.3)).exp()
+}
+
+///
+/// # Parameters
+/// latitude = degrees, day_of_year = 1..=366
+///
+/// # Returns
+/// Extraterrestrial radiation in MJ m-2 day-1 (FAO-56 eq. 21).
+pub fn extraterrestrial_radiation(latitude: f64, day_of_year: u32) -> f64 {
+    let latitude = latitude.to_radians();
+    let year_angle = 2.0 * std::f64::consts::PI * day_of_year as f64 / 365.0;
+    let inverse_distance = 1.0 + 0.033 * year_angle.cos();
+    let declination = 0.409 * (year_angle - 1.39).sin();
+    let sunset_angle = (-latitude.tan() * declination.tan()).clamp(-1.0, 1.0).acos();
+
+    24.0 * 60.0 / std::f64::consts::PI * SOLAR_CONSTANT * inverse_distance
+        * (sunset_angle * latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * sunset_angle.sin())
+}
+
+///
+/// # Returns
+/// Reference evapotranspiration in mm day-1 from temperatures only (Hargreaves).
+pub fn et0_hargreaves(temperature_min: f64, temperature_max: f64, latitude: f64, day_of_year: u32) -> f64 {
+    let temperature_mean = (temperature_min + temperature_max) / 2.0;
+    let range = (temperature_max - temperature_min).max(0.0);
+    0.0023 * (temperature_mean + 17.8) * range.sqrt() * extraterrestrial_radiation(latitude, day_of_year) * RADIATION_TO_EVAPORATION
+}
+
+///
+/// # Parameters
+/// elevation = meters above sea level
+///
+/// # Returns
+/// Reference evapotranspiration in mm day-1 (FAO-56 Penman-Monteith), `None`
+/// when humidity, wind or solar radiation is missing.
+pub fn et0_penman_monteith(day: &DailyWeather, latitude: f64, elevation: f64, day_of_year: u32) -> Option<f64> {
+    let (humidity_min, humidity_max) = (day.humidity_min?, day.humidity_max?);
+    let (wind_speed, solar_radiation) = (day.wind_speed?, day.solar_radiation?);
+
+    let temperature_mean = (day.temperature_min + day.temperature_max) / 2.0;
+    let saturation = (saturation_vapour_pressure(day.temperature_max) + saturation_vapour_pressure(day.temperature_min)) / 2.0;
+    let actual = (saturation_vapour_pressure(day.temperature_min) * humidity_max / 100.0
+        + saturation_vapour_pressure(day.temperature_max) * humidity_min / 100.0)
+        / 2.0;
+    let slope = 4098.0 * saturation_vapour_pressure(temperature_mean) / (temperature_mean + 237.3).powi(2);
+    let pressure = 101.3 * ((293.0 - 0.0065 * elevation) / 293.0).powf(5.26);
+    let psychrometric = 0.000665 * pressure;
+
+    let clear_sky_radiation = (0.75 + 2e-5 * elevation) * extraterrestrial_radiation(latitude, day_of_year);
+    let net_shortwave = 0.77 * solar_radiation;
+    let kelvin_fourth = ((day.temperature_max + 273.16).powi(4) + (day.temperature_min + 273.16).powi(4)) / 2.0;
+    let relative_radiation = (solar_radiation / clear_sky_radiation).min(1.0);
+    let net_longwave = STEFAN_BOLTZMANN_DAILY * kelvin_fourth * (0.34 - 0.14 * actual.sqrt()) * (1.35 * relative_radiation - 0.35);
+    let net_radiation = net_shortwave - net_longwave;
+
+    let numerator = RADIATION_TO_EVAPORATION * slope * net_radiation
+        + psychrometric * 900.0 / (temperature_mean + 273.0) * wind_speed * (saturation - actual);
+    Some(numerator / (slope + psychrometric * (1.0 + 0.34 * wind_speed)))
+}
+
+///
+/// # Returns
+/// Reference evapotranspiration in mm day-1, Penman-Monteith when all inputs are
+/// available, Hargreaves otherwise.
+pub fn reference_evapotranspiration(day: &DailyWeather, latitude: f64, elevation: f64, day_of_year: u32) -> f64 {
+    et0_penman_monteith(day, latitude, elevation, day_of_year)
+        .unwrap_or_else(|| et0_hargreaves(day.temperature_min, day.temperature_max, latitude, day_of_year))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 0.05, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn dew_point_magnus() {
+        assert_close(dew_point(20.0, 50.0), 9.26);
+        assert_close(dew_point(15.0, 100.0), 15.0);
+    }
+
+    #[test]
+    fn heat_index_uses_regression_when_hot() {
+        assert_close(heat_index(32.0, 70.0), 40.41);
+        assert_close(heat_index(20.0, 50.0), 19.36);
+    }
+
+    #[test]
+    fn humidex_and_thw() {
+        assert_close(humidex(30.0, 70.0), 41.20);
+        assert_close(thw_index(32.0, 70.0, 0.0), heat_index(32.0, 70.0));
+        assert_close(ComfortIndex::Thw.compute(32.0, 70.0, 4.4704), 34.45);
+    }
+
+    #[test]
+    fn absolute_humidity_and_derived() {
+        assert_close(absolute_humidity(20.0, 50.0), 8.64);
+        assert_close(absolute_humidity(30.0, 80.0), 24.28);
+
+        let derived = MeteoDerived::compute(32.0, 70.0);
+        assert_close(derived.dew_point, dew_point(32.0, 70.0));
+        assert_close(derived.heat_index, 40.41);
+    }
+
+    #[test]
+    fn pressure_tendency_thresholds() {
+        assert_eq!(PressureTendency::from_change(2.0), PressureTendency::Rising);
+        assert_eq!(PressureTendency::from_change(-1.5), PressureTendency::Steady);
+        assert_eq!(PressureTendency::from_change(-1.6), PressureTendency::Falling);
+    }
+
+    #[test]
+    fn extraterrestrial_radiation_fao_example() {
+        assert_close(extraterrestrial_radiation(-20.0, 246), 32.2);
+    }
+
+    #[test]
+    fn evapotranspiration_falls_back_to_hargreaves() {
+        // FAO-56 example 18, Brussels on 6 July.
+        let mut day = DailyWeather {
+            temperature_min: 12.3,
+            temperature_max: 21.5,
+            humidity_min: Some(63.0),
+            humidity_max: Some(84.0),
+            wind_speed: Some(2.078),
+            solar_radiation: Some(22.07),
+        };
+        assert_close(reference_evapotranspiration(&day, 50.8, 100.0, 187), 3.88);
+
+        day.solar_radiation = None;
+        assert_close(reference_evapotranspiration(&day, 50.8, 100.0, 187), 4.06);
+    }
+}